@@ -6,6 +6,25 @@ pub const ROOT_VERSION: u64 = 1;
 /// Up to 1 million
 pub const MAJOR_VERSION: u64 = 1;
 /// Up to 1 million
-pub const MINOR_VERSION: u64 = 0;
+pub const MINOR_VERSION: u64 = 1;
 
-pub static VERSION: u64 = ROOT_VERSION * 1000_000_000_000 + MAJOR_VERSION * 1000_000 + MINOR_VERSION;
\ No newline at end of file
+pub static VERSION: u64 = ROOT_VERSION * 1000_000_000_000 + MAJOR_VERSION * 1000_000 + MINOR_VERSION;
+/// The first minor version with the authenticated handshake on every connection and the channel
+/// in `DvfMessage`. Nodes before it can't talk to the ones after it at all.
+pub const HANDSHAKE_MINOR_VERSION: u64 = 1;
+
+/// The oldest version this build still exchanges messages with. Minor releases keep the message
+/// formats backward compatible, so this only moves when a release drops support for older ones.
+pub static MIN_SUPPORTED_VERSION: u64 = ROOT_VERSION * 1000_000_000_000 + MAJOR_VERSION * 1000_000 + HANDSHAKE_MINOR_VERSION;
+/// The newest version this build exchanges messages with, i.e. the last minor version of its major one.
+pub static MAX_SUPPORTED_VERSION: u64 = ROOT_VERSION * 1000_000_000_000 + (MAJOR_VERSION + 1) * 1000_000 - 1;
+
+/// Human readable form of `version`, e.g. `v1.1.0`.
+pub fn version_string(version: u64) -> String {
+    format!(
+        "v{}.{}.{}",
+        version / 1000_000_000_000,
+        version / 1000_000 % 1000_000,
+        version % 1000_000
+    )
+}
@@ -12,7 +12,7 @@ use bytes::Bytes;
 use crypto::{Digest, PublicKey, SignatureService};
 use futures::SinkExt as _;
 use mempool::ConsensusMempoolMessage;
-use network::{MessageHandler, Writer};
+use network::{MessageHandler, PeerKey, Writer};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use store::Store;
@@ -85,7 +85,7 @@ impl Consensus {
             consensus_handler_map
                 .write()
                 .await
-                .insert(validator_id, ConsensusReceiverHandler{tx_consensus, tx_helper});
+                .insert(validator_id, ConsensusReceiverHandler{tx_consensus, tx_helper, committee: committee.clone()});
             info!("Insert consensus handler for validator: {}", validator_id);
         }
         
@@ -159,6 +159,7 @@ impl Consensus {
 pub struct ConsensusReceiverHandler {
     tx_consensus: MonitoredSender<ConsensusMessage>,
     tx_helper: MonitoredSender<(Digest, PublicKey)>,
+    committee: Committee,
 }
 
 #[async_trait]
@@ -189,4 +190,8 @@ impl MessageHandler for ConsensusReceiverHandler {
         }
         Ok(())
     }
+
+    fn accepts(&self, peer: &PeerKey) -> bool {
+        self.committee.authorities.contains_key(&PublicKey(*peer))
+    }
 }
@@ -3,7 +3,7 @@ use crate::consensus::ConsensusMessage;
 use bytes::Bytes;
 use crypto::{Digest, PublicKey};
 use log::{warn, debug};
-use network::{SimpleSender, DvfMessage, VERSION};
+use network::{SimpleSender, Channel, DvfMessage, VERSION};
 use store::Store;
 use tokio::sync::mpsc::Receiver;
 
@@ -69,7 +69,7 @@ impl Helper {
                             bincode::deserialize(&bytes).expect("Failed to deserialize our own block");
                         let message = bincode::serialize(&ConsensusMessage::Propose(block))
                             .expect("Failed to serialize block");
-                        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, message: message};
+                        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, channel: Channel::Consensus, message: message};
                         let serialized_msg = bincode::serialize(&dvf_message).unwrap();
                         debug!("[HELPER] Sending to {:?}", address);
                         self.network.send(address, Bytes::from(serialized_msg)).await;
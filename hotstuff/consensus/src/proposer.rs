@@ -4,7 +4,7 @@ use crate::messages::{Block, QC, TC};
 use bytes::Bytes;
 use crypto::{Digest, PublicKey, SignatureService};
 use log::{debug, info};
-use network::{CancelHandler, SimpleSender, DvfMessage, VERSION};
+use network::{CancelHandler, SimpleSender, Channel, DvfMessage, VERSION};
 use std::collections::HashSet;
 use tokio::sync::mpsc::{Receiver};
 use crypto::Hash;
@@ -114,7 +114,7 @@ impl Proposer {
             .unzip();
         let message = bincode::serialize(&ConsensusMessage::Propose(block.clone()))
             .expect("Failed to serialize block");
-        let dvf_message = DvfMessage {version: VERSION, validator_id: self.validator_id, message: message};
+        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, channel: Channel::Consensus, message: message};
         let serialized_msg = bincode::serialize(&dvf_message).unwrap();
         // let handles = self
         //     .network
@@ -11,7 +11,6 @@ use rand::SeedableRng as _;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
 // Fixture.
 pub fn keys() -> Vec<(PublicKey, SecretKey)> {
@@ -182,8 +181,8 @@ pub fn chain(keys: Vec<(PublicKey, SecretKey)>) -> Vec<Block> {
 pub fn listener(address: SocketAddr, expected: Option<Bytes>) -> JoinHandle<()> {
     tokio::spawn(async move {
         let listener = TcpListener::bind(&address).await.unwrap();
-        let (socket, _) = listener.accept().await.unwrap();
-        let transport = Framed::new(socket, LengthDelimitedCodec::new());
+        let (socket, peer) = listener.accept().await.unwrap();
+        let (_, transport) = network::handshake(socket, peer, false).await.unwrap();
         let (mut writer, mut reader) = transport.split();
         match reader.next().await {
             Some(Ok(received)) => {
@@ -9,7 +9,7 @@ use bytes::Bytes;
 use crypto::{Digest, PublicKey};
 use futures::sink::SinkExt as _;
 use log::{info, warn};
-use network::{MessageHandler, Writer};
+use network::{MessageHandler, PeerKey, Writer};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use store::Store;
@@ -141,7 +141,7 @@ impl Mempool {
             tx_handler_map
                 .write()
                 .await
-                .insert(self.validator_id.clone(), TxReceiverHandler{tx_batch_maker});
+                .insert(self.validator_id.clone(), TxReceiverHandler{tx_batch_maker, committee: self.committee.clone()});
             info!("Insert transaction handler for validator: {}", self.validator_id);
         }
         
@@ -204,7 +204,7 @@ impl Mempool {
             mempool_handler_map
                 .write()
                 .await
-                .insert(self.validator_id.clone(), MempoolReceiverHandler{tx_helper, tx_processor});
+                .insert(self.validator_id.clone(), MempoolReceiverHandler{tx_helper, tx_processor, committee: self.committee.clone()});
             info!("Insert mempool handler for validator: {}", self.validator_id);
         }
         
@@ -244,6 +244,7 @@ impl Mempool {
 #[derive(Clone)]
 pub struct TxReceiverHandler {
     tx_batch_maker: MonitoredSender<Transaction>,
+    committee: Committee,
 }
 
 #[async_trait]
@@ -259,6 +260,10 @@ impl MessageHandler for TxReceiverHandler {
         // tokio::task::yield_now().await;
         Ok(())
     }
+
+    fn accepts(&self, peer: &PeerKey) -> bool {
+        self.committee.authorities.contains_key(&PublicKey(*peer))
+    }
 }
 
 /// Defines how the network receiver handles incoming mempool messages.
@@ -266,6 +271,7 @@ impl MessageHandler for TxReceiverHandler {
 pub struct MempoolReceiverHandler {
     tx_helper: MonitoredSender<(Vec<Digest>, PublicKey)>,
     tx_processor: MonitoredSender<SerializedBatchMessage>,
+    committee: Committee,
 }
 
 #[async_trait]
@@ -290,4 +296,8 @@ impl MessageHandler for MempoolReceiverHandler {
         }
         Ok(())
     }
+
+    fn accepts(&self, peer: &PeerKey) -> bool {
+        self.committee.authorities.contains_key(&PublicKey(*peer))
+    }
 }
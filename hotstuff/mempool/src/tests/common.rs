@@ -13,7 +13,6 @@ use std::convert::TryInto as _;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
 // Fixture
 pub fn keys() -> Vec<(PublicKey, SecretKey)> {
@@ -80,8 +79,8 @@ pub fn batch_digest() -> Digest {
 pub fn listener(address: SocketAddr, expected: Option<Bytes>) -> JoinHandle<()> {
     tokio::spawn(async move {
         let listener = TcpListener::bind(&address).await.unwrap();
-        let (socket, _) = listener.accept().await.unwrap();
-        let transport = Framed::new(socket, LengthDelimitedCodec::new());
+        let (socket, peer) = listener.accept().await.unwrap();
+        let (_, transport) = network::handshake(socket, peer, false).await.unwrap();
         let (mut writer, mut reader) = transport.split();
         match reader.next().await {
             Some(Ok(received)) => {
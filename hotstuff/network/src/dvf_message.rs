@@ -2,11 +2,73 @@ use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 pub use dvf_version::{VERSION};
+use dvf_version::{MAX_SUPPORTED_VERSION, MIN_SUPPORTED_VERSION};
+
+/// The service a message is for, so that a single listener can serve all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Channel {
+    Transaction,
+    Mempool,
+    Consensus,
+    Signature,
+    /// IP lookups at the boot node.
+    Discovery,
+}
+
+impl Channel {
+    /// The oldest version whose messages on this channel are understood. Raise the entry of a
+    /// channel when its message format changes, so that older peers can keep using the others.
+    pub fn min_version(&self) -> u64 {
+        match self {
+            Channel::Transaction
+            | Channel::Mempool
+            | Channel::Consensus
+            | Channel::Signature
+            | Channel::Discovery => MIN_SUPPORTED_VERSION,
+        }
+    }
+
+    /// Whether a message sent by a node on `version` can be handled on this channel.
+    pub fn accepts_version(&self, version: u64) -> bool {
+        version >= self.min_version() && version <= MAX_SUPPORTED_VERSION
+    }
+}
+
+/// The versions a node exchanges messages with, announced in the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Versions {
+    /// The version the node runs.
+    pub current: u64,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl Versions {
+    pub fn ours() -> Self {
+        Self {
+            current: VERSION,
+            min: MIN_SUPPORTED_VERSION,
+            max: MAX_SUPPORTED_VERSION,
+        }
+    }
+
+    /// The version both sides can speak, if each of them supports the version the other one runs.
+    /// It is the older of the two, and the one whose compatibility rules apply on the connection.
+    pub fn negotiate(&self, peer: &Versions) -> Option<u64> {
+        let supports = |versions: &Versions, version: u64| versions.min <= version && version <= versions.max;
+        if supports(self, peer.current) && supports(peer, self.current) {
+            Some(self.current.min(peer.current))
+        } else {
+            None
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DvfMessage {
     pub version: u64,
     pub validator_id: u64,
+    pub channel: Channel,
     pub message: Vec<u8> 
 }
 
@@ -28,4 +28,10 @@ pub enum NetworkError {
 
     #[error("Tokio sender channel is closed for {0}")]
     TokioChannelClosed(SocketAddr),
+
+    #[error("Failed handshake with {0}: {1}")]
+    FailedHandshake(SocketAddr, String),
+
+    #[error("Invalid node key: {0}")]
+    InvalidIdentity(String),
 }
@@ -4,14 +4,16 @@ mod receiver;
 mod reliable_sender;
 mod simple_sender;
 mod dvf_message;
+mod transport;
 #[cfg(test)]
 #[path = "tests/common.rs"]
 pub mod common;
 
 pub const CHANNEL_CAPACITY: usize = 1_000;
 
-pub use crate::receiver::{MessageHandler, Receiver, Writer};
+pub use crate::receiver::{ForwardedConnection, MessageHandler, Receiver, Routes, Writer};
 pub use crate::reliable_sender::{CancelHandler, ReliableSender};
 pub use crate::simple_sender::SimpleSender;
-pub use crate::dvf_message::DvfMessage;
-pub use crate::dvf_message::VERSION;
\ No newline at end of file
+pub use crate::dvf_message::{Channel, DvfMessage, Versions};
+pub use crate::dvf_message::VERSION;
+pub use crate::transport::{handshake, handshake_with, identity, peer_versions, pre_handshake_peers, set_identity, Identity, Peer, PeerKey, Transport};
@@ -7,12 +7,16 @@ use futures::stream::StreamExt as _;
 use log::{info, warn, error, debug};
 use std::error::Error;
 use std::net::SocketAddr;
+use tokio::io::AsyncReadExt as _;
 use tokio::net::{TcpListener, TcpStream};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use tokio::sync::mpsc::Sender;
+use tokio::time::{sleep, timeout, Duration};
 use std::collections::HashMap;
 use std::sync::{Arc};
 use tokio::sync::{RwLock};
-use crate::dvf_message::{DvfMessage, VERSION};
+use crate::dvf_message::{Channel, DvfMessage, VERSION};
+use dvf_version::version_string;
+use crate::transport::{handshake, PeerKey, Transport};
 use futures::SinkExt;
 
 #[cfg(test)]
@@ -20,7 +24,7 @@ use futures::SinkExt;
 pub mod receiver_tests;
 
 /// Convenient alias for the writer end of the TCP channel.
-pub type Writer = SplitSink<Framed<TcpStream, LengthDelimitedCodec>, Bytes>;
+pub type Writer = SplitSink<Transport, Bytes>;
 #[async_trait]
 pub trait MessageHandler: Clone + Send + Sync + 'static {
     /// Defines how to handle an incoming message. A typical usage is to define a `MessageHandler` with a
@@ -28,23 +32,130 @@ pub trait MessageHandler: Clone + Send + Sync + 'static {
     /// forward them through the appropriate delivery channel. Then `writer` can be used to send back
     /// responses or acknowledgements to the sender machine (see unit tests for examples).
     async fn dispatch(&self, writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>>;
+
+    /// Whether the peer authenticated with the node key `peer` may send messages to this handler,
+    /// typically whether it belongs to the committee of the handler's validator.
+    fn accepts(&self, peer: &PeerKey) -> bool;
+}
+
+/// Object safe view of a `MessageHandler`, so that one listener can serve handlers of different types.
+#[async_trait]
+trait Handler: Send + Sync {
+    async fn dispatch(&self, writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>>;
+
+    fn accepts(&self, peer: &PeerKey) -> bool;
+}
+
+#[async_trait]
+impl<H: MessageHandler> Handler for H {
+    async fn dispatch(&self, writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>> {
+        MessageHandler::dispatch(self, writer, message).await
+    }
+
+    fn accepts(&self, peer: &PeerKey) -> bool {
+        MessageHandler::accepts(self, peer)
+    }
+}
+
+/// The handlers of one channel, by validator.
+#[async_trait]
+trait Route: Send + Sync {
+    async fn handler(&self, validator_id: u64) -> Option<Box<dyn Handler>>;
+}
+
+#[async_trait]
+impl<H: MessageHandler> Route for RwLock<HashMap<u64, H>> {
+    async fn handler(&self, validator_id: u64) -> Option<Box<dyn Handler>> {
+        self.read()
+            .await
+            .get(&validator_id)
+            .cloned()
+            .map(|handler| Box::new(handler) as Box<dyn Handler>)
+    }
+}
+
+/// A connection passed on by a listener, with the address of the peer.
+pub type ForwardedConnection = (TcpStream, SocketAddr);
+
+/// How long a multiplexed listener waits for the first bytes of a connection to tell where it goes.
+const PREAMBLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a listener passes the messages of each channel.
+#[derive(Clone, Default)]
+pub struct Routes {
+    /// Handlers of every channel, for listeners that serve a single one.
+    any: Option<Arc<dyn Route>>,
+    channels: HashMap<Channel, Arc<dyn Route>>,
+    /// Connections that start with this preamble belong to another protocol and are passed on
+    /// as they are, without the preamble.
+    forward: Option<(&'static [u8], Sender<ForwardedConnection>)>,
+}
+
+impl Routes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pass messages tagged with `channel` to the handler of their validator in `handler_map`.
+    pub fn route<H: MessageHandler>(mut self, channel: Channel, handler_map: Arc<RwLock<HashMap<u64, H>>>) -> Self {
+        self.channels.insert(channel, handler_map);
+        self
+    }
+
+    /// Pass connections starting with `preamble` to `sender`, e.g. the DKG connections.
+    pub fn forward(mut self, preamble: &'static [u8], sender: Sender<ForwardedConnection>) -> Self {
+        self.forward = Some((preamble, sender));
+        self
+    }
+
+    fn get(&self, channel: Channel) -> Option<&Arc<dyn Route>> {
+        self.any.as_ref().or_else(|| self.channels.get(&channel))
+    }
+}
+
+/// Whether the connection starts with `preamble`. Only peeks, so the stream is left untouched.
+async fn has_preamble(socket: &TcpStream, preamble: &[u8]) -> std::io::Result<bool> {
+    let mut buffer = vec![0u8; preamble.len()];
+    loop {
+        let n = socket.peek(&mut buffer).await?;
+        if n == 0 || buffer[..n] != preamble[..n] {
+            return Ok(false);
+        }
+        if n == preamble.len() {
+            return Ok(true);
+        }
+        // only part of the preamble has arrived yet
+        sleep(Duration::from_millis(10)).await;
+    }
 }
 
 /// For each incoming request, we spawn a new runner responsible to receive messages and forward them
 /// through the provided deliver channel.
-pub struct Receiver<Handler: MessageHandler> {
+pub struct Receiver {
     /// Address to listen to.
     address: SocketAddr,
     /// Struct responsible to define how to handle received messages.
-    handler_map: Arc<RwLock<HashMap<u64, Handler>>>,
+    routes: Routes,
     name: &'static str,
 }
 
-impl<Handler: MessageHandler> Receiver<Handler> {
+impl Receiver {
     /// Spawn a new network receiver handling connections from any incoming peer.
-    pub fn spawn(address: SocketAddr, handler_map: Arc<RwLock<HashMap<u64, Handler>>>, name: &'static str) {
+    pub fn spawn<Handler: MessageHandler>(address: SocketAddr, handler_map: Arc<RwLock<HashMap<u64, Handler>>>, name: &'static str) {
+        let routes = Routes {
+            any: Some(handler_map),
+            ..Routes::default()
+        };
         tokio::spawn(async move {
-            Self { address, handler_map, name}.run().await;
+            Self { address, routes, name}.run().await;
+        });
+    }
+
+    /// Spawn a network receiver serving several channels on a single port. Messages are passed on
+    /// according to their channel tag.
+    pub fn spawn_multiplexed(address: SocketAddr, routes: Routes, name: &'static str) {
+        tokio::spawn(async move {
+            Self { address, routes, name}.run().await;
         });
     }
 
@@ -68,13 +179,40 @@ impl<Handler: MessageHandler> Receiver<Handler> {
         }
     }
 
-    async fn spawn_runner(&self, socket: TcpStream, peer: SocketAddr) {
-        let handler_map = self.handler_map.clone(); 
+    async fn spawn_runner(&self, mut socket: TcpStream, peer: SocketAddr) {
+        let routes = self.routes.clone(); 
         let name = self.name.clone();
 
         tokio::spawn(async move {
-            let mut handler_opt: Option<Handler> = None;
-            let transport = Framed::new(socket, LengthDelimitedCodec::new());
+            if let Some((preamble, sender)) = routes.forward.as_ref() {
+                match timeout(PREAMBLE_TIMEOUT, has_preamble(&socket, preamble)).await {
+                    Ok(Ok(true)) => {
+                        let mut buffer = vec![0u8; preamble.len()];
+                        if socket.read_exact(&mut buffer).await.is_ok() {
+                            let _ = sender.send((socket, peer)).await;
+                        }
+                        return;
+                    }
+                    Ok(Ok(false)) => {}
+                    Ok(Err(e)) => {
+                        warn!("{}", NetworkError::FailedToReceiveMessage(peer, e));
+                        return;
+                    }
+                    Err(_) => {
+                        warn!("Connection from {} timed out before sending anything. [{:?}]", peer, name);
+                        return;
+                    }
+                }
+            }
+
+            let mut handler_opt: Option<((Channel, u64), Box<dyn Handler>)> = None;
+            let (remote, transport) = match handshake(socket, peer, false).await {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("{}", e);
+                    return;
+                }
+            };
             let (mut writer, mut reader) = transport.split();
             while let Some(frame) = reader.next().await {
                 match frame.map_err(|e| NetworkError::FailedToReceiveMessage(peer, e)) {
@@ -84,9 +222,17 @@ impl<Handler: MessageHandler> Receiver<Handler> {
                             Ok(dvf_message) => {
                                 let validator_id = dvf_message.validator_id;
                                 let version = dvf_message.version;
-                                if version != VERSION {
+                                if !dvf_message.channel.accepts_version(version) {
                                     let _ = writer.send(Bytes::from("Version mismatch")).await;
-                                    error!("[VA {}] Version mismatch: got ({}), expected ({})", validator_id, version, VERSION);
+                                    error!(
+                                        "[VA {}] Version mismatch: {} from {} on {} isn't supported on the {:?} channel, we run {}",
+                                        validator_id,
+                                        base64::encode(remote.key),
+                                        peer,
+                                        version_string(version),
+                                        dvf_message.channel,
+                                        version_string(VERSION)
+                                    );
                                     // [zico] Should we kill the connection here? 
                                     // If we kill it, then a reliable sender can resend the message because the ACK is not normal, but it may cause the 
                                     // sender to frequently retry the connection and resend the message when the VA is not ready, making the program to
@@ -96,12 +242,20 @@ impl<Handler: MessageHandler> Receiver<Handler> {
                                     continue;  // Keep the connection
                                 }
                                 
-                                if handler_opt.is_none() {
-                                    let handler_map_lock = handler_map.read().await;
-                                    handler_opt = handler_map_lock.get(&validator_id).cloned();
-                                    drop(handler_map_lock);
-                                }                                
-                                match handler_opt.as_ref() {
+                                let key = (dvf_message.channel, validator_id);
+                                if !matches!(&handler_opt, Some((cached, _)) if *cached == key) {
+                                    handler_opt = match routes.get(dvf_message.channel) {
+                                        Some(route) => route.handler(validator_id).await.map(|handler| (key, handler)),
+                                        None => None,
+                                    };
+                                }
+                                match handler_opt.as_ref().map(|(_, handler)| handler) {
+                                    Some(handler) if !handler.accepts(&remote.key) => {
+                                        // Only the operators of a validator may talk to its handlers
+                                        let _ = writer.send(Bytes::from("Unauthorized")).await;
+                                        warn!("[VA {}] Drop connection from {} with unknown node key {}. [{:?}]", validator_id, peer, base64::encode(remote.key), name);
+                                        return;
+                                    },
                                     Some(handler) => {
                                         // trunctate the prefix
                                         let msg = dvf_message.message;
@@ -113,7 +267,7 @@ impl<Handler: MessageHandler> Receiver<Handler> {
                                     None => {
                                         // [zico] Constantly review this. For now, we sent back a message, which is different from a normal 'Ack' message
                                         let _ = writer.send(Bytes::from("No handler found")).await;
-                                        error!("[VA {}] Receive a message, but no handler found! [{:?}]", validator_id, name);
+                                        error!("[VA {}] Receive a {:?} message, but no handler found! [{:?}]", validator_id, dvf_message.channel, name);
                                         // [zico] Should we kill the connection here? 
                                         // If we kill it, then a reliable sender can resend the message because the ACK is not normal, but it may cause the 
                                         // sender to frequently retry the connection and resend the message when the VA is not ready, making the program to
@@ -11,13 +11,12 @@ use std::cmp::min;
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::net::SocketAddr;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc::{Receiver};
 use tokio::sync::{oneshot, RwLock};
 use tokio::time::{sleep, Duration};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use std::sync::Arc;
 use crate::CHANNEL_CAPACITY;
+use crate::transport::{connect, Transport};
 use utils::monitored_channel::{MonitoredChannel, MonitoredSender};
 
 #[cfg(test)]
@@ -167,8 +166,8 @@ impl Connection {
         let mut delay = self.retry_delay;
         let mut retry = 0;
         loop {
-            match TcpStream::connect(self.address).await {
-                Ok(stream) => {
+            match connect(self.address, retry).await {
+                Ok(transport) => {
                     debug!("Outgoing connection established with {}", self.address);
 
                     // Reset the delay.
@@ -177,7 +176,7 @@ impl Connection {
 
                     // Try to transmit all messages in the buffer and keep transmitting incoming messages.
                     // The following function only returns if there is an error.
-                    let error = self.keep_alive(stream).await;
+                    let error = self.keep_alive(transport).await;
                     warn!("{}", error);
                     match error {
                         NetworkError::TokioChannelClosed(_) => {
@@ -187,7 +186,7 @@ impl Connection {
                     }
                 }
                 Err(e) => {
-                    warn!("{}", NetworkError::FailedToConnect(self.address, retry, e));
+                    warn!("{}", e);
                     let timer = sleep(Duration::from_millis(delay));
                     tokio::pin!(timer);
 
@@ -228,12 +227,12 @@ impl Connection {
     }
 
     /// Transmit messages once we have established a connection.
-    async fn keep_alive(&mut self, stream: TcpStream) -> NetworkError {
+    async fn keep_alive(&mut self, transport: Transport) -> NetworkError {
         // This buffer keeps all messages and handlers that we have successfully transmitted but for
         // which we are still waiting to receive an ACK.
         let mut pending_replies = VecDeque::new();
 
-        let (mut writer, mut reader) = Framed::new(stream, LengthDelimitedCodec::new()).split();
+        let (mut writer, mut reader) = transport.split();
         let error = 'connection: loop {
             let exit = self.exit.clone();
             // Try to send all messages of the buffer.
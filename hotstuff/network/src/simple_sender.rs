@@ -9,13 +9,12 @@ use rand::rngs::SmallRng;
 use rand::SeedableRng as _;
 use std::collections::HashMap;
 use std::net::SocketAddr;
-use tokio::net::TcpStream;
 use tokio::sync::mpsc::{Receiver};
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Duration};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use std::sync::Arc;
 use crate::CHANNEL_CAPACITY;
+use crate::transport::connect;
 use utils::monitored_channel::{MonitoredChannel, MonitoredSender};
 
 #[cfg(test)]
@@ -168,13 +167,10 @@ impl Connection {
     /// Main loop trying to connect to the peer and transmit messages.
     async fn run(&mut self) {
         // Try to connect to the peer.
-        let (mut writer, mut reader) = match TcpStream::connect(self.address).await {
-            Ok(stream) => Framed::new(stream, LengthDelimitedCodec::new()).split(),
+        let (mut writer, mut reader) = match connect(self.address, /* retry */ 0).await {
+            Ok(transport) => transport.split(),
             Err(e) => {
-                warn!(
-                    "{}",
-                    NetworkError::FailedToConnect(self.address, /* retry */ 0, e)
-                );
+                warn!("{}", e);
                 // If failed to connect, retry after 10 seconds. Any further message sent in these 10 seconds
                 // is ignored. This is fine for simple sender, which does not guarantee the receiver will receive the message. 
                 sleep(Duration::from_secs(10)).await;
@@ -1,17 +1,17 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
+use crate::transport::handshake;
 use bytes::Bytes;
 use futures::sink::SinkExt as _;
 use futures::stream::StreamExt as _;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
 
 pub fn listener(address: SocketAddr, expected: String) -> JoinHandle<()> {
     tokio::spawn(async move {
         let listener = TcpListener::bind(&address).await.unwrap();
-        let (socket, _) = listener.accept().await.unwrap();
-        let transport = Framed::new(socket, LengthDelimitedCodec::new());
+        let (socket, peer) = listener.accept().await.unwrap();
+        let (_, transport) = handshake(socket, peer, false).await.unwrap();
         let (mut writer, mut reader) = transport.split();
         match reader.next().await {
             Some(Ok(received)) => {
@@ -1,6 +1,7 @@
 // Copyright(C) Facebook, Inc. and its affiliates.
 use super::*;
-use futures::sink::SinkExt as _;
+use crate::transport::{connect, identity};
+use tokio::io::AsyncWriteExt as _;
 use tokio::sync::mpsc::channel;
 use tokio::sync::mpsc::Sender;
 use tokio::time::{sleep, Duration};
@@ -8,6 +9,7 @@ use tokio::time::{sleep, Duration};
 #[derive(Clone)]
 struct TestHandler {
     deliver: Sender<String>,
+    allowed: PeerKey,
 }
 
 #[async_trait]
@@ -23,6 +25,20 @@ impl MessageHandler for TestHandler {
         self.deliver.send(message).await.unwrap();
         Ok(())
     }
+
+    fn accepts(&self, peer: &PeerKey) -> bool {
+        *peer == self.allowed
+    }
+}
+
+fn dvf_message(validator_id: u64, channel: Channel, sent: &str) -> Bytes {
+    let message = DvfMessage {
+        version: VERSION,
+        validator_id,
+        channel,
+        message: bincode::serialize(sent).unwrap(),
+    };
+    Bytes::from(bincode::serialize(&message).unwrap())
 }
 
 #[tokio::test]
@@ -30,15 +46,15 @@ async fn receive() {
     // Make the network receiver.
     let address = "127.0.0.1:4000".parse::<SocketAddr>().unwrap();
     let (tx, mut rx) = channel(1);
-    Receiver::spawn(address, TestHandler { deliver: tx });
+    let handler = TestHandler { deliver: tx, allowed: identity().public_key() };
+    let handler_map = Arc::new(RwLock::new(HashMap::from([(1, handler)])));
+    Receiver::spawn(address, handler_map, "test");
     sleep(Duration::from_millis(50)).await;
 
     // Send a message.
     let sent = "Hello, world!";
-    let bytes = Bytes::from(bincode::serialize(sent).unwrap());
-    let stream = TcpStream::connect(address).await.unwrap();
-    let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
-    transport.send(bytes.clone()).await.unwrap();
+    let mut transport = connect(address, 0).await.unwrap();
+    transport.send(dvf_message(1, Channel::Consensus, sent)).await.unwrap();
 
     // Ensure the message gets passed to the channel.
     let message = rx.recv().await;
@@ -46,3 +62,93 @@ async fn receive() {
     let received = message.unwrap();
     assert_eq!(received, sent);
 }
+
+#[tokio::test]
+async fn reject_unknown_peer() {
+    // Make a network receiver that only accepts some other node.
+    let address = "127.0.0.1:4001".parse::<SocketAddr>().unwrap();
+    let (tx, mut rx) = channel(1);
+    let handler = TestHandler { deliver: tx, allowed: [2u8; 33] };
+    let handler_map = Arc::new(RwLock::new(HashMap::from([(1, handler)])));
+    Receiver::spawn(address, handler_map, "test");
+    sleep(Duration::from_millis(50)).await;
+
+    // Send a message.
+    let mut transport = connect(address, 0).await.unwrap();
+    transport.send(dvf_message(1, Channel::Consensus, "Hello, world!")).await.unwrap();
+
+    // Ensure the receiver refuses it and drops the connection.
+    let reply = transport.next().await.unwrap().unwrap();
+    assert_eq!(reply, "Unauthorized");
+    assert!(transport.next().await.is_none());
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn receive_multiplexed() {
+    // Make a network receiver serving two channels on a single port.
+    let address = "127.0.0.1:4002".parse::<SocketAddr>().unwrap();
+    let (tx_mempool, mut rx_mempool) = channel(1);
+    let (tx_consensus, mut rx_consensus) = channel(1);
+    let (tx_forward, mut rx_forward) = channel(1);
+    let allowed = identity().public_key();
+    let mempool = Arc::new(RwLock::new(HashMap::from([(1, TestHandler { deliver: tx_mempool, allowed })])));
+    let consensus = Arc::new(RwLock::new(HashMap::from([(1, TestHandler { deliver: tx_consensus, allowed })])));
+    let routes = Routes::new()
+        .route(Channel::Mempool, mempool)
+        .route(Channel::Consensus, consensus)
+        .forward(b"preamble", tx_forward);
+    Receiver::spawn_multiplexed(address, routes, "test");
+    sleep(Duration::from_millis(50)).await;
+
+    // Ensure every message reaches the handler of its channel, even on the same connection.
+    let mut transport = connect(address, 0).await.unwrap();
+    transport.send(dvf_message(1, Channel::Consensus, "consensus")).await.unwrap();
+    assert_eq!(rx_consensus.recv().await.unwrap(), "consensus");
+    transport.send(dvf_message(1, Channel::Mempool, "mempool")).await.unwrap();
+    assert_eq!(rx_mempool.recv().await.unwrap(), "mempool");
+
+    // A channel without handlers isn't served.
+    let mut transport = connect(address, 0).await.unwrap();
+    transport.send(dvf_message(1, Channel::Signature, "signature")).await.unwrap();
+    assert_eq!(transport.next().await.unwrap().unwrap(), "No handler found");
+
+    // Connections of other protocols are passed on without their preamble.
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    stream.write_all(b"preamble").await.unwrap();
+    stream.write_all(b"payload").await.unwrap();
+    let (mut forwarded, _) = rx_forward.recv().await.unwrap();
+    let mut payload = [0u8; 7];
+    forwarded.read_exact(&mut payload).await.unwrap();
+    assert_eq!(&payload, b"payload");
+}
+
+#[tokio::test]
+async fn receive_compatible_versions() {
+    // Make the network receiver.
+    let address = "127.0.0.1:4003".parse::<SocketAddr>().unwrap();
+    let (tx, mut rx) = channel(1);
+    let handler = TestHandler { deliver: tx, allowed: identity().public_key() };
+    let handler_map = Arc::new(RwLock::new(HashMap::from([(1, handler)])));
+    Receiver::spawn(address, handler_map, "test");
+    sleep(Duration::from_millis(50)).await;
+
+    let message = |version: u64, sent: &str| {
+        let message = DvfMessage {
+            version,
+            validator_id: 1,
+            channel: Channel::Consensus,
+            message: bincode::serialize(sent).unwrap(),
+        };
+        Bytes::from(bincode::serialize(&message).unwrap())
+    };
+    let mut transport = connect(address, 0).await.unwrap();
+
+    // Messages of versions older than the channel supports are refused.
+    transport.send(message(Channel::Consensus.min_version() - 1, "old")).await.unwrap();
+    assert_eq!(transport.next().await.unwrap().unwrap(), "Version mismatch");
+
+    // Newer minor versions are compatible.
+    transport.send(message(VERSION + 1, "new")).await.unwrap();
+    assert_eq!(rx.recv().await.unwrap(), "new");
+}
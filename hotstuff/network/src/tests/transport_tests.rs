@@ -0,0 +1,112 @@
+use super::*;
+use crate::dvf_message::VERSION;
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn handshake_authenticates_both_sides() {
+    let address = "127.0.0.1:4100".parse::<SocketAddr>().unwrap();
+    let listener = TcpListener::bind(&address).await.unwrap();
+    let responder = tokio::spawn(async move {
+        let (socket, peer) = listener.accept().await.unwrap();
+        let (remote, mut transport) = handshake(socket, peer, false).await.unwrap();
+        let received = transport.next().await.unwrap().unwrap();
+        assert_eq!(received, "Hello, world!");
+        transport.send(Bytes::from("Ack")).await.unwrap();
+        remote
+    });
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    let (remote, mut transport) = handshake(stream, address, true).await.unwrap();
+    transport.send(Bytes::from("Hello, world!")).await.unwrap();
+    assert_eq!(transport.next().await.unwrap().unwrap(), "Ack");
+
+    // Both sides run in this process, so they authenticate with the same key.
+    assert_eq!(remote.key, identity().public_key());
+    assert_eq!(remote.negotiated_version, VERSION);
+    assert_eq!(responder.await.unwrap().key, identity().public_key());
+    assert_eq!(peer_versions().get(&remote.key), Some(&VERSION));
+}
+
+#[tokio::test]
+async fn tampered_frame_is_rejected() {
+    let (send_key, recv_key) = ([1u8; 32], [2u8; 32]);
+    let mut sender = SecureCodec::new(LengthDelimitedCodec::new(), &send_key, &recv_key);
+    let mut receiver = SecureCodec::new(LengthDelimitedCodec::new(), &recv_key, &send_key);
+
+    let mut buffer = BytesMut::new();
+    sender.encode(Bytes::from("first"), &mut buffer).unwrap();
+    sender.encode(Bytes::from("second"), &mut buffer).unwrap();
+    assert_eq!(receiver.decode(&mut buffer).unwrap().unwrap(), "first");
+    assert_eq!(receiver.decode(&mut buffer).unwrap().unwrap(), "second");
+
+    // Flip a bit of the ciphertext.
+    sender.encode(Bytes::from("third"), &mut buffer).unwrap();
+    let last = buffer.len() - 1;
+    buffer[last] ^= 1;
+    assert!(receiver.decode(&mut buffer).is_err());
+
+    // A replayed frame uses a stale nonce.
+    let mut replay = SecureCodec::new(LengthDelimitedCodec::new(), &send_key, &recv_key);
+    let mut receiver = SecureCodec::new(LengthDelimitedCodec::new(), &recv_key, &send_key);
+    let mut buffer = BytesMut::new();
+    replay.encode(Bytes::from("first"), &mut buffer).unwrap();
+    let frame = buffer.clone();
+    assert!(receiver.decode(&mut buffer).unwrap().is_some());
+    let mut buffer = frame;
+    assert!(receiver.decode(&mut buffer).is_err());
+}
+
+#[tokio::test]
+async fn pre_handshake_peer_is_refused() {
+    let address = "127.0.0.1:4101".parse::<SocketAddr>().unwrap();
+    let listener = TcpListener::bind(&address).await.unwrap();
+    let responder = tokio::spawn(async move {
+        let (socket, peer) = listener.accept().await.unwrap();
+        handshake(socket, peer, false).await
+    });
+
+    // A v1.0.0 node sends its message right away, without the channel.
+    #[derive(Serialize)]
+    struct LegacyDvfMessage {
+        version: u64,
+        validator_id: u64,
+        message: Vec<u8>,
+    }
+    let legacy = LegacyDvfMessage { version: MIN_SUPPORTED_VERSION - 1, validator_id: 1, message: vec![1, 2, 3] };
+    let stream = TcpStream::connect(address).await.unwrap();
+    let mut transport = Framed::new(stream, LengthDelimitedCodec::new());
+    transport.send(Bytes::from(bincode::serialize(&legacy).unwrap())).await.unwrap();
+
+    let refused = pre_handshake_peers();
+    match responder.await.unwrap() {
+        Err(NetworkError::FailedHandshake(_, e)) => assert!(e.contains("predates the handshake"), "{}", e),
+        _ => panic!("a peer from before the handshake was accepted"),
+    }
+    assert_eq!(pre_handshake_peers(), refused + 1);
+    assert!(pre_handshake_version(b"not a message").is_none());
+}
+
+#[test]
+fn negotiate_versions() {
+    let ours = Versions::ours();
+    let newer_minor = Versions { current: VERSION + 1, ..ours };
+    assert_eq!(ours.negotiate(&newer_minor), Some(VERSION));
+    assert_eq!(newer_minor.negotiate(&ours), Some(VERSION));
+
+    // a newer release that dropped the version we run
+    let newer = Versions { current: ours.max + 1, min: VERSION + 1, max: ours.max + 1_000_000 };
+    assert_eq!(ours.negotiate(&newer), None);
+    assert_eq!(newer.negotiate(&ours), None);
+
+    // a newer major release supports ours, but we could not parse its messages
+    let newer = Versions { current: ours.max + 1, min: ours.min, max: ours.max + 1_000_000 };
+    assert_eq!(ours.negotiate(&newer), None);
+    assert_eq!(newer.negotiate(&ours), None);
+}
+
+#[test]
+fn invalid_identity() {
+    assert!(Identity::new(&[0u8; 32]).is_err());
+    assert!(Identity::new(&[1u8; 31]).is_err());
+    assert!(Identity::new(&[1u8; 32]).is_ok());
+}
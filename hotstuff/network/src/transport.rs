@@ -0,0 +1,359 @@
+use crate::dvf_message::Versions;
+use crate::error::NetworkError;
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use bytes::{Bytes, BytesMut};
+use futures::sink::SinkExt as _;
+use futures::stream::StreamExt as _;
+use dvf_version::{version_string, MIN_SUPPORTED_VERSION, ROOT_VERSION};
+use lazy_static::lazy_static;
+use log::info;
+use secp256k1::{ecdh, ecdsa, Message, PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+use tokio_util::codec::{Decoder, Encoder, Framed, FramedParts, LengthDelimitedCodec};
+
+#[cfg(test)]
+#[path = "tests/transport_tests.rs"]
+pub mod transport_tests;
+
+/// Compressed secp256k1 public key of a node, i.e. the `name` of its hotstuff `Secret`.
+pub type PeerKey = [u8; 33];
+
+/// An authenticated connection whose frames are encrypted.
+pub type Transport = Framed<TcpStream, SecureCodec>;
+
+/// The other side of a connection, as authenticated in the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peer {
+    pub key: PeerKey,
+    /// The version the peer runs.
+    pub version: u64,
+    /// The version both sides agreed on.
+    pub negotiated_version: u64,
+}
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const HANDSHAKE_DOMAIN: &[u8] = b"dvf-hotstuff-handshake-v1";
+
+/// The key a node authenticates with on its hotstuff and signature connections.
+#[derive(Clone)]
+pub struct Identity {
+    secret_key: SecretKey,
+    public_key: PeerKey,
+}
+
+impl Identity {
+    pub fn new(secret_key: &[u8]) -> Result<Self, NetworkError> {
+        let secret_key = SecretKey::from_slice(secret_key).map_err(|e| NetworkError::InvalidIdentity(e.to_string()))?;
+        let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), &secret_key).serialize();
+        Ok(Self { secret_key, public_key })
+    }
+
+    fn random() -> Self {
+        let (secret_key, public_key) = Secp256k1::new().generate_keypair(&mut secp256k1::rand::thread_rng());
+        Self { secret_key, public_key: public_key.serialize() }
+    }
+
+    pub fn public_key(&self) -> PeerKey {
+        self.public_key
+    }
+}
+
+lazy_static! {
+    static ref IDENTITY: RwLock<Identity> = RwLock::new(Identity::random());
+    static ref PEER_VERSIONS: RwLock<HashMap<PeerKey, u64>> = RwLock::new(HashMap::new());
+}
+
+/// The version each peer ran when it last connected, to follow a rolling upgrade.
+pub fn peer_versions() -> HashMap<PeerKey, u64> {
+    PEER_VERSIONS.read().unwrap().clone()
+}
+
+static PRE_HANDSHAKE_PEERS: AtomicU64 = AtomicU64::new(0);
+
+/// How many connections were refused because the peer runs a version from before the handshake.
+pub fn pre_handshake_peers() -> u64 {
+    PRE_HANDSHAKE_PEERS.load(Ordering::Relaxed)
+}
+
+fn record_peer_version(peer: &Peer) {
+    let previous = PEER_VERSIONS.write().unwrap().insert(peer.key, peer.version);
+    if previous != Some(peer.version) {
+        info!(
+            "Peer {} runs {}, talking {}",
+            base64::encode(peer.key),
+            version_string(peer.version),
+            version_string(peer.negotiated_version)
+        );
+    }
+}
+
+/// Set the key that all connections of this process authenticate with. Until it is set, e.g. in
+/// a process that only queries the boot node, connections use a random key.
+pub fn set_identity(identity: Identity) {
+    *IDENTITY.write().unwrap() = identity;
+}
+
+pub fn identity() -> Identity {
+    IDENTITY.read().unwrap().clone()
+}
+
+/// Connect to `address` and authenticate with our identity.
+pub async fn connect(address: SocketAddr, retry: u16) -> Result<Transport, NetworkError> {
+    let stream = TcpStream::connect(address)
+        .await
+        .map_err(|e| NetworkError::FailedToConnect(address, retry, e))?;
+    let (_, transport) = handshake(stream, address, true).await?;
+    Ok(transport)
+}
+
+#[derive(Serialize, Deserialize)]
+struct Hello {
+    static_key: Vec<u8>,
+    ephemeral_key: Vec<u8>,
+    versions: Versions,
+}
+
+/// What nodes from before the handshake send first, the `DvfMessage` without its channel.
+#[derive(Deserialize)]
+struct PreHandshakeMessage {
+    version: u64,
+    _validator_id: u64,
+    _message: Vec<u8>,
+}
+
+/// The version of a peer from before the handshake, if `frame` is the message it sent instead of
+/// a hello.
+fn pre_handshake_version(frame: &[u8]) -> Option<u64> {
+    bincode::deserialize::<PreHandshakeMessage>(frame)
+        .ok()
+        .map(|message| message.version)
+        .filter(|version| version / 1_000_000_000_000 == ROOT_VERSION && *version < MIN_SUPPORTED_VERSION)
+}
+
+/// Authenticate a new connection and agree on the frame keys, in the spirit of Noise XX:
+///
+/// 1. Both sides exchange their static key, an ephemeral key and the versions they support, and
+///    give up unless each one supports the version the other one runs.
+/// 2. The initiator signs the transcript with its static key, and the responder only answers
+///    with its own signature once the initiator's one verifies.
+/// 3. Frames are encrypted with keys derived from the ephemeral ECDH secret and the transcript.
+///
+/// Returns the authenticated static key of the peer, which receivers check against the
+/// committee of the validator a message is for, and the version it runs.
+pub async fn handshake(stream: TcpStream, peer: SocketAddr, initiator: bool) -> Result<(Peer, Transport), NetworkError> {
+    handshake_with(&identity(), stream, peer, initiator).await
+}
+
+/// Like `handshake`, but authenticate with `identity` instead of the identity of the process.
+pub async fn handshake_with(
+    identity: &Identity,
+    stream: TcpStream,
+    peer: SocketAddr,
+    initiator: bool,
+) -> Result<(Peer, Transport), NetworkError> {
+    let transport = Framed::new(stream, LengthDelimitedCodec::new());
+    match timeout(HANDSHAKE_TIMEOUT, run_handshake(transport, identity, initiator)).await {
+        Ok(result) => result.map_err(|e| NetworkError::FailedHandshake(peer, e)),
+        Err(_) => Err(NetworkError::FailedHandshake(peer, "timed out".to_string())),
+    }
+}
+
+async fn recv_frame(transport: &mut Framed<TcpStream, LengthDelimitedCodec>) -> Result<Bytes, String> {
+    match transport.next().await {
+        Some(Ok(message)) => Ok(message.freeze()),
+        Some(Err(e)) => Err(format!("connection error {}", e)),
+        None => Err("connection closed".to_string()),
+    }
+}
+
+async fn send_frame(transport: &mut Framed<TcpStream, LengthDelimitedCodec>, message: Bytes) -> Result<(), String> {
+    transport.send(message).await.map_err(|e| format!("connection error {}", e))
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    parts.iter().for_each(|part| hasher.update(part));
+    hasher.finalize().into()
+}
+
+async fn run_handshake(
+    mut transport: Framed<TcpStream, LengthDelimitedCodec>,
+    identity: &Identity,
+    initiator: bool,
+) -> Result<(Peer, Transport), String> {
+    let secp = Secp256k1::new();
+    let (ephemeral_sk, ephemeral_pk) = secp.generate_keypair(&mut secp256k1::rand::thread_rng());
+    let hello = Hello {
+        static_key: identity.public_key.to_vec(),
+        ephemeral_key: ephemeral_pk.serialize().to_vec(),
+        versions: Versions::ours(),
+    };
+    let hello = Bytes::from(bincode::serialize(&hello).unwrap());
+
+    let peer_hello = if initiator {
+        send_frame(&mut transport, hello).await?;
+        recv_frame(&mut transport).await?
+    } else {
+        let peer_hello = recv_frame(&mut transport).await?;
+        send_frame(&mut transport, hello).await?;
+        peer_hello
+    };
+    let peer_hello = bincode::deserialize::<Hello>(&peer_hello).map_err(|e| match pre_handshake_version(&peer_hello) {
+        Some(version) => {
+            PRE_HANDSHAKE_PEERS.fetch_add(1, Ordering::Relaxed);
+            format!(
+                "peer runs {}, which predates the handshake, it has to upgrade to {} or later",
+                version_string(version),
+                version_string(MIN_SUPPORTED_VERSION)
+            )
+        }
+        None => format!("invalid hello {}", e),
+    })?;
+    let peer_pk = PublicKey::from_slice(&peer_hello.static_key).map_err(|e| format!("invalid static key {}", e))?;
+    let peer_ephemeral_pk = PublicKey::from_slice(&peer_hello.ephemeral_key).map_err(|e| format!("invalid ephemeral key {}", e))?;
+    let versions = Versions::ours();
+    let negotiated_version = versions.negotiate(&peer_hello.versions).ok_or_else(|| {
+        format!(
+            "incompatible versions, peer {} runs {} and supports {} to {}, we run {} and support {} to {}",
+            base64::encode(peer_pk.serialize()),
+            version_string(peer_hello.versions.current),
+            version_string(peer_hello.versions.min),
+            version_string(peer_hello.versions.max),
+            version_string(versions.current),
+            version_string(versions.min),
+            version_string(versions.max)
+        )
+    })?;
+
+    // the versions are part of the transcript, so that they can't be downgraded on the way
+    let ours = (identity.public_key, ephemeral_pk.serialize(), bincode::serialize(&versions).unwrap());
+    let theirs = (peer_pk.serialize(), peer_ephemeral_pk.serialize(), bincode::serialize(&peer_hello.versions).unwrap());
+    let (initiator_keys, responder_keys) = if initiator { (ours, theirs) } else { (theirs, ours) };
+    let transcript = hash(&[
+        HANDSHAKE_DOMAIN,
+        &initiator_keys.0,
+        &initiator_keys.1,
+        &initiator_keys.2,
+        &responder_keys.0,
+        &responder_keys.1,
+        &responder_keys.2,
+    ]);
+    let signed = |role: &[u8]| Message::from_slice(&hash(&[&transcript, role])).unwrap();
+    let (self_role, peer_role): (&[u8], &[u8]) = if initiator { (b"initiator", b"responder") } else { (b"responder", b"initiator") };
+    let signature = Bytes::from(secp.sign_ecdsa(&signed(self_role), &identity.secret_key).serialize_compact().to_vec());
+
+    let verify = |peer_signature: Bytes| {
+        ecdsa::Signature::from_compact(&peer_signature)
+            .and_then(|sig| secp.verify_ecdsa(&signed(peer_role), &sig, &peer_pk))
+            .map_err(|_| format!("peer {} failed to authenticate", base64::encode(peer_pk.serialize())))
+    };
+    if initiator {
+        send_frame(&mut transport, signature).await?;
+        verify(recv_frame(&mut transport).await?)?;
+    } else {
+        verify(recv_frame(&mut transport).await?)?;
+        send_frame(&mut transport, signature).await?;
+    }
+
+    let shared_secret = ecdh::shared_secret_point(&peer_ephemeral_pk, &ephemeral_sk);
+    let key = |direction: &[u8]| hash(&[&shared_secret, &transcript, direction]);
+    let (send_key, recv_key) = if initiator {
+        (key(b"initiator-to-responder"), key(b"responder-to-initiator"))
+    } else {
+        (key(b"responder-to-initiator"), key(b"initiator-to-responder"))
+    };
+
+    // keep whatever the peer already sent after its last handshake message
+    let parts = transport.into_parts();
+    let mut secure_parts = FramedParts::new::<Bytes>(parts.io, SecureCodec::new(parts.codec, &send_key, &recv_key));
+    secure_parts.read_buf = parts.read_buf;
+    secure_parts.write_buf = parts.write_buf;
+    let peer = Peer {
+        key: peer_pk.serialize(),
+        version: peer_hello.versions.current,
+        negotiated_version,
+    };
+    record_peer_version(&peer);
+    Ok((peer, Framed::from_parts(secure_parts)))
+}
+
+/// AES-256-GCM with a per-direction frame counter as the nonce, so frames can't be replayed
+/// or reordered.
+struct FrameCipher {
+    cipher: Aes256Gcm,
+    counter: u64,
+}
+
+impl FrameCipher {
+    fn new(key: &[u8]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::from_slice(key)),
+            counter: 0,
+        }
+    }
+
+    fn nonce(&mut self) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter += 1;
+        nonce
+    }
+}
+
+/// Length-delimited frames, encrypted with the keys agreed on in the handshake.
+pub struct SecureCodec {
+    frames: LengthDelimitedCodec,
+    sealer: FrameCipher,
+    opener: FrameCipher,
+}
+
+impl SecureCodec {
+    fn new(frames: LengthDelimitedCodec, send_key: &[u8], recv_key: &[u8]) -> Self {
+        Self {
+            frames,
+            sealer: FrameCipher::new(send_key),
+            opener: FrameCipher::new(recv_key),
+        }
+    }
+}
+
+impl Encoder<Bytes> for SecureCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: Bytes, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let nonce = self.sealer.nonce();
+        let frame = self
+            .sealer
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), &message[..])
+            .map_err(|_| io::Error::other("failed to encrypt frame"))?;
+        self.frames.encode(Bytes::from(frame), dst)
+    }
+}
+
+impl Decoder for SecureCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>, io::Error> {
+        let frame = match self.frames.decode(src)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        let nonce = self.opener.nonce();
+        self.opener
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), &frame[..])
+            .map(|message| Some(BytesMut::from(&message[..])))
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "failed to decrypt frame"))
+    }
+}
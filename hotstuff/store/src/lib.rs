@@ -19,7 +19,8 @@ pub enum StoreCommand {
     Read(Key, oneshot::Sender<StoreResult<Option<Value>>>),
     NotifyRead(Key, oneshot::Sender<StoreResult<Value>>),
     NotifyDestroy(oneshot::Sender<bool>),
-    Delete(Key)
+    Delete(Key),
+    Close(oneshot::Sender<()>),
 }
 
 #[derive(Clone)]
@@ -92,6 +93,11 @@ impl Store {
                     StoreCommand::Delete(key) => {
                         let _ = db.delete(key);
                     }
+                    StoreCommand::Close(sender) => {
+                        drop(db);
+                        let _ = sender.send(());
+                        break;
+                    }
                 }
             }
         });
@@ -148,4 +154,12 @@ impl Store {
             .expect("Failed to receive reply to NotifyDestroy command from store");
     }
 
+    // close the database so that its files can be moved, the store can't be used afterwards
+    pub async fn close(&self) {
+        let (sender, receiver) = oneshot::channel();
+        if self.channel.send(StoreCommand::Close(sender)).await.is_ok() {
+            let _ = receiver.await;
+        }
+    }
+
 }
@@ -0,0 +1,32 @@
+use dvf::node::contract::{CONTRACT_DATABASE_FILE, SECONDS_PER_EPOCH};
+use dvf::node::db::Database;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// print the validator-epochs this operator served for each owner address
+#[tokio::main]
+async fn main() {
+    let base_dir = PathBuf::from(
+        std::env::args()
+            .nth(1)
+            .expect("ERROR: there is no valid base dir argument"),
+    );
+    let db_path = base_dir.join(CONTRACT_DATABASE_FILE);
+    if !db_path.exists() {
+        println!("ERROR: can't find contract database {:?}", db_path);
+        return;
+    }
+    let db = Database::new(db_path).expect("ERROR: can't open contract database");
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("ERROR: system time is before unix epoch")
+        .as_secs();
+    let served = db
+        .query_served_epochs(now, SECONDS_PER_EPOCH)
+        .await
+        .expect("ERROR: can't query served epochs");
+    println!("owner address,validator epochs");
+    for (owner, epochs) in served {
+        println!("{:?},{}", owner, epochs);
+    }
+}
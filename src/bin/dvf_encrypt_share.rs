@@ -0,0 +1,26 @@
+use dvf::crypto::elgamal::Elgamal;
+use secp256k1::PublicKey;
+
+// Encrypts a validator key share to an operator in the format the nodes expect in
+// `encryptedKeys`, for tools that register validators.
+//
+// usage: dvf_encrypt_share <operator public key hex> <validator public key hex> <operator id> <share hex>
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut next_arg = |name: &str| args.next().unwrap_or_else(|| panic!("ERROR: missing argument <{}>", name));
+    let operator_pk = hex::decode(next_arg("operator public key").trim_start_matches("0x"))
+        .expect("ERROR: operator public key isn't hex");
+    let operator_pk = PublicKey::from_slice(&operator_pk).expect("ERROR: invalid operator public key");
+    let validator_pk = hex::decode(next_arg("validator public key").trim_start_matches("0x"))
+        .expect("ERROR: validator public key isn't hex");
+    bls::PublicKey::deserialize(&validator_pk).expect("ERROR: invalid validator public key");
+    let operator_id: u64 = next_arg("operator id").parse().expect("ERROR: invalid operator id");
+    let share = hex::decode(next_arg("share").trim_start_matches("0x")).expect("ERROR: share isn't hex");
+    bls::SecretKey::deserialize(&share).expect("ERROR: invalid share");
+
+    let mut elgamal = Elgamal::new(rand::thread_rng());
+    let envelope = elgamal
+        .encrypt_share(&share, &operator_pk, &validator_pk, operator_id)
+        .expect("ERROR: encryption failed");
+    println!("0x{}", hex::encode(envelope.to_bytes()));
+}
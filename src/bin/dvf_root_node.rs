@@ -19,7 +19,7 @@ use hsconfig::{Secret};
 use hsconfig::Export as _;
 use std::fs;
 use bytes::Bytes;
-use network::{Receiver as NetworkReceiver, MessageHandler, Writer as NetworkWriter};
+use network::{Receiver as NetworkReceiver, MessageHandler, PeerKey, Writer as NetworkWriter};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::collections::HashMap;
@@ -59,6 +59,11 @@ impl MessageHandler for IpQueryReceiverHandler {
         }
         Ok(())  
     }
+
+    // any node may look up the ip of another operator
+    fn accepts(&self, _peer: &PeerKey) -> bool {
+        true
+    }
 }
 
 #[tokio::main]
@@ -179,8 +184,12 @@ async fn main() {
                             continue;
                         }
                         info!("A peer has established session: public key: {}, ip: {:?}", base64::encode(enr.public_key().encode()), enr_ip);
-                        // store binary data
-                        store.write(enr.public_key().encode(), enr_ip.octets().to_vec()).await;
+                        // store binary data, the ip followed by the base port if the node advertises one
+                        let mut address = enr_ip.octets().to_vec();
+                        if let Some(port) = enr.tcp4() {
+                            address.extend_from_slice(&port.to_be_bytes());
+                        }
+                        store.write(enr.public_key().encode(), address).await;
                         ip_set.insert(enr_ip);
                     }
                 }
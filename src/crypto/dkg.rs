@@ -1,35 +1,89 @@
 use crate::crypto::ThresholdSignature;
-use crate::crypto::define::{MODULUS};
+use crate::crypto::scalar::{Scalar, lagrange_coeffs_at_zero};
 use std::ptr;
-use bls::{Hash256, Signature, SecretKey, PublicKey, Keypair, PUBLIC_KEY_BYTES_LEN, SECRET_KEY_BYTES_LEN};
-use num_bigint::{BigInt, Sign};
-use num_bigint::ToBigInt;
-use crate::math::bigint_ext::Ring;
+use bls::{Hash256, Signature, PublicKey, Keypair, PUBLIC_KEY_BYTES_LEN};
+use crate::math::polynomial::Polynomial;
+use crate::utils::rand_utils::RandUtilsRng;
 use std::sync::Arc;
-use blst::{blst_p1, blst_p1_affine};
-use crate::network::io_committee::{IOCommittee, IOChannel};
+use blst::{blst_p1, blst_p1_affine, BLST_ERROR};
+use crate::network::io_committee::{IOCommittee, IOChannel, SessionId};
 use futures::future::join_all;
 use bytes::Bytes;
 use crate::utils::error::DvfError;
 use std::marker::PhantomData;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use serde_derive::{Deserialize, Serialize};
+use log::{error, warn};
+use tokio::time::{timeout, Duration};
+use zeroize::Zeroize;
+
+/// How long a party waits for each other party's message in a round. A party that stays silent
+/// is treated as a dealer that didn't deal.
+pub const DKG_ROUND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Every message starts with the session id and the round number, see `DKG::exchange`.
+const ROUND_TAG_LEN: usize = 32 + 8;
+
+/// Round 1: the dealer's polynomial commitments (compressed G1 points, constant term first)
+/// and the recipient's share, a big-endian scalar.
+#[derive(Clone, Serialize, Deserialize)]
+struct DealMessage {
+    commitments: Vec<Vec<u8>>,
+    share: Vec<u8>,
+}
+
+/// Round 2: dealers whose share didn't verify, and a digest of the commitments received from
+/// every dealer so that parties can tell whether a dealer showed everyone the same polynomial.
+#[derive(Clone, Serialize, Deserialize)]
+struct ComplaintMessage {
+    complaints: Vec<u64>,
+    commitment_digests: Vec<(u64, Vec<u8>)>,
+}
+
+/// Round 3: a dealer answers the complaints against it by publishing its commitments and the
+/// disputed shares.
+#[derive(Clone, Serialize, Deserialize)]
+struct JustificationMessage {
+    commitments: Vec<Vec<u8>>,
+    shares: Vec<(u64, Vec<u8>)>,
+}
+
+/// The constant terms of the polynomials dealt in a run.
+enum Constant {
+    /// A fresh random secret.
+    Random,
+    /// Zero. Its commitment is the identity and isn't sent.
+    Zero,
+    /// The dealers' current shares of a key shared with `threshold`, `None` if we don't deal.
+    Share(Option<Scalar>, usize),
+}
 
 /// Distributed key generation.
-/// NOTE: DKG and the corresponding IO committee are not thread-safe if multiple instances for
-/// one party are running at the same time, so users should make sure that DKG requests are created 
-/// and processed one by one sequentially, instead of concurrently.
-/// 
-/// For example, if there are two committees X: (A, B, C, D) and Y: (A, B, E, F),
-/// then we should make sure that either:
-/// - X runs first, and then Y runs;
-/// - Y runs first, and then X runs.
-/// 
-/// Such a restriction is mainly due to the fact that each party is listening on a single port for DKG, 
-/// so multiple concurrent DKG instances would cause chaos in the connections among parties.
+///
+/// Joint Feldman VSS: every party deals a random polynomial of degree `threshold - 1`, broadcasts
+/// commitments to its coefficients in G1 and sends each party its share. Shares are checked
+/// against the commitments; a party complains about a dealer whose share doesn't verify, and the
+/// dealer must publish that share. Dealers that fail to justify, or that showed different
+/// commitments to different parties, are disqualified. The key is the sum of the remaining
+/// dealers' secrets, and parties confirm they agree on it before returning; the run fails if
+/// fewer than `threshold` dealers remain or the parties don't agree. Parties that send nothing
+/// within a round timeout are disqualified like dealers that didn't deal.
+///
+/// Multiple instances for one party can run at the same time as long as their IO committees
+/// use different sessions (see `NetIOCommittee::new`), since connections on the party's DKG port
+/// are told apart by the session id.
 pub struct DKG<T, U> {
     party: u64,
     io: Arc<T>,
     threshold: usize,
+    seed: Option<[u8; 32]>,
+    round_timeout: Duration,
+    /// The number of rounds exchanged so far, all parties go through the same rounds.
+    round: AtomicU64,
+    /// Messages of parties that are ahead of us, by party and round.
+    early: Mutex<HashMap<(u64, u64), Bytes>>,
     _h: blst_p1,
     _phantom: PhantomData<U>,
 }
@@ -55,111 +109,583 @@ where
             party,
             io,
             threshold,
+            seed: None,
+            round_timeout: DKG_ROUND_TIMEOUT,
+            round: AtomicU64::new(0),
+            early: Mutex::new(HashMap::new()),
             _h: h,
             _phantom: PhantomData,
         }
     }
 
+    /// Deal from a seeded RNG instead of system entropy, so that runs are reproducible. Only for
+    /// known-answer tests: anyone who knows the seed learns the dealt polynomial.
+    pub fn with_seed(mut self, seed: [u8; 32]) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Wait at most `round_timeout` for each party's message in a round.
+    pub fn with_round_timeout(mut self, round_timeout: Duration) -> Self {
+        self.round_timeout = round_timeout;
+        self
+    }
+
     /// Returns:
     /// 1. Self's key pair
     /// 2. Master public key
     /// 3. A hashmap from a party's ID to its shared public key
     pub async fn run(&self) -> Result<(Keypair, PublicKey, HashMap<u64, PublicKey>), DvfError> {
-        let mut threshold_sig = ThresholdSignature::new(self.threshold);
-        let ids = self.io.ids();
-        let (kp, kps) = threshold_sig.key_gen(ids)?;
+        let ids = self.io.ids().to_vec();
+        let (qualified, dealt) = self.deal(&Constant::Random, &ids, &ids).await?;
 
-        let kps_ref = &kps; // Take reference so that it can be used in async move
-        let kp_ref = &kp;
-        let futs = ids.iter().map(|id| async move {
-            let s_ij = Bytes::copy_from_slice(kps_ref.get(id).unwrap().sk.serialize().as_bytes());
-            let pk_i = Bytes::copy_from_slice(kp_ref.pk.serialize().as_slice());
-            let (s_ji, pk_j) = {
-                if *id == self.party {
-                    (s_ij, pk_i)
-                }
-                else {
-                    let send_channel = self.io.channel(self.party, *id);
-                    let recv_channel = self.io.channel(*id, self.party);
-                    // Send a share of my secret to party `id`
-                    send_channel.send(s_ij).await;
-                    // Recv a share of party `id`'s secret
-                    let s = recv_channel.recv().await;
-                    // Publish public key corresponding to my secret to party `id`
-                    send_channel.send(pk_i).await;
-                    // Recv public key corresponding to the secret of party `id`
-                    let pk_bytes = recv_channel.recv().await;
-                    (s, pk_bytes)
-                }
-            };
+        // 5. Combine the shares and commitments of the qualified dealers
+        let mut gsk = Scalar::zero();
+        for dealer in qualified.iter() {
+            gsk += dealt[dealer].1.as_ref().unwrap();
+        }
+        let gsk = gsk.to_secret_key()?;
+        let kp = Keypair::from_components(gsk.public_key(), gsk);
 
-            let s = BigInt::from_bytes_be(Sign::Plus, s_ji.as_ref());
-            let mut pk: blst_p1_affine = Default::default();
-            unsafe {
-                blst::blst_p1_uncompress(&mut pk, pk_j.as_ptr());
+        let mpk = g1_sum(qualified.iter().map(|dealer| dealt[dealer].0[0]));
+        let mpk = PublicKey::deserialize(&g1_compress(&mpk))
+            .map_err(|e| DvfError::KeyGenError(format!("invalid master public key {:?}", e)))?;
+        let mut pks = HashMap::new();
+        for id in ids.iter() {
+            let pk = g1_sum(qualified.iter().map(|dealer| eval_commitments(&dealt[dealer].0, *id)));
+            let pk = PublicKey::deserialize(&g1_compress(&pk))
+                .map_err(|e| DvfError::KeyGenError(format!("invalid public key of party {} {:?}", id, e)))?;
+            pks.insert(*id, pk);
+        }
+        if pks[&self.party] != kp.pk {
+            return Err(DvfError::KeyGenError("group secret key doesn't match the commitments".to_string()));
+        }
+
+        // 6. Make sure every qualified party ends up with the same key
+        self.confirm(&qualified, &qualified, &mpk, &pks).await?;
+        Ok((kp, mpk, pks))
+    }
+
+    /// Proactive refresh: every party deals a sharing of zero, which is added to the current
+    /// shares. The master public key stays the same, while shares from before and after the
+    /// refresh can't be combined.
+    ///
+    /// Returns self's new key pair and the new shared public keys of all parties.
+    pub async fn refresh(
+        &self,
+        keypair: &Keypair,
+        mpk: &PublicKey,
+        pks: &HashMap<u64, PublicKey>,
+    ) -> Result<(Keypair, HashMap<u64, PublicKey>), DvfError> {
+        let ids = self.io.ids().to_vec();
+        let (qualified, dealt) = self.deal(&Constant::Zero, &ids, &ids).await?;
+
+        let mut sk = Scalar::from_secret_key(&keypair.sk);
+        for dealer in qualified.iter() {
+            sk += dealt[dealer].1.as_ref().unwrap();
+        }
+        let sk = sk.to_secret_key()?;
+        let kp = Keypair::from_components(sk.public_key(), sk);
+
+        let mut new_pks = HashMap::new();
+        for id in ids.iter() {
+            let pk = pks
+                .get(id)
+                .and_then(|pk| g1_decompress(pk.serialize().as_slice()))
+                .ok_or_else(|| DvfError::KeyGenError(format!("no public key of party {}", id)))?;
+            let pk = g1_sum(std::iter::once(pk).chain(qualified.iter().map(|dealer| eval_commitments(&dealt[dealer].0, *id))));
+            let pk = PublicKey::deserialize(&g1_compress(&pk))
+                .map_err(|e| DvfError::KeyGenError(format!("invalid public key of party {} {:?}", id, e)))?;
+            new_pks.insert(*id, pk);
+        }
+        if new_pks[&self.party] != kp.pk {
+            return Err(DvfError::KeyGenError("refreshed secret key doesn't match the commitments".to_string()));
+        }
+
+        self.confirm(&qualified, &qualified, mpk, &new_pks).await?;
+        Ok((kp, new_pks))
+    }
+
+    /// Resharing to another committee: the parties of the IO committee that hold a share of the
+    /// current key (`keypair` is set) deal it with a polynomial of degree `self.threshold - 1`,
+    /// and every party in `new_ids` combines what it received from the qualified dealers with
+    /// Lagrange coefficients. The key is never reconstructed and the master public key stays the
+    /// same. At least `threshold`, the threshold of the current committee, of the dealers must be
+    /// qualified.
+    ///
+    /// `dealers` are the ids of the current committee that take part. `pks` are the current shared
+    /// public keys if we know them; they only serve to name dealers that didn't deal their share.
+    ///
+    /// Returns self's new key pair if it is in the new committee, and the shared public keys of
+    /// the new committee.
+    pub async fn reshare(
+        &self,
+        keypair: Option<&Keypair>,
+        dealers: &[u64],
+        threshold: usize,
+        mpk: &PublicKey,
+        pks: Option<&HashMap<u64, PublicKey>>,
+        new_ids: &[u64],
+    ) -> Result<(Option<Keypair>, HashMap<u64, PublicKey>), DvfError> {
+        let share = keypair.map(|kp| Scalar::from_secret_key(&kp.sk));
+        let (qualified, dealt) = self.deal(&Constant::Share(share, threshold), dealers, new_ids).await?;
+
+        // The qualified dealers' constant terms must interpolate to the master secret key
+        let lambdas = lagrange_at_zero(&qualified);
+        let constant = g1_sum(qualified.iter().map(|dealer| g1_mul(&dealt[dealer].0[0], &lambdas[dealer])));
+        if g1_compress(&constant) != mpk.serialize().to_vec() {
+            let cheaters: Vec<u64> = match pks {
+                Some(pks) => qualified
+                    .iter()
+                    .filter(|dealer| {
+                        pks.get(dealer).map_or(true, |pk| g1_compress(&dealt[*dealer].0[0]) != pk.serialize().to_vec())
+                    })
+                    .cloned()
+                    .collect(),
+                None => vec![],
             };
+            return Err(DvfError::KeyGenError(format!(
+                "dealt shares don't match the master public key, dealers {:?} didn't deal their share",
+                cheaters
+            )));
+        }
 
-            (*id, s, pk)
-        });
-        let results = join_all(futs)
+        let kp = if new_ids.contains(&self.party) {
+            let mut sk = Scalar::zero();
+            for dealer in qualified.iter() {
+                let mut term = dealt[dealer].1.clone().unwrap();
+                term *= &lambdas[dealer];
+                sk += &term;
+            }
+            let sk = sk.to_secret_key()?;
+            Some(Keypair::from_components(sk.public_key(), sk))
+        } else {
+            None
+        };
+
+        let mut new_pks = HashMap::new();
+        for id in new_ids.iter() {
+            let pk = g1_sum(
+                qualified
+                    .iter()
+                    .map(|dealer| g1_mul(&eval_commitments(&dealt[dealer].0, *id), &lambdas[dealer])),
+            );
+            let pk = PublicKey::deserialize(&g1_compress(&pk))
+                .map_err(|e| DvfError::KeyGenError(format!("invalid public key of party {} {:?}", id, e)))?;
+            new_pks.insert(*id, pk);
+        }
+        if let Some(kp) = kp.as_ref() {
+            if new_pks[&self.party] != kp.pk {
+                return Err(DvfError::KeyGenError("reshared secret key doesn't match the commitments".to_string()));
+            }
+        }
+
+        let confirmers = qualified.iter().chain(new_ids.iter()).cloned().collect();
+        self.confirm(&qualified, &confirmers, mpk, &new_pks).await?;
+        Ok((kp, new_pks))
+    }
+
+    /// Deal a polynomial with the given constant term from each of `dealers` to each of
+    /// `receivers`, and collect the verified shares of the qualified dealers. Every party of the
+    /// IO committee sees the commitments and takes part in the complaints.
+    async fn deal(
+        &self,
+        constant: &Constant,
+        dealers: &[u64],
+        receivers: &[u64],
+    ) -> Result<(BTreeSet<u64>, HashMap<u64, (Vec<blst_p1>, Option<Scalar>)>), DvfError> {
+        let ids = self.io.ids().to_vec();
+        let receiving = receivers.contains(&self.party);
+
+        // 1. Deal shares of the constant, with commitments to the polynomial
+        let mut rng = match self.seed.as_ref() {
+            Some(seed) => RandUtilsRng::from_seed(seed),
+            None => RandUtilsRng::new(),
+        };
+        let mut coeffs = Scalar::random_vec(&mut rng.rng, self.threshold);
+        match constant {
+            Constant::Random => {}
+            Constant::Zero => coeffs[0] = Scalar::zero(),
+            Constant::Share(Some(share), _) => coeffs[0] = share.clone(),
+            Constant::Share(None, _) => {
+                if dealers.contains(&self.party) {
+                    return Err(DvfError::KeyGenError("no share to deal".to_string()));
+                }
+            }
+        }
+        let zero = matches!(constant, Constant::Zero);
+        // the commitment to a zero constant is the identity and isn't sent
+        let commitments: Vec<Vec<u8>> = coeffs.iter().skip(zero as usize).map(|c| g1_compress(&g1_generator_mul(c))).collect();
+        let poly = Polynomial::new(coeffs);
+        let share_of = |id: u64| poly.eval(&Scalar::from_u64(id)).to_bytes_be().to_vec();
+        // parties that don't receive shares only get the commitments, and non-dealers send nothing
+        let deals = match dealers.contains(&self.party) {
+            true => ids
+                .iter()
+                .map(|id| {
+                    let share = if receivers.contains(id) { share_of(*id) } else { vec![] };
+                    let deal = DealMessage { commitments: commitments.clone(), share };
+                    (*id, Bytes::from(bincode::serialize(&deal).unwrap()))
+                })
+                .collect(),
+            false => HashMap::new(),
+        };
+        let received = self.exchange(deals).await;
+
+        // 2. Verify the received shares and complain about dealers whose share is invalid
+        let mut dealt: HashMap<u64, (Vec<blst_p1>, Option<Scalar>)> = HashMap::new();
+        let mut complaints = vec![];
+        let mut commitment_digests = vec![];
+        for dealer in dealers.iter() {
+            let deal = received.get(dealer).and_then(|bytes| bincode::deserialize::<DealMessage>(bytes).ok());
+            let poly_commitments = deal.as_ref().and_then(|deal| self.decode_commitments(&deal.commitments, zero));
+            match poly_commitments {
+                Some(poly_commitments) => {
+                    commitment_digests.push((*dealer, commitments_digest(&deal.as_ref().unwrap().commitments)));
+                    if !receiving {
+                        dealt.insert(*dealer, (poly_commitments, None));
+                        continue;
+                    }
+                    let share = Scalar::from_bytes_be(&deal.unwrap().share)
+                        .filter(|share| verify_share(&poly_commitments, self.party, share));
+                    if share.is_none() {
+                        warn!("[DKG] party {}: invalid share from dealer {}", self.party, dealer);
+                        complaints.push(*dealer);
+                    }
+                    dealt.insert(*dealer, (poly_commitments, share));
+                }
+                None => {
+                    // reported without a digest, so the dealer has to justify the commitments
+                    warn!("[DKG] party {}: invalid commitments from dealer {}", self.party, dealer);
+                    if receiving {
+                        complaints.push(*dealer);
+                    }
+                }
+            }
+        }
+        let complaint = ComplaintMessage { complaints, commitment_digests };
+        let complaints: HashMap<u64, ComplaintMessage> = self
+            .broadcast(Bytes::from(bincode::serialize(&complaint).unwrap()))
             .await
             .into_iter()
-            .collect::<Vec<(u64, BigInt, blst_p1_affine)>>();
-        
-        // 1. Construct self's individual group key pair
-        let mut gsk = 0.to_bigint().unwrap();
-        for (_id, s, _) in results.iter() {
-            gsk += s;
-        }
-        gsk = gsk.reduce(&MODULUS);
-        let (_, mut gsk_bytes) = gsk.to_bytes_be();
-        if gsk_bytes.len() < SECRET_KEY_BYTES_LEN {
-            (0..SECRET_KEY_BYTES_LEN - gsk_bytes.len()).for_each(|_| gsk_bytes.insert(0, 0));
-        }  
-        let gsk = SecretKey::deserialize(&gsk_bytes[..]).unwrap();
-        let kp = Keypair::from_components(gsk.public_key(), gsk);
+            .filter_map(|(id, bytes)| Some((id, bincode::deserialize::<ComplaintMessage>(&bytes).ok()?)))
+            .collect();
 
-        // 2. Construct master public key
-        
-        let mut mpk_bytes: [u8; PUBLIC_KEY_BYTES_LEN] = [0; PUBLIC_KEY_BYTES_LEN];
-        unsafe {
-            let pks = results.iter().map(|(_, _, x)| ptr::addr_of!(*x)).collect::<Vec<*const blst_p1_affine>>();
-            let mut mpk: blst_p1 = Default::default();
-            blst::blst_p1s_add(&mut mpk, pks.as_ptr(), pks.len());
-            blst::blst_p1_compress(mpk_bytes.as_mut_ptr(), &mpk);
+        // 3. Publish the shares that others complained about. A party that got different
+        // commitments from us counts as a complaint too.
+        let justification = match dealers.contains(&self.party) {
+            true => {
+                let my_digest = commitments_digest(&commitments);
+                let justification = JustificationMessage {
+                    commitments: commitments.clone(),
+                    shares: complaints
+                        .iter()
+                        .filter(|(complainer, complaint)| {
+                            receivers.contains(complainer)
+                                && (complaint.complaints.contains(&self.party)
+                                    || reported_digest(complaint, self.party) != Some(&my_digest))
+                        })
+                        .map(|(complainer, _)| (*complainer, share_of(*complainer)))
+                        .collect(),
+                };
+                Bytes::from(bincode::serialize(&justification).unwrap())
+            }
+            false => Bytes::new(),
         };
-        let mpk = PublicKey::deserialize(&mpk_bytes).unwrap();
+        let justifications: HashMap<u64, JustificationMessage> = self
+            .broadcast(justification)
+            .await
+            .into_iter()
+            .filter_map(|(id, bytes)| Some((id, bincode::deserialize::<JustificationMessage>(&bytes).ok()?)))
+            .collect();
 
-        // 3. Exchange individual group public key
-        let kp_ref = &kp;
-        let futs = ids.iter().map(|id| async move {
-            let pk_bytes = Bytes::copy_from_slice(kp_ref.pk.serialize().as_slice());
-            let (id, pk) = {
-                if *id == self.party {
-                    (*id, kp_ref.pk.clone())
+        // 4. Disqualify dealers that cheated
+        let mut qualified = BTreeSet::new();
+        for dealer in dealers.iter() {
+            match self.judge(*dealer, zero, receivers, &mut dealt, &complaints, &justifications) {
+                Ok(_) => {
+                    qualified.insert(*dealer);
                 }
-                else {
-                    let send_channel = self.io.channel(self.party, *id);
-                    let recv_channel = self.io.channel(*id, self.party);
-                    // Send my pk to party `id`
-                    send_channel.send(pk_bytes).await;
-                    // Recv pk of party `id`
-                    let pk_bytes = recv_channel.recv().await;
-                    let pk = PublicKey::deserialize(pk_bytes.as_ref()).unwrap();
-                    (*id, pk)
+                Err(reason) => error!("[DKG] party {}: dealer {} is disqualified, {}", self.party, dealer, reason),
+            }
+        }
+        let required = match constant {
+            Constant::Share(_, threshold) => *threshold,
+            _ => self.threshold,
+        };
+        if qualified.len() < required {
+            return Err(DvfError::KeyGenError(format!(
+                "only {} dealers are qualified, {} are required",
+                qualified.len(),
+                required
+            )));
+        }
+
+        Ok((qualified, dealt))
+    }
+
+    /// Make sure the `confirmers` end up with the same keys.
+    async fn confirm(
+        &self,
+        qualified: &BTreeSet<u64>,
+        confirmers: &BTreeSet<u64>,
+        mpk: &PublicKey,
+        pks: &HashMap<u64, PublicKey>,
+    ) -> Result<(), DvfError> {
+        let mut transcript = vec![];
+        for dealer in qualified.iter() {
+            transcript.extend_from_slice(&dealer.to_be_bytes());
+        }
+        transcript.extend_from_slice(mpk.serialize().as_slice());
+        for id in pks.keys().collect::<BTreeSet<_>>() {
+            transcript.extend_from_slice(&id.to_be_bytes());
+            transcript.extend_from_slice(pks[id].serialize().as_slice());
+        }
+        let digest = Bytes::from(eth2_hashing::hash(&transcript));
+        let confirmations = self.broadcast(digest.clone()).await;
+        for party in confirmers.iter() {
+            if confirmations.get(party) != Some(&digest) {
+                return Err(DvfError::KeyGenError(format!("party {} derived a different key", party)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a dealer against the complaints and justifications, fixing up our commitments and
+    /// share from the justification if we complained.
+    ///
+    /// The dealer's commitments are the ones reported by a majority of the parties. Parties that
+    /// reported other commitments, or complained about their share, must be answered with the
+    /// commitments and, if they receive shares, with a share that verifies against them.
+    fn judge(
+        &self,
+        dealer: u64,
+        zero: bool,
+        receivers: &[u64],
+        dealt: &mut HashMap<u64, (Vec<blst_p1>, Option<Scalar>)>,
+        complaints: &HashMap<u64, ComplaintMessage>,
+        justifications: &HashMap<u64, JustificationMessage>,
+    ) -> Result<(), String> {
+        let mut votes: HashMap<&Vec<u8>, usize> = HashMap::new();
+        for complaint in complaints.values() {
+            if let Some(digest) = reported_digest(complaint, dealer) {
+                *votes.entry(digest).or_default() += 1;
+            }
+        }
+        let agreed = votes
+            .into_iter()
+            .find(|(_, n)| *n * 2 > complaints.len())
+            .map(|(digest, _)| digest)
+            .ok_or_else(|| "parties don't agree on the commitments".to_string())?;
+
+        let complainers: Vec<u64> = complaints
+            .iter()
+            .filter(|(_, complaint)| {
+                complaint.complaints.contains(&dealer) || reported_digest(complaint, dealer) != Some(agreed)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if complainers.is_empty() {
+            return Ok(());
+        }
+
+        let justification = justifications.get(&dealer).ok_or_else(|| "no justification".to_string())?;
+        if commitments_digest(&justification.commitments) != *agreed {
+            return Err("justified with different commitments".to_string());
+        }
+        let poly_commitments = self
+            .decode_commitments(&justification.commitments, zero)
+            .ok_or_else(|| "justified with invalid commitments".to_string())?;
+        for id in complainers {
+            if !receivers.contains(&id) {
+                if id == self.party {
+                    dealt.insert(dealer, (poly_commitments.clone(), None));
                 }
-            };
+                continue;
+            }
+            let share = justification
+                .shares
+                .iter()
+                .find(|(complainer, _)| *complainer == id)
+                .and_then(|(_, share)| Scalar::from_bytes_be(share))
+                .ok_or_else(|| format!("no justification for the complaint of party {}", id))?;
+            if !verify_share(&poly_commitments, id, &share) {
+                return Err(format!("invalid justification for the complaint of party {}", id));
+            }
+            if id == self.party {
+                dealt.insert(dealer, (poly_commitments.clone(), Some(share)));
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_commitments(&self, commitments: &[Vec<u8>], zero: bool) -> Option<Vec<blst_p1>> {
+        if commitments.len() + zero as usize != self.threshold {
+            return None;
+        }
+        let mut points: Vec<blst_p1> = commitments.iter().map(|c| g1_decompress(c)).collect::<Option<_>>()?;
+        if zero {
+            // the identity
+            points.insert(0, blst_p1::default());
+        }
+        Some(points)
+    }
 
-            (id, pk)
+    /// Send each party its message and receive the party's message to us. Our own message is
+    /// returned as if we received it, and parties that don't answer in time are left out.
+    ///
+    /// Messages are tagged with the session and the round, so that a message that arrives after
+    /// its round timed out is dropped instead of being taken for the message of a later round.
+    async fn exchange(&self, messages: HashMap<u64, Bytes>) -> HashMap<u64, Bytes> {
+        let ids = self.io.ids();
+        let round = self.round.fetch_add(1, Ordering::SeqCst);
+        let messages_ref = &messages;
+        let futs = ids.iter().map(|id| async move {
+            let message = messages_ref.get(id).cloned().unwrap_or_default();
+            if *id == self.party {
+                return Some((*id, message));
+            }
+            let send_channel = self.io.channel(self.party, *id);
+            let recv_channel = self.io.channel(*id, self.party);
+            let exchanged = async {
+                send_channel.send(round_tagged(&self.io.session(), round, &message)).await;
+                self.recv_round(*id, recv_channel, round).await
+            };
+            match timeout(self.round_timeout, exchanged).await {
+                Ok(message) => Some((*id, message)),
+                Err(_) => {
+                    warn!("[DKG] party {}: no message from party {} in time", self.party, id);
+                    None
+                }
+            }
         });
-        let pks = join_all(futs)
-            .await
-            .into_iter()
-            .collect::<HashMap<u64, PublicKey>>();
+        join_all(futs).await.into_iter().flatten().collect()
+    }
 
-        Ok((kp, mpk, pks))
+    /// Receive the message of party `id` for `round`, dropping the ones of earlier rounds and
+    /// keeping the ones of later rounds for when we get there.
+    async fn recv_round(&self, id: u64, channel: &U, round: u64) -> Bytes {
+        if let Some(message) = self.early.lock().unwrap().remove(&(id, round)) {
+            return message;
+        }
+        let session = self.io.session();
+        loop {
+            let frame = channel.recv().await;
+            if frame.len() < ROUND_TAG_LEN || frame[..32] != session[..] {
+                warn!("[DKG] party {}: dropped a message of another session from party {}", self.party, id);
+                continue;
+            }
+            let mut frame_round = [0u8; 8];
+            frame_round.copy_from_slice(&frame[32..ROUND_TAG_LEN]);
+            let frame_round = u64::from_be_bytes(frame_round);
+            let message = frame.slice(ROUND_TAG_LEN..);
+            if frame_round == round {
+                return message;
+            }
+            if frame_round > round {
+                self.early.lock().unwrap().insert((id, frame_round), message);
+            } else {
+                warn!("[DKG] party {}: dropped the message of round {} from party {}, it came too late", self.party, frame_round, id);
+            }
+        }
+    }
+
+    async fn broadcast(&self, message: Bytes) -> HashMap<u64, Bytes> {
+        let messages = self.io.ids().iter().map(|id| (*id, message.clone())).collect();
+        self.exchange(messages).await
     }
+}
+
+fn round_tagged(session: &SessionId, round: u64, message: &[u8]) -> Bytes {
+    let mut frame = Vec::with_capacity(ROUND_TAG_LEN + message.len());
+    frame.extend_from_slice(session);
+    frame.extend_from_slice(&round.to_be_bytes());
+    frame.extend_from_slice(message);
+    Bytes::from(frame)
+}
+
+fn g1_mul(p: &blst_p1, x: &Scalar) -> blst_p1 {
+    let mut scalar = x.to_blst_scalar();
+    let mut out = blst_p1::default();
+    unsafe {
+        blst::blst_p1_mult(&mut out, p, scalar.b.as_ptr(), 255);
+    }
+    scalar.b.zeroize();
+    out
+}
 
+fn g1_generator_mul(x: &Scalar) -> blst_p1 {
+    unsafe { g1_mul(&*blst::blst_p1_generator(), x) }
+}
+
+fn g1_sum(points: impl Iterator<Item = blst_p1>) -> blst_p1 {
+    let mut sum = blst_p1::default();
+    for p in points {
+        let acc = sum;
+        unsafe {
+            blst::blst_p1_add_or_double(&mut sum, &acc, &p);
+        }
+    }
+    sum
+}
+
+fn g1_compress(p: &blst_p1) -> Vec<u8> {
+    let mut bytes = [0u8; PUBLIC_KEY_BYTES_LEN];
+    unsafe {
+        blst::blst_p1_compress(bytes.as_mut_ptr(), p);
+    }
+    bytes.to_vec()
+}
+
+fn g1_decompress(bytes: &[u8]) -> Option<blst_p1> {
+    if bytes.len() != PUBLIC_KEY_BYTES_LEN {
+        return None;
+    }
+    let mut affine = blst_p1_affine::default();
+    let mut p = blst_p1::default();
+    unsafe {
+        if blst::blst_p1_uncompress(&mut affine, bytes.as_ptr()) != BLST_ERROR::BLST_SUCCESS
+            || !blst::blst_p1_affine_in_g1(&affine)
+        {
+            return None;
+        }
+        blst::blst_p1_from_affine(&mut p, &affine);
+    }
+    Some(p)
+}
+
+/// The commitment to f(id), i.e. sum of `commitments[k] * id^k`.
+fn eval_commitments(commitments: &[blst_p1], id: u64) -> blst_p1 {
+    let x = Scalar::from_u64(id);
+    let mut acc = commitments[commitments.len() - 1];
+    for c in commitments.iter().rev().skip(1) {
+        acc = g1_sum(vec![g1_mul(&acc, &x), *c].into_iter());
+    }
+    acc
+}
+
+fn verify_share(commitments: &[blst_p1], id: u64, share: &Scalar) -> bool {
+    let expected = eval_commitments(commitments, id);
+    let actual = g1_generator_mul(share);
+    unsafe { blst::blst_p1_is_equal(&expected, &actual) }
+}
+
+fn reported_digest(complaint: &ComplaintMessage, dealer: u64) -> Option<&Vec<u8>> {
+    complaint
+        .commitment_digests
+        .iter()
+        .find(|(d, _)| *d == dealer)
+        .map(|(_, digest)| digest)
+}
+
+fn commitments_digest(commitments: &[Vec<u8>]) -> Vec<u8> {
+    eth2_hashing::hash(&commitments.concat())
+}
+
+/// Lagrange coefficients for interpolating at 0 from the shares of `ids`.
+fn lagrange_at_zero(ids: &BTreeSet<u64>) -> HashMap<u64, Scalar> {
+    let ids: Vec<u64> = ids.iter().cloned().collect();
+    ids.iter().cloned().zip(lagrange_coeffs_at_zero(&ids)).collect()
 }
 
 pub struct SimpleDistributedSigner<T, U> {
@@ -241,12 +767,11 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::network::io_committee::{MemIOCommittee, NetIOCommittee};
+    use crate::network::io_committee::{session_id, MemIOCommittee, MemIOChannel, NetIOCommittee, NetIOIdentity};
     use crate::crypto::ThresholdSignature;
     use types::{Hash256}; 
     use eth2_hashing::{Context, Sha256Context};
     use futures::future::join_all;
-    use futures::executor::block_on;
     use bls::{Signature, PublicKey};
     use std::net::SocketAddr;
     use std::collections::HashMap;
@@ -254,6 +779,16 @@ mod tests {
     const t: usize = 3;
     const ids: [u64; 4] = [1, 2, 3, 4];
 
+    fn operator_keys() -> Vec<(secp256k1::SecretKey, secp256k1::PublicKey)> {
+        let secp = secp256k1::Secp256k1::new();
+        ids.iter().map(|_| secp.generate_keypair(&mut rand::thread_rng())).collect()
+    }
+
+    fn net_identity(keys: &[(secp256k1::SecretKey, secp256k1::PublicKey)], i: usize) -> NetIOIdentity {
+        let public_keys: Vec<secp256k1::PublicKey> = keys.iter().map(|(_, pk)| *pk).collect();
+        NetIOIdentity::new(keys[i].0, ids.as_slice(), &public_keys)
+    }
+
     fn verify_dkg_results(results: Vec<(Keypair, PublicKey, HashMap<u64, PublicKey>)>) {
         // Verify master public keys
         for i in 1..results.len() {
@@ -282,8 +817,8 @@ mod tests {
         assert!(status1, "Signature verification failed");
     }
 
-    #[test]
-    fn test_dkg() {
+    #[tokio::test]
+    async fn test_dkg() {
         // Use dkg to generate secret-shared keys
         let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
         let futs = ids.iter().map(|id| async move {
@@ -291,7 +826,7 @@ mod tests {
             dkg.run().await
         });
 
-        let results = block_on(join_all(futs))
+        let results = join_all(futs).await
             .into_iter()
             .flatten()
             .collect::<Vec<(Keypair, PublicKey, HashMap<u64, PublicKey>)>>();
@@ -299,16 +834,232 @@ mod tests {
         verify_dkg_results(results);
     }
 
+    // Party 4 sends party 1 a wrong share and can't justify it
+    async fn run_cheating_dealer(dkg: &DKG<MemIOCommittee, MemIOChannel>) {
+        let mut rng = RandUtilsRng::new();
+        let coeffs = Scalar::random_vec(&mut rng.rng, t);
+        let commitments: Vec<Vec<u8>> = coeffs.iter().map(|c| g1_compress(&g1_generator_mul(c))).collect();
+        let poly = Polynomial::new(coeffs);
+        let bad_share = |id: u64| {
+            let mut share = poly.eval(&Scalar::from_u64(id));
+            if id == 1 {
+                share += &Scalar::one();
+            }
+            share.to_bytes_be().to_vec()
+        };
+        let deals = ids
+            .iter()
+            .map(|id| {
+                let deal = DealMessage { commitments: commitments.clone(), share: bad_share(*id) };
+                (*id, Bytes::from(bincode::serialize(&deal).unwrap()))
+            })
+            .collect();
+        let received = dkg.exchange(deals).await;
+
+        let commitment_digests = received
+            .iter()
+            .map(|(id, bytes)| {
+                let deal = bincode::deserialize::<DealMessage>(bytes).unwrap();
+                (*id, commitments_digest(&deal.commitments))
+            })
+            .collect();
+        let complaint = ComplaintMessage { complaints: vec![], commitment_digests };
+        dkg.broadcast(Bytes::from(bincode::serialize(&complaint).unwrap())).await;
+
+        let justification = JustificationMessage { commitments, shares: vec![(1, bad_share(1))] };
+        dkg.broadcast(Bytes::from(bincode::serialize(&justification).unwrap())).await;
+
+        dkg.broadcast(Bytes::from(vec![0u8; 32])).await;
+    }
+
+    #[tokio::test]
+    async fn test_dkg_disqualifies_cheating_dealer() {
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = ids.iter().map(|id| async move {
+            let dkg = DKG::new(*id, io.clone(), t);
+            if *id == 4 {
+                run_cheating_dealer(&dkg).await;
+                None
+            } else {
+                Some(dkg.run().await)
+            }
+        });
+
+        let results = join_all(futs).await
+            .into_iter()
+            .flatten()
+            .collect::<Result<Vec<(Keypair, PublicKey, HashMap<u64, PublicKey>)>, DvfError>>()
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        for i in 1..results.len() {
+            assert_eq!(results[i].1, results[0].1, "Master public keys are not the same");
+            assert_eq!(results[i].2, results[0].2, "Shared public keys are not the same");
+        }
+
+        // The honest parties can still sign for the master public key
+        let message = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+        let sigs: Vec<Signature> = results.iter().map(|(kp, _, _)| kp.sk.sign(message)).collect();
+        let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = results.iter().map(|(kp, _, _)| &kp.pk).collect();
+        let threshold_sig = ThresholdSignature::new(t);
+        let agg_sig = threshold_sig.threshold_aggregate(&sigs_ref[..], &pks[..], &ids[..3], message).unwrap();
+        assert!(agg_sig.verify(&results[0].1, message), "Signature verification failed");
+    }
+
+    // Party 4 never sends anything
+    #[tokio::test]
+    async fn test_dkg_disqualifies_silent_party() {
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = ids.iter().filter(|id| **id != 4).map(|id| async move {
+            DKG::new(*id, io.clone(), t)
+                .with_round_timeout(std::time::Duration::from_millis(200))
+                .run()
+                .await
+        });
+        let results = join_all(futs)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<(Keypair, PublicKey, HashMap<u64, PublicKey>)>, DvfError>>()
+            .unwrap();
+        for i in 1..results.len() {
+            assert_eq!(results[i].1, results[0].1, "Master public keys are not the same");
+            assert_eq!(results[i].2, results[0].2, "Shared public keys are not the same");
+        }
+
+        // with one more silent party, too few dealers are left
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = ids.iter().filter(|id| **id < 3).map(|id| async move {
+            DKG::new(*id, io.clone(), t)
+                .with_round_timeout(std::time::Duration::from_millis(200))
+                .run()
+                .await
+        });
+        for result in join_all(futs).await {
+            assert!(result.is_err(), "DKG succeeded without enough dealers");
+        }
+    }
+
+    // Party 4 is slow: its round 1 message arrives after the others' round timed out. It must
+    // be dropped instead of being taken for party 4's message of the next round.
+    #[tokio::test]
+    async fn test_dkg_late_round_message() {
+        let round_timeout = std::time::Duration::from_millis(200);
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = ids.iter().map(|id| async move {
+            let dkg = DKG::new(*id, io.clone(), t).with_round_timeout(round_timeout);
+            if *id == 4 {
+                tokio::time::sleep(round_timeout * 3 / 2).await;
+            }
+            let mut rounds = vec![];
+            for round in 1..=3u8 {
+                rounds.push(dkg.broadcast(Bytes::from(vec![round, *id as u8])).await);
+            }
+            rounds
+        });
+        let results = join_all(futs).await;
+        for (i, rounds) in results[..3].iter().enumerate() {
+            assert!(!rounds[0].contains_key(&4), "party {} got the late message in its round", ids[i]);
+            for (round, received) in rounds.iter().enumerate().skip(1) {
+                assert_eq!(received.get(&4), Some(&Bytes::from(vec![round as u8 + 1, 4])));
+            }
+        }
+        // the late party still gets everyone's messages, they were sent in time
+        for (round, received) in results[3].iter().enumerate() {
+            for id in ids.iter() {
+                assert_eq!(received.get(id), Some(&Bytes::from(vec![round as u8 + 1, *id as u8])));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dkg_refresh() {
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = ids.iter().map(|id| async move {
+            let dkg = DKG::new(*id, io.clone(), t);
+            let (kp, mpk, pks) = dkg.run().await?;
+            let (new_kp, new_pks) = dkg.refresh(&kp, &mpk, &pks).await?;
+            Ok::<_, DvfError>((kp, mpk, new_kp, new_pks))
+        });
+        let results = join_all(futs).await
+            .into_iter()
+            .collect::<Result<Vec<_>, DvfError>>()
+            .unwrap();
+
+        let mpk = &results[0].1;
+        for (kp, _, new_kp, new_pks) in results.iter() {
+            assert_ne!(kp.pk, new_kp.pk, "Shares are not refreshed");
+            assert_eq!(new_pks, &results[0].3, "Shared public keys are not the same");
+        }
+
+        let message = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+        let threshold_sig = ThresholdSignature::new(t);
+        let sign = |kps: Vec<&Keypair>| {
+            let sigs: Vec<Signature> = kps.iter().map(|kp| kp.sk.sign(message)).collect();
+            let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+            let pks: Vec<&PublicKey> = kps.iter().map(|kp| &kp.pk).collect();
+            threshold_sig.threshold_aggregate(&sigs_ref[..], &pks[..], &ids[..3], message).unwrap()
+        };
+        // refreshed shares still sign for the same master public key
+        let new_kps: Vec<&Keypair> = results.iter().take(3).map(|r| &r.2).collect();
+        assert!(sign(new_kps).verify(mpk, message), "Signature verification failed");
+        // but can't be combined with old shares
+        let mixed_kps = vec![&results[0].0, &results[1].2, &results[2].2];
+        assert!(!sign(mixed_kps).verify(mpk, message), "Old and new shares are combined");
+    }
+
+    #[tokio::test]
+    async fn test_dkg_reshare() {
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = ids.iter().map(|id| async move { DKG::new(*id, io.clone(), t).run().await });
+        let results = join_all(futs).await
+            .into_iter()
+            .collect::<Result<Vec<_>, DvfError>>()
+            .unwrap();
+        let results = &results;
+        let mpk = &results[0].1;
+        let pks = &results[0].2;
+
+        // operator 1 is replaced by operator 5, the others deal their shares
+        let new_ids: [u64; 4] = [2, 3, 4, 5];
+        let dealers: [u64; 3] = [2, 3, 4];
+        let new_io = &Arc::new(MemIOCommittee::new(new_ids.as_slice()));
+        let futs = new_ids.iter().map(|id| async move {
+            let kp = results.iter().zip(ids.iter()).find(|(_, i)| *i == id).map(|(r, _)| &r.0);
+            let dkg = DKG::new(*id, new_io.clone(), t);
+            dkg.reshare(kp, &dealers, t, mpk, kp.map(|_| pks), &new_ids).await
+        });
+        let reshared = join_all(futs).await
+            .into_iter()
+            .collect::<Result<Vec<_>, DvfError>>()
+            .unwrap();
+        for (_, new_pks) in reshared.iter() {
+            assert_eq!(new_pks, &reshared[0].1, "Shared public keys are not the same");
+        }
+
+        // any threshold of the new committee signs for the same master public key
+        let message = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+        let kps: Vec<&Keypair> = reshared.iter().skip(1).map(|(kp, _)| kp.as_ref().unwrap()).collect();
+        let sigs: Vec<Signature> = kps.iter().map(|kp| kp.sk.sign(message)).collect();
+        let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+        let new_pks: Vec<&PublicKey> = kps.iter().map(|kp| &kp.pk).collect();
+        let threshold_sig = ThresholdSignature::new(t);
+        let agg_sig = threshold_sig.threshold_aggregate(&sigs_ref[..], &new_pks[..], &new_ids[1..], message).unwrap();
+        assert!(agg_sig.verify(mpk, message), "Signature verification failed");
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_dkg_net() {
         let ports: Vec<u16> = ids.iter().map(|id| (25000 + *id) as u16).collect();
         let addrs: Vec<SocketAddr> = ports.iter().map(|port| SocketAddr::new("127.0.0.1".parse().unwrap(), *port)).collect();
 
+        let keys = operator_keys();
         let ports_ref = &ports;
         let addrs_ref = &addrs;
+        let keys_ref = &keys;
         // Use dkg to generate secret-shared keys
         let futs = (0..ids.len()).map(|i| async move {
-            let io = &Arc::new(NetIOCommittee::new(ids[i], ports_ref[i], ids.as_slice(), addrs_ref.as_slice()).await);
+            let identity = net_identity(keys_ref, i);
+            let io = &Arc::new(NetIOCommittee::new(ids[i], ports_ref[i], session_id("test-dkg-net", &ids), ids.as_slice(), addrs_ref.as_slice(), identity).await.unwrap());
             let mut dkg = DKG::new(ids[i], io.clone(), t);
             dkg.run().await
         });
@@ -332,11 +1083,14 @@ mod tests {
         context.update(message.as_bytes());
         let message = Hash256::from_slice(&context.finalize());
 
+        let keys = operator_keys();
         let ports_ref = &ports;
         let addrs_ref = &addrs;
+        let keys_ref = &keys;
         // Use dkg to generate secret-shared keys
         let futs = (0..ids.len()).map(|i| async move {
-            let io = &Arc::new(NetIOCommittee::new(ids[i], ports_ref[i], ids.as_slice(), addrs_ref.as_slice()).await);
+            let identity = net_identity(keys_ref, i);
+            let io = &Arc::new(NetIOCommittee::new(ids[i], ports_ref[i], session_id("test-dkg-sig-net", &ids), ids.as_slice(), addrs_ref.as_slice(), identity).await.unwrap());
             let mut dkg = DKG::new(ids[i], io.clone(), t);
             let (kp, mpk, pks) = dkg.run().await?;
 
@@ -1,8 +1,19 @@
 use secp256k1::{All, Secp256k1, SecretKey, PublicKey, ecdh};
 use sha256::{digest_bytes};
-use aes_gcm::{Aes128Gcm, Key, Nonce, Error};
-use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes128Gcm, Aes256Gcm, Key, Nonce, Error};
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use hkdf::Hkdf;
+use sha2::Sha256;
 use rand::Rng;
+use zeroize::Zeroize;
+use log::warn;
+
+/// Version byte of the share envelope produced by `Elgamal::encrypt_share`.
+pub const SHARE_ENVELOPE_VERSION: u8 = 1;
+const SHARE_KDF_INFO: &[u8] = b"dvf share envelope v1";
+const PUBLIC_KEY_LEN: usize = 33;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
 
 #[derive(PartialEq, Debug)]
 pub struct Ciphertext {
@@ -18,16 +29,85 @@ impl Ciphertext {
         [Vec::from(self.temp_pk.serialize()), self.aes_ct.clone()].concat()
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let temp_pk = PublicKey::from_slice(&bytes[0..33]).unwrap();
-        let aes_ct = Vec::from(&bytes[33..]);
-        Self {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < PUBLIC_KEY_LEN + NONCE_LEN + TAG_LEN {
+            return Err(format!("ciphertext too short: {} bytes", bytes.len()));
+        }
+        let temp_pk = PublicKey::from_slice(&bytes[0..PUBLIC_KEY_LEN])
+            .map_err(|e| format!("invalid ephemeral public key: {:?}", e))?;
+        let aes_ct = Vec::from(&bytes[PUBLIC_KEY_LEN..]);
+        Ok(Self {
             temp_pk,
             aes_ct,
+        })
+    }
+}
+
+/// An encrypted validator key share, as published in the `encryptedKeys` of a registration:
+///
+/// `version (1) || ephemeral pk (33) || nonce (12) || AES-256-GCM ciphertext || tag (16)`
+///
+/// The AES key is derived with HKDF-SHA256 from the ECDH point of the ephemeral key and the
+/// operator's key, salted with the ephemeral public key. The validator public key and the
+/// operator id are authenticated as associated data, so a share can't be replayed into the
+/// registration of another validator or sent to another operator.
+#[derive(PartialEq, Debug)]
+pub struct ShareEnvelope {
+    temp_pk: PublicKey,
+    nonce: [u8; NONCE_LEN],
+    aes_ct: Vec<u8>,
+}
+
+impl ShareEnvelope {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [
+            vec![SHARE_ENVELOPE_VERSION],
+            Vec::from(self.temp_pk.serialize()),
+            self.nonce.to_vec(),
+            self.aes_ct.clone(),
+        ]
+        .concat()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        match bytes.first() {
+            Some(&SHARE_ENVELOPE_VERSION) => {}
+            Some(version) => return Err(format!("unsupported share envelope version {}", version)),
+            None => return Err("empty share envelope".to_string()),
         }
+        let bytes = &bytes[1..];
+        if bytes.len() < PUBLIC_KEY_LEN + NONCE_LEN + TAG_LEN {
+            return Err(format!("share envelope too short: {} bytes", bytes.len() + 1));
+        }
+        let temp_pk = PublicKey::from_slice(&bytes[..PUBLIC_KEY_LEN])
+            .map_err(|e| format!("invalid ephemeral public key: {:?}", e))?;
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&bytes[PUBLIC_KEY_LEN..PUBLIC_KEY_LEN + NONCE_LEN]);
+        Ok(Self {
+            temp_pk,
+            nonce,
+            aes_ct: Vec::from(&bytes[PUBLIC_KEY_LEN + NONCE_LEN..]),
+        })
     }
 }
 
+/// The AEAD associated data of a share: the compressed validator public key followed by the
+/// operator id as a big-endian u64.
+fn share_aad(validator_pk: &[u8], operator_id: u64) -> Vec<u8> {
+    [validator_pk, &operator_id.to_be_bytes()[..]].concat()
+}
+
+fn share_cipher(pk: &PublicKey, sk: &SecretKey, temp_pk: &PublicKey) -> Aes256Gcm {
+    let mut point = ecdh::shared_secret_point(pk, sk);
+    let hk = Hkdf::<Sha256>::new(Some(&temp_pk.serialize()), &point);
+    let mut key = [0u8; 32];
+    hk.expand(SHARE_KDF_INFO, &mut key).expect("32 bytes is a valid HKDF-SHA256 length");
+    let cipher = Aes256Gcm::new(Key::from_slice(&key));
+    point.zeroize();
+    key.zeroize();
+    cipher
+}
+
 #[derive(Debug)]
 pub struct Elgamal<R> {
 	rng: R,
@@ -121,5 +201,88 @@ where R: Rng {
         cipher.decrypt(nonce, aes_ct) 
     }
 
+    /// Encrypt a validator key share to an operator, bound to the validator and the operator id.
+    /// This is the encryption that frontends and tools use to build the `encryptedKeys` of a
+    /// registration.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut elgamal = Elgamal::new(rand::thread_rng());
+    /// let envelope = elgamal.encrypt_share(&share.serialize(), &operator_pk, &validator_pk.serialize(), operator_id)?;
+    /// let encrypted_key = envelope.to_bytes();
+    /// ```
+    pub fn encrypt_share(
+        &mut self,
+        share: &[u8],
+        pk: &PublicKey,
+        validator_pk: &[u8],
+        operator_id: u64,
+    ) -> Result<ShareEnvelope, Error> {
+        let (temp_sk, temp_pk) = self.secp.generate_keypair(&mut self.rng);
+        let cipher = share_cipher(pk, &temp_sk, &temp_pk);
+
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce[..]);
+        let aad = share_aad(validator_pk, operator_id);
+        let aes_ct = cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: share, aad: &aad })?;
+
+        Ok(ShareEnvelope {
+            temp_pk,
+            nonce,
+            aes_ct,
+        })
+    }
+
+    /// Decrypt a share encrypted with `encrypt_share`. Fails if the envelope was made for another
+    /// validator or operator.
+    pub fn decrypt_share(
+        &mut self,
+        envelope: &ShareEnvelope,
+        sk: &SecretKey,
+        validator_pk: &[u8],
+        operator_id: u64,
+    ) -> Result<Vec<u8>, Error> {
+        let cipher = share_cipher(&envelope.temp_pk, sk, &envelope.temp_pk);
+        let aad = share_aad(validator_pk, operator_id);
+        cipher.decrypt(
+            Nonce::from_slice(&envelope.nonce),
+            Payload { msg: &envelope.aes_ct, aad: &aad },
+        )
+    }
+
+    /// Decrypt an entry of the `encryptedKeys` of a registration. Share envelopes start with
+    /// their version byte, while registrations made before the envelope carry a plain
+    /// `Ciphertext`, which starts with the 0x02 or 0x03 prefix of its compressed ephemeral key.
+    /// The latter aren't bound to the validator and the operator, so a ciphertext copied from
+    /// another registration would decrypt just as well; they are only accepted with
+    /// `accept_legacy`, for registrations made before the envelope.
+    pub fn decrypt_encrypted_key(
+        &mut self,
+        encrypted_key: &[u8],
+        sk: &SecretKey,
+        validator_pk: &[u8],
+        operator_id: u64,
+        accept_legacy: bool,
+    ) -> Result<Vec<u8>, String> {
+        match encrypted_key.first() {
+            Some(&SHARE_ENVELOPE_VERSION) => {
+                let envelope = ShareEnvelope::from_bytes(encrypted_key)?;
+                self.decrypt_share(&envelope, sk, validator_pk, operator_id)
+                    .map_err(|_| "failed to decrypt share envelope".to_string())
+            }
+            Some(0x02) | Some(0x03) if !accept_legacy => {
+                Err("legacy ciphertext shares are only accepted for earlier registrations".to_string())
+            }
+            Some(0x02) | Some(0x03) => {
+                warn!("share for operator {} is a legacy ciphertext, which is deprecated in favor of share envelopes", operator_id);
+                let ct = Ciphertext::from_bytes(encrypted_key)?;
+                self.decrypt(&ct, sk).map_err(|_| "failed to decrypt legacy ciphertext".to_string())
+            }
+            Some(version) => Err(format!("unsupported share envelope version {}", version)),
+            None => Err("empty encrypted share".to_string()),
+        }
+    }
+
 }
 
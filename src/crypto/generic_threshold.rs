@@ -1,14 +1,10 @@
-use bls::{Signature, PublicKey, SecretKey, Keypair, SECRET_KEY_BYTES_LEN};
+use bls::{Signature, PublicKey, SecretKey, Keypair};
 use types::{Hash256}; 
 use crate::utils::error::{DvfError};
 use log::{error};
-use crate::crypto::define::{MODULUS};
-use num_bigint::ToBigInt;
+use crate::crypto::scalar::Scalar;
 use crate::math::polynomial::Polynomial;
-use num_bigint::{BigInt, Sign};
-use crate::math::bigint_ext::Ring;
 use crate::utils::rand_utils::RandUtilsRng;
-use crate::utils::rand_utils::Sample;
 use std::collections::{HashSet, HashMap};
 
 
@@ -19,6 +15,9 @@ pub trait TThresholdSignature: Sized + Clone {
 
     fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Signature;
 
+    /// Whether all `sigs` are valid signatures of `msg` under the matching `pks`.
+    fn batch_verify(&self, sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> bool;
+
     fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> Result<Signature, DvfError>;
 }
 
@@ -52,25 +51,8 @@ where
 
     pub fn key_split(&mut self, sk: &SecretKey, ids: &[u64]) -> Result<HashMap<u64, Keypair>, DvfError> {
         let mut rng = RandUtilsRng::new();
-
-        let mut coeffs: Vec<BigInt> = rng.sample_vec(self.threshold(), &MODULUS);
-        coeffs[0] = BigInt::from_bytes_be(Sign::Plus, &sk.serialize().as_bytes()); 
-        let poly = Polynomial::new(coeffs);
-
-        let mut kps: HashMap<u64, Keypair> = HashMap::new();
-        for i in 0..ids.len() {
-            if ids[i] == 0 {
-                return Err(DvfError::KeyGenError(format!("Invalid id {}", ids[i])));
-            }
-
-            let (_, mut sk_share) = poly.eval(&(&ids[i]).to_bigint().unwrap()).reduce(&MODULUS).to_bytes_be();
-            if sk_share.len() < SECRET_KEY_BYTES_LEN {
-                (0..SECRET_KEY_BYTES_LEN-sk_share.len()).for_each(|_| sk_share.insert(0, 0));
-            }
-            let sk_share = SecretKey::deserialize(&sk_share[..]).unwrap();
-            kps.insert(ids[i], Keypair::from_components(sk_share.public_key(), sk_share));
-        }
-        Ok(kps)
+        let poly = self.split_polynomial(sk, &mut rng)?;
+        split_keys(&poly, ids)
     }
 
     /// Split the key in a deterministic way.  
@@ -79,25 +61,8 @@ where
     pub fn deterministic_key_split(&mut self, sk: &SecretKey, ids: &[u64]) -> Result<HashMap<u64, Keypair>, DvfError> {
         let seed: [u8; 32] = [0; 32];
         let mut rng = RandUtilsRng::from_seed(&seed);
-
-        let mut coeffs: Vec<BigInt> = rng.sample_vec(self.threshold(), &MODULUS);
-        coeffs[0] = BigInt::from_bytes_be(Sign::Plus, &sk.serialize().as_bytes()); 
-        let poly = Polynomial::new(coeffs);
-
-        let mut kps: HashMap<u64, Keypair> = HashMap::new();
-        for i in 0..ids.len() {
-            if ids[i] == 0 {
-                return Err(DvfError::KeyGenError(format!("Invalid id {}", ids[i])));
-            }
-
-            let (_, mut sk_share) = poly.eval(&(&ids[i]).to_bigint().unwrap()).reduce(&MODULUS).to_bytes_be();
-            if sk_share.len() < SECRET_KEY_BYTES_LEN {
-                (0..SECRET_KEY_BYTES_LEN-sk_share.len()).for_each(|_| sk_share.insert(0, 0));
-            }
-            let sk_share = SecretKey::deserialize(&sk_share[..]).unwrap();
-            kps.insert(ids[i], Keypair::from_components(sk_share.public_key(), sk_share));
-        }
-        Ok(kps)
+        let poly = self.split_polynomial(sk, &mut rng)?;
+        split_keys(&poly, ids)
     }
 
     /// Compute the key share in a deterministic way.  
@@ -110,17 +75,18 @@ where
         
         let seed: [u8; 32] = [0; 32];
         let mut rng = RandUtilsRng::from_seed(&seed);
+        let poly = self.split_polynomial(sk, &mut rng).expect("Invalid secret key");
+        split_keys(&poly, &[id]).expect("Invalid key share").remove(&id).unwrap()
+    }
 
-        let mut coeffs: Vec<BigInt> = rng.sample_vec(self.threshold(), &MODULUS);
-        coeffs[0] = BigInt::from_bytes_be(Sign::Plus, &sk.serialize().as_bytes()); 
-        let poly = Polynomial::new(coeffs);
-
-        let (_, mut sk_share) = poly.eval(&(id).to_bigint().unwrap()).reduce(&MODULUS).to_bytes_be();
-        if sk_share.len() < SECRET_KEY_BYTES_LEN {
-            (0..SECRET_KEY_BYTES_LEN-sk_share.len()).for_each(|_| sk_share.insert(0, 0));
+    /// A random polynomial of degree `threshold - 1` whose constant term is `sk`.
+    fn split_polynomial(&self, sk: &SecretKey, rng: &mut RandUtilsRng) -> Result<Polynomial<Scalar>, DvfError> {
+        if self.threshold() == 0 {
+            return Err(DvfError::KeyGenError("Invalid threshold 0".to_string()));
         }
-        let sk_share = SecretKey::deserialize(&sk_share[..]).unwrap();
-        Keypair::from_components(sk_share.public_key(), sk_share)
+        let mut coeffs = Scalar::random_vec(&mut rng.rng, self.threshold());
+        coeffs[0] = Scalar::from_secret_key(sk);
+        Ok(Polynomial::new(coeffs))
     }
 
     pub fn threshold_aggregate(&self, sigs: &[&Signature], pks: &[&PublicKey], ids: &[u64], msg: Hash256) -> Result<Signature, DvfError> {
@@ -134,26 +100,37 @@ where
             return Err(DvfError::InsufficientSignatures{got: sigs.len(), expected: self.threshold()}); 
         }
 
-        let mut pks_valid: Vec<&PublicKey> = Vec::new();
-        let mut sigs_valid: Vec<&Signature> = Vec::new();
-        let mut ids_valid: Vec<u64> = Vec::new();
-        let mut valid_set: HashSet<u64> = HashSet::new();
-
-        let total = sigs.len();
-        for i in 0..total {
+        let mut candidates: Vec<usize> = Vec::new();
+        let mut candidate_set: HashSet<u64> = HashSet::new();
+        for i in 0..sigs.len() {
             if ids[i] == 0 {
                 return Err(DvfError::InvalidOperatorId {id: ids[i]});
             }
-            if valid_set.contains(&ids[i]) {
-                continue;
+            if candidate_set.insert(ids[i]) {
+                candidates.push(i);
             }
+        }
+
+        // Usually all signatures are valid, so the first `threshold` of them are checked together.
+        // Only if that fails every signature is checked on its own to find the faulty operators.
+        if candidates.len() >= self.threshold() {
+            let batch = &candidates[..self.threshold()];
+            let batch_sigs: Vec<&Signature> = batch.iter().map(|i| sigs[*i]).collect();
+            let batch_pks: Vec<&PublicKey> = batch.iter().map(|i| pks[*i]).collect();
+            if self.batch_verify(&batch_sigs, &batch_pks, msg) {
+                let batch_ids: Vec<u64> = batch.iter().map(|i| ids[*i]).collect();
+                return Ok(self.unsafe_aggregate(&batch_sigs, &batch_ids[..]));
+            }
+        }
+
+        let mut sigs_valid: Vec<&Signature> = Vec::new();
+        let mut ids_valid: Vec<u64> = Vec::new();
+        for i in candidates {
             let status = sigs[i].verify(pks[i], msg);
             if status {
-                pks_valid.push(pks[i]);
                 sigs_valid.push(sigs[i]);
                 ids_valid.push(ids[i]);
-                valid_set.insert(ids[i]);
-                if pks_valid.len() >= self.threshold() {
+                if sigs_valid.len() >= self.threshold() {
                     break
                 }
             }
@@ -161,8 +138,8 @@ where
                 error!("Invalid signature from operator {}", ids[i]);
             }
         }
-        if pks_valid.len() < self.threshold() {
-            return Err(DvfError::InsufficientValidSignatures{got: pks_valid.len(), expected: self.threshold()}); 
+        if sigs_valid.len() < self.threshold() {
+            return Err(DvfError::InsufficientValidSignatures{got: sigs_valid.len(), expected: self.threshold()}); 
         }
 
         Ok(self.unsafe_aggregate(&sigs_valid, &ids_valid[..]))
@@ -171,5 +148,20 @@ where
     pub fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Signature {
         self.point.unsafe_aggregate(sigs, ids)
     }
+
+    pub fn batch_verify(&self, sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> bool {
+        self.point.batch_verify(sigs, pks, msg)
+    }
 }
 
+fn split_keys(poly: &Polynomial<Scalar>, ids: &[u64]) -> Result<HashMap<u64, Keypair>, DvfError> {
+    let mut kps: HashMap<u64, Keypair> = HashMap::new();
+    for id in ids {
+        if *id == 0 {
+            return Err(DvfError::KeyGenError(format!("Invalid id {}", id)));
+        }
+        let sk_share = poly.eval(&Scalar::from_u64(*id)).to_secret_key()?;
+        kps.insert(*id, Keypair::from_components(sk_share.public_key(), sk_share));
+    }
+    Ok(kps)
+}
@@ -1,11 +1,13 @@
 use bls::{Signature, PublicKey, Hash256};
 use crate::{
     crypto::generic_threshold::{TThresholdSignature},
+    crypto::scalar::lagrange_coeffs_at_zero,
 };
 use bls::{INFINITY_SIGNATURE};
-use crate::utils::error::{require, DvfError};
+use crate::utils::error::DvfError;
 pub use blst::min_pk as blst_core;
-use blst::{blst_scalar, blst_p2, blst_p2_affine};
+use blst::{blst_scalar, blst_p2, blst_p2_affine, BLST_ERROR};
+use rand::Rng;
 
 pub const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
 pub const RAND_BITS: usize = 64;
@@ -24,28 +26,6 @@ fn u64_to_blst_scalar (v: u64) -> blst_scalar {
     }
 }
 
-fn lagrange_coeffs(coeffs: &mut [blst_scalar], x: &[u64]) {
-    require(coeffs.len() == x.len(), "Different length");
-    let n = coeffs.len();
-    for i in 0..n {
-        let mut p = u64_to_blst_scalar(1);
-        let xi = u64_to_blst_scalar(x[i]);
-        for j in 0..n {
-            if i!=j {
-                let xj = u64_to_blst_scalar(x[j]); 
-                let mut tmp = std::mem::MaybeUninit::<blst_scalar>::uninit();
-                unsafe {
-                    blst::blst_sk_sub_n_check(tmp.as_mut_ptr(), &xj, &xi);
-                    blst::blst_sk_inverse(tmp.as_mut_ptr(), tmp.as_ptr());
-                    blst::blst_sk_mul_n_check(tmp.as_mut_ptr(), &xj, tmp.as_ptr());
-                    blst::blst_sk_mul_n_check(&mut p, &p, tmp.as_ptr());
-                }
-            }
-        }
-        coeffs[i] = p;
-    }
-}
-
 pub struct BlstThresholdSignature(blst_core::AggregateSignature, usize);
 
 impl Clone for BlstThresholdSignature {
@@ -73,8 +53,10 @@ impl TThresholdSignature for BlstThresholdSignature {
     }
 
     fn unsafe_aggregate(&self, sigs: &[&Signature], ids: &[u64]) -> Signature {
-        let mut coeffs = vec![blst_scalar::default(); self.threshold()];
-        lagrange_coeffs(&mut coeffs, &ids);
+        let coeffs: Vec<blst_scalar> = lagrange_coeffs_at_zero(&ids[..self.threshold()])
+            .iter()
+            .map(|c| c.to_blst_scalar())
+            .collect();
 
         let mut agg = Self::infinity(self.1);
         for i in 0..self.threshold() {
@@ -94,6 +76,46 @@ impl TThresholdSignature for BlstThresholdSignature {
         Signature::deserialize(&agg.0.to_signature().to_bytes()).unwrap()
     }
 
+    /// Randomized batch verification: all signatures are checked with a single multi-pairing,
+    /// each weighted by a random 64-bit scalar so that invalid signatures can't cancel out.
+    fn batch_verify(&self, sigs: &[&Signature], pks: &[&PublicKey], msg: Hash256) -> bool {
+        if sigs.len() != pks.len() || sigs.is_empty() {
+            return false;
+        }
+        let sigs = match sigs
+            .iter()
+            .map(|sig| blst_core::Signature::from_bytes(&sig.serialize()))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(sigs) => sigs,
+            Err(_) => return false,
+        };
+        let pks = match pks
+            .iter()
+            .map(|pk| blst_core::PublicKey::from_bytes(&pk.serialize()))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(pks) => pks,
+            Err(_) => return false,
+        };
+        let mut rng = rand::thread_rng();
+        let rands: Vec<blst_scalar> = (0..sigs.len())
+            .map(|_| {
+                let mut r = 0u64;
+                while r == 0 {
+                    r = rng.gen();
+                }
+                u64_to_blst_scalar(r)
+            })
+            .collect();
+        let msgs: Vec<&[u8]> = vec![msg.as_bytes(); sigs.len()];
+        let sigs_ref: Vec<&blst_core::Signature> = sigs.iter().collect();
+        let pks_ref: Vec<&blst_core::PublicKey> = pks.iter().collect();
+        blst_core::Signature::verify_multiple_aggregate_signatures(
+            &msgs, DST, &pks_ref, true, &sigs_ref, true, &rands, RAND_BITS,
+        ) == BLST_ERROR::BLST_SUCCESS
+    }
+
     fn threshold_aggregate(&self, _sigs: &[&Signature], _pks: &[&PublicKey], _msg: Hash256) -> Result<Signature, DvfError> {
         Err(DvfError::UnexpectedCall(String::from("threshold_aggregate"))) 
     }
@@ -1,4 +1,4 @@
-pub mod define;
+pub mod scalar;
 pub mod generic_threshold;
 pub mod impls;
 pub mod elgamal;
@@ -0,0 +1,245 @@
+use crate::utils::error::DvfError;
+use bls::SecretKey;
+use blst::{blst_fr, blst_scalar};
+use num_traits::Zero;
+use rand::RngCore;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use zeroize::Zeroize;
+
+pub const SCALAR_BYTES_LEN: usize = 32;
+
+/// An element of the scalar field of BLS12-381, i.e. an integer modulo the group order `r`.
+///
+/// Arithmetic uses blst's `blst_fr` operations, which run in constant time, and the value is
+/// zeroized when dropped, so secret shares and polynomial coefficients can be kept in it.
+#[derive(Clone, Default)]
+pub struct Scalar(blst_fr);
+
+impl Scalar {
+    pub fn zero() -> Self {
+        Self(blst_fr::default())
+    }
+
+    pub fn one() -> Self {
+        Self::from_u64(1)
+    }
+
+    pub fn from_u64(v: u64) -> Self {
+        let limbs = [v, 0, 0, 0];
+        let mut out = blst_fr::default();
+        unsafe {
+            blst::blst_fr_from_uint64(&mut out, limbs.as_ptr());
+        }
+        Self(out)
+    }
+
+    /// A uniformly random scalar, drawn by rejection the way the `num-bigint` sampling of earlier
+    /// versions did, so that the same RNG stream gives the same scalars: 32 random bytes are read
+    /// as a little-endian integer of 32-bit limbs, the top limb is shifted down to the 255 bits of
+    /// `r`, and values that aren't less than `r` are drawn again.
+    pub fn random<R: RngCore + ?Sized>(rng: &mut R) -> Self {
+        let mut bytes = [0u8; SCALAR_BYTES_LEN];
+        loop {
+            rng.fill_bytes(&mut bytes);
+            let top = u32::from_le_bytes([bytes[28], bytes[29], bytes[30], bytes[31]]) >> 1;
+            bytes[28..].copy_from_slice(&top.to_le_bytes());
+            bytes.reverse();
+            if let Some(scalar) = Self::from_bytes_be(&bytes) {
+                bytes.zeroize();
+                return scalar;
+            }
+        }
+    }
+
+    pub fn random_vec<R: RngCore + ?Sized>(rng: &mut R, len: usize) -> Vec<Self> {
+        (0..len).map(|_| Self::random(rng)).collect()
+    }
+
+    /// Parses a 32-byte big-endian integer, which must be less than `r`.
+    pub fn from_bytes_be(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SCALAR_BYTES_LEN {
+            return None;
+        }
+        let mut scalar = blst_scalar::default();
+        let mut out = blst_fr::default();
+        let valid = unsafe {
+            blst::blst_scalar_from_bendian(&mut scalar, bytes.as_ptr());
+            let valid = blst::blst_scalar_fr_check(&scalar);
+            blst::blst_fr_from_scalar(&mut out, &scalar);
+            valid
+        };
+        scalar.b.zeroize();
+        if valid {
+            Some(Self(out))
+        } else {
+            out.l.zeroize();
+            None
+        }
+    }
+
+    /// The 32-byte big-endian encoding, e.g. of a BLS secret key.
+    pub fn to_bytes_be(&self) -> [u8; SCALAR_BYTES_LEN] {
+        let mut scalar = self.to_blst_scalar();
+        let mut out = [0u8; SCALAR_BYTES_LEN];
+        unsafe {
+            blst::blst_bendian_from_scalar(out.as_mut_ptr(), &scalar);
+        }
+        scalar.b.zeroize();
+        out
+    }
+
+    pub fn from_secret_key(sk: &SecretKey) -> Self {
+        Self::from_bytes_be(sk.serialize().as_bytes()).expect("secret keys are less than r")
+    }
+
+    /// Fails for zero, which isn't a valid secret key.
+    pub fn to_secret_key(&self) -> Result<SecretKey, DvfError> {
+        let mut bytes = self.to_bytes_be();
+        let sk = SecretKey::deserialize(&bytes)
+            .map_err(|e| DvfError::KeyGenError(format!("invalid secret key {:?}", e)));
+        bytes.zeroize();
+        sk
+    }
+
+    /// The little-endian `blst_scalar` that blst's point multiplications take.
+    pub fn to_blst_scalar(&self) -> blst_scalar {
+        let mut out = blst_scalar::default();
+        unsafe {
+            blst::blst_scalar_from_fr(&mut out, &self.0);
+        }
+        out
+    }
+
+    /// The multiplicative inverse, `None` for zero.
+    pub fn inverse(&self) -> Option<Self> {
+        if self.is_zero() {
+            return None;
+        }
+        let mut out = blst_fr::default();
+        unsafe {
+            blst::blst_fr_inverse(&mut out, &self.0);
+        }
+        Some(Self(out))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.l.iter().fold(0u64, |acc, limb| acc | limb) == 0
+    }
+}
+
+/// Lagrange coefficients for interpolating at 0 from the evaluations at `ids`.
+pub fn lagrange_coeffs_at_zero(ids: &[u64]) -> Vec<Scalar> {
+    ids.iter()
+        .enumerate()
+        .map(|(i, xi)| {
+            let mut numerator = Scalar::one();
+            let mut denominator = Scalar::one();
+            for (j, xj) in ids.iter().enumerate() {
+                if i != j {
+                    numerator *= &Scalar::from_u64(*xj);
+                    denominator *= &(Scalar::from_u64(*xj) - Scalar::from_u64(*xi));
+                }
+            }
+            // ids are distinct, so the denominator isn't zero
+            numerator * denominator.inverse().unwrap_or_default()
+        })
+        .collect()
+}
+
+impl Drop for Scalar {
+    fn drop(&mut self) {
+        self.0.l.zeroize();
+    }
+}
+
+impl PartialEq for Scalar {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .l
+            .iter()
+            .zip(other.0.l.iter())
+            .fold(0u64, |acc, (a, b)| acc | (a ^ b))
+            == 0
+    }
+}
+
+impl Eq for Scalar {}
+
+impl std::fmt::Debug for Scalar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Scalar(..)")
+    }
+}
+
+impl<'a> AddAssign<&'a Scalar> for Scalar {
+    fn add_assign(&mut self, rhs: &'a Scalar) {
+        // blst allows the output to alias an input
+        let out: *mut blst_fr = &mut self.0;
+        unsafe {
+            blst::blst_fr_add(out, out, &rhs.0);
+        }
+    }
+}
+
+impl<'a> SubAssign<&'a Scalar> for Scalar {
+    fn sub_assign(&mut self, rhs: &'a Scalar) {
+        let out: *mut blst_fr = &mut self.0;
+        unsafe {
+            blst::blst_fr_sub(out, out, &rhs.0);
+        }
+    }
+}
+
+impl<'a> MulAssign<&'a Scalar> for Scalar {
+    fn mul_assign(&mut self, rhs: &'a Scalar) {
+        let out: *mut blst_fr = &mut self.0;
+        unsafe {
+            blst::blst_fr_mul(out, out, &rhs.0);
+        }
+    }
+}
+
+impl Add for Scalar {
+    type Output = Scalar;
+
+    fn add(mut self, rhs: Scalar) -> Scalar {
+        self += &rhs;
+        self
+    }
+}
+
+impl Sub for Scalar {
+    type Output = Scalar;
+
+    fn sub(mut self, rhs: Scalar) -> Scalar {
+        self -= &rhs;
+        self
+    }
+}
+
+impl Mul for Scalar {
+    type Output = Scalar;
+
+    fn mul(mut self, rhs: Scalar) -> Scalar {
+        self *= &rhs;
+        self
+    }
+}
+
+impl Neg for Scalar {
+    type Output = Scalar;
+
+    fn neg(self) -> Scalar {
+        Scalar::zero() - self
+    }
+}
+
+impl Zero for Scalar {
+    fn zero() -> Self {
+        Scalar::zero()
+    }
+
+    fn is_zero(&self) -> bool {
+        Scalar::is_zero(self)
+    }
+}
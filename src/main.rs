@@ -17,6 +17,8 @@ use std::path::PathBuf;
 use std::process::exit;
 use task_executor::ShutdownReason;
 use types::{EthSpec, EthSpecId};
+use dvf::node::node::Node;
+use dvf::node::resync::Resync;
 use dvf::validation::ProductionValidatorClient;
 use std::io::Write;
 use chrono::Local;
@@ -561,7 +563,35 @@ fn run<E: EthSpec>(
             let shutdown_flag = matches.is_present("immediate-shutdown");
             // Dump configs if `dump-config` or `dump-chain-config` flags are set
             clap_utils::check_dump_configs::<_, E>(matches, &config, &context.eth2_config.spec)?;
-            if !shutdown_flag {
+            if let Some(from_block) = config.resync_from_block {
+                executor.clone().spawn(
+                    async move {
+                        let base_dir = config.secrets_dir.parent().unwrap().to_path_buf();
+                        let result = match Node::<E>::open_or_create_secret(config.dvf_node_config.node_key_path.clone()) {
+                            Ok(secret) => {
+                                Resync::new(base_dir, config.validator_dir.clone(), config.secrets_dir.clone())
+                                    .run(secret.name, from_block)
+                                    .await
+                            }
+                            Err(e) => Err(format!("Can't read node key {:?}", e)),
+                        };
+                        match result {
+                            Ok(_) => {
+                                let _ = executor.shutdown_sender().try_send(ShutdownReason::Success(
+                                    "Resync finished.",
+                                ));
+                            }
+                            Err(e) => {
+                                crit!(log, "Failed to resync"; "reason" => e);
+                                let _ = executor.shutdown_sender().try_send(ShutdownReason::Failure(
+                                    "Failed to resync",
+                                ));
+                            }
+                        }
+                    },
+                    "resync",
+                );
+            } else if !shutdown_flag {
                 executor.clone().spawn(
                     async move {
                         if let Err(e) = ProductionValidatorClient::new(context, config)
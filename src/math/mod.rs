@@ -1,3 +1,2 @@
 pub mod polynomial;
-pub mod bigint_ext;
 
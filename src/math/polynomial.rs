@@ -2,7 +2,7 @@ use num_traits::Zero;
 use std::ops::{MulAssign, AddAssign};
 
 /// A simple polynomial implementation by referring to: polynomial-ring (https://lib.rs/crates/polynomial-ring)
-/// Their implementation requires trait 'Sized' for the coefficient type, which is not good for BigInt.
+/// Their implementation requires trait 'Sized' for the coefficient type, which is not good for Scalar.
 pub struct Polynomial<T: Zero> {
     coeffs: Vec<T>
 }
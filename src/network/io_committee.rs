@@ -1,5 +1,6 @@
 use tokio::sync::mpsc::{channel, Receiver, Sender};
 use crate::DEFAULT_CHANNEL_CAPACITY;
+use crate::node::config::{port_mode, PortMode};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use bytes::Bytes;
@@ -8,14 +9,19 @@ use tokio::sync::{RwLock};
 use futures::stream::{SplitSink, SplitStream};
 use futures::stream::StreamExt as _;
 use tokio::net::{TcpListener, TcpStream};
-use tokio_util::codec::{Framed, LengthDelimitedCodec};
 use futures::SinkExt;
 use std::net::{SocketAddr};
 use log::{info, warn, debug};
 use tokio::task::JoinHandle;
-use tokio::sync::{Notify};
-use tokio::time::{sleep, Duration};
+use tokio::sync::{Notify, Mutex as AsyncMutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::{sleep, timeout, Duration};
 use std::cmp::min;
+use std::sync::{Mutex, Weak};
+use lazy_static::lazy_static;
+use secp256k1::{PublicKey, SecretKey};
+use serde_derive::{Deserialize, Serialize};
+use network::{handshake_with, Identity, PeerKey, Transport};
 
 
 #[async_trait]
@@ -58,128 +64,436 @@ impl IOChannel for MemIOChannel {
     }
 }
 
+/// Identifies a committee session, so that concurrent sessions of a party can share one port.
+pub type SessionId = [u8; 32];
+
+/// The session id of a committee among `ids` for `purpose`, e.g. the DKG of an initializer.
+pub fn session_id(purpose: &str, ids: &[u64]) -> SessionId {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    let mut bytes = purpose.as_bytes().to_vec();
+    for id in ids {
+        bytes.extend_from_slice(&id.to_be_bytes());
+    }
+    let mut session = [0u8; 32];
+    session.copy_from_slice(&eth2_hashing::hash(&bytes));
+    session
+}
+
+type Sessions = RwLock<HashMap<SessionId, Arc<NetIOIdentity>>>;
+
+/// Sent by the dialing side before the handshake when it connects to the base port of a peer, so
+/// that DKG connections can share that port with the other channels of the node.
+pub const CONNECTION_PREAMBLE: &[u8] = b"dvf-dkg\0";
+
+type Incoming = Arc<AsyncMutex<Receiver<(TcpStream, SocketAddr)>>>;
+
+lazy_static! {
+    static ref CONNECTION_MANAGERS: Mutex<HashMap<u16, Weak<ConnectionManager>>> = Mutex::new(HashMap::new());
+    // connections for the managers on a port that are accepted by another listener
+    static ref FORWARDED_CONNECTIONS: Mutex<HashMap<u16, Incoming>> = Mutex::new(HashMap::new());
+}
+
+/// Let the connection managers on `port` take their connections from the returned sender instead
+/// of listening on `port` themselves, e.g. from the multiplexed listener of the node.
+pub fn forward_connections(port: u16) -> Sender<(TcpStream, SocketAddr)> {
+    let (sender, receiver) = channel(DEFAULT_CHANNEL_CAPACITY);
+    FORWARDED_CONNECTIONS.lock().unwrap().insert(port, Arc::new(AsyncMutex::new(receiver)));
+    sender
+}
+
+async fn skip_preamble(socket: &mut TcpStream) -> std::io::Result<()> {
+    let mut preamble = [0u8; CONNECTION_PREAMBLE.len()];
+    loop {
+        let n = socket.peek(&mut preamble).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        if preamble[..n] != CONNECTION_PREAMBLE[..n] {
+            return Ok(());
+        }
+        if n == preamble.len() {
+            return socket.read_exact(&mut preamble).await.map(|_| ());
+        }
+        // wait for the rest of the preamble
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Accept DKG connections on `address` and pass them to `sender`. Nodes that address us at our
+/// base port send a preamble first, which is skipped; the other ones start the handshake directly.
+pub async fn listen(address: SocketAddr, sender: Sender<(TcpStream, SocketAddr)>) {
+    // the listener of a manager that was just shut down may still hold the port
+    let mut retry = 0;
+    let listener = loop {
+        match TcpListener::bind(&address).await {
+            Ok(listener) => break listener,
+            Err(e) if retry < 10 => {
+                warn!("Failed to bind TCP address {}: {}. Try({}).", address, e, retry);
+                sleep(Duration::from_millis(200)).await;
+                retry += 1;
+            }
+            Err(e) => panic!("Failed to bind TCP address {}: {}", address, e),
+        }
+    };
+
+    info!("Listening on {}. [DKG]", address);
+    loop {
+        let (mut socket, peer_address) = match listener.accept().await {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match timeout(HANDSHAKE_TIMEOUT, skip_preamble(&mut socket)).await {
+                Ok(Ok(())) => {
+                    let _ = sender.send((socket, peer_address)).await;
+                }
+                Ok(Err(e)) => warn!("Failed to read from {}: {}", peer_address, e),
+                Err(_) => warn!("Handshake with {} timed out", peer_address),
+            }
+        });
+    }
+}
+
 pub struct ConnectionManager {
     /// Address to listen to.
     _address: SocketAddr,
+    party: u64,
+    /// Identities of the open sessions, connections are only accepted for them.
+    sessions: Arc<Sessions>,
     /// Struct responsible to define how to handle received messages.
-    connections: Arc<RwLock<HashMap<u64, NetIOChannel>>>,
-    notifications: Arc<RwLock<HashMap<u64, Arc<Notify>>>>,
+    connections: Arc<RwLock<HashMap<(SessionId, u64), NetIOChannel>>>,
+    notifications: Arc<RwLock<HashMap<(SessionId, u64), Arc<Notify>>>>,
     thread_handle: JoinHandle<()>,
+    listener_handle: Option<JoinHandle<()>>,
 }
 
 impl Drop for ConnectionManager {
     fn drop(&mut self) {
         info!("Shutting down connection manager");
         self.thread_handle.abort();
+        if let Some(listener_handle) = self.listener_handle.as_ref() {
+            listener_handle.abort();
+        }
     }
 }
 
 impl ConnectionManager {
-    pub fn new(address: SocketAddr) -> Self {
-        let connections: Arc<RwLock<HashMap<u64, NetIOChannel>>> = 
+    /// The connection manager of `party` listening on `port`. It is shared by all sessions of the
+    /// party on that port, and shuts down once none of them is left.
+    pub fn shared(party: u64, port: u16) -> Arc<Self> {
+        let mut managers = CONNECTION_MANAGERS.lock().unwrap();
+        if let Some(manager) = managers.get(&port).and_then(|manager| manager.upgrade()) {
+            if manager.party != party {
+                panic!("Port {} is used by party {}", port, manager.party);
+            }
+            return manager;
+        }
+        let manager = Arc::new(Self::new(SocketAddr::new("0.0.0.0".parse().unwrap(), port), party));
+        managers.insert(port, Arc::downgrade(&manager));
+        manager
+    }
+
+    fn new(address: SocketAddr, party: u64) -> Self {
+        let sessions: Arc<Sessions> = Arc::new(RwLock::new(HashMap::default()));
+        let sessions_clone = sessions.clone();
+
+        let connections: Arc<RwLock<HashMap<(SessionId, u64), NetIOChannel>>> = 
             Arc::new(RwLock::new(HashMap::default()));
         let connections_clone = connections.clone();
 
-        let notifications: Arc<RwLock<HashMap<u64, Arc<Notify>>>> = 
+        let notifications: Arc<RwLock<HashMap<(SessionId, u64), Arc<Notify>>>> = 
         Arc::new(RwLock::new(HashMap::default()));
         let notifications_clone = notifications.clone();
 
-        let address_clone = address.clone();
+        let forwarded = FORWARDED_CONNECTIONS.lock().unwrap().get(&address.port()).cloned();
+        let (incoming, listener_handle) = match forwarded {
+            Some(incoming) => (incoming, None),
+            None => {
+                let (sender, receiver) = channel(DEFAULT_CHANNEL_CAPACITY);
+                (Arc::new(AsyncMutex::new(receiver)), Some(tokio::spawn(listen(address, sender))))
+            }
+        };
         let thread_handle = tokio::spawn(async move {
-            let listener = TcpListener::bind(&address_clone)
-                .await
-                .expect(format!("Failed to bind TCP address {}", address_clone).as_str());
-
-            info!("Listening on {}. [DKG]", address_clone);
-            loop {
-                let (socket, _peer) = match listener.accept().await {
-                    Ok(value) => value,
-                    Err(e) => {
-                        warn!("Failed to accept connection: {}", e);
-                        continue;
+            let mut incoming = incoming.lock().await;
+            while let Some((socket, peer_address)) = incoming.recv().await {
+                let sessions = sessions_clone.clone();
+                let connections = connections_clone.clone();
+                let notifications = notifications_clone.clone();
+                tokio::spawn(async move {
+                    let (session, peer, channel) = match timeout(HANDSHAKE_TIMEOUT, handshake(socket, peer_address, party, None, &sessions)).await {
+                        Ok(Ok(value)) => value,
+                        Ok(Err(e)) => {
+                            warn!("Rejected connection from {}: {}", peer_address, e.as_str());
+                            return;
+                        }
+                        Err(_) => {
+                            warn!("Handshake with {} timed out", peer_address);
+                            return;
+                        }
+                    };
+                    let mut connections = connections.write().await;
+                    let mut notifications = notifications.write().await;
+                    connections.insert((session, peer), channel);
+                    if let Some(notify) = notifications.get(&(session, peer)) {
+                        notify.notify_one();
                     }
-                };
-                let channel = NetIOChannel::new(socket);
-                let peer = bincode::deserialize::<u64>(&channel.recv().await[..]).unwrap();
-                let mut connections = connections_clone.write().await;
-                let mut notifications = notifications_clone.write().await;
-                connections.insert(peer, channel);
-                if let Some(notify) = notifications.get(&peer) {
-                    notify.notify_one();
-                }
-                else {
-                    let notify = Arc::new(Notify::new());
-                    notify.notify_one();
-                    notifications.insert(peer, notify);
-                }
+                    else {
+                        let notify = Arc::new(Notify::new());
+                        notify.notify_one();
+                        notifications.insert((session, peer), notify);
+                    }
+                });
             }
         });
 
         Self {
             _address: address,
+            party,
+            sessions,
             connections,
             notifications,
             thread_handle,
+            listener_handle,
+        }
+    }
+
+    /// Start accepting connections for `session`, authenticated with the keys in `identity`.
+    pub async fn open_session(&self, session: SessionId, identity: NetIOIdentity) -> Result<(), String> {
+        let mut sessions = self.sessions.write().await;
+        if sessions.contains_key(&session) {
+            return Err(format!("Session {} is already open", hex::encode(session)));
         }
+        // connections are authenticated before the session is known, with the key of the party
+        if sessions.values().any(|open| open.identity.public_key() != identity.identity.public_key()) {
+            return Err(format!("Session {} uses another key than the open sessions", hex::encode(session)));
+        }
+        sessions.insert(session, Arc::new(identity));
+        Ok(())
+    }
+
+    /// Stop accepting connections for `session` and drop the ones that were not taken.
+    pub async fn close_session(&self, session: SessionId) {
+        self.sessions.write().await.remove(&session);
+        self.connections.write().await.retain(|(s, _), _| *s != session);
+        self.notifications.write().await.retain(|(s, _), _| *s != session);
     }
 
-    /// Connect from `party` to a peer with `peer_address`.
-    /// The `party` id is sent to the peer right after connection to identify itself.
-    pub async fn connect(party: u64, peer_address: SocketAddr) -> Option<NetIOChannel> {
+    /// Connect to `peer` with `peer_address` in `session`.
+    /// Both sides authenticate with their keys of the session right after connection.
+    pub async fn connect(&self, session: SessionId, peer: u64, peer_address: SocketAddr) -> Option<NetIOChannel> {
         let mut delay = 200;
         let mut retry = 0;
         loop {
             match TcpStream::connect(peer_address).await {
-                Ok(stream) => {
-                    let channel = NetIOChannel::new(stream);
-                    channel.send(Bytes::from(bincode::serialize(&party).unwrap())).await;
-                    return Some(channel);
+                Ok(mut stream) => {
+                    if port_mode() != PortMode::PerPort && stream.write_all(CONNECTION_PREAMBLE).await.is_err() {
+                        warn!("connection-manager: Failed to connect to {}. Try({}).", peer_address, retry);
+                        sleep(Duration::from_millis(delay)).await;
+                        delay = min(2*delay, 60_000);
+                        retry +=1;
+                        continue;
+                    }
+                    match timeout(HANDSHAKE_TIMEOUT, handshake(stream, peer_address, self.party, Some((session, peer)), &self.sessions)).await {
+                        Ok(Ok((_, _, channel))) => return Some(channel),
+                        Ok(Err(HandshakeError::Rejected(e))) => {
+                            warn!("connection-manager: Failed to authenticate {}: {}", peer_address, e);
+                            return None;
+                        }
+                        Ok(Err(HandshakeError::NotReady(e))) => {
+                            warn!("connection-manager: {} is not ready for the session: {}. Try({}).", peer_address, e, retry);
+                        }
+                        Err(_) => {
+                            warn!("connection-manager: Handshake with {} timed out. Try({}).", peer_address, retry);
+                        }
+                    }
                 },
                 Err(_e) => {
                     warn!("connection-manager: Failed to connect to {}. Try({}).", peer_address, retry);
-                    sleep(Duration::from_millis(delay)).await;
-
-                    // Wait an increasing delay before attempting to reconnect.
-                    delay = min(2*delay, 60_000);
-                    retry +=1;
                 }
             }
+            sleep(Duration::from_millis(delay)).await;
+
+            // Wait an increasing delay before attempting to reconnect.
+            delay = min(2*delay, 60_000);
+            retry +=1;
         }
     }
 
-    /// Accept connection from `peer`
-    pub async fn accept(&mut self, peer: u64) -> Option<NetIOChannel> {
+    /// Accept connection from `peer` in `session`
+    pub async fn accept(&self, session: SessionId, peer: u64) -> Option<NetIOChannel> {
         let notify = {
             let mut notifications = self.notifications.write().await; 
-            if let Some(notify) = notifications.get(&peer) {
+            if let Some(notify) = notifications.get(&(session, peer)) {
                 notify.clone()
             }
             else {
                 let notify = Arc::new(Notify::new());
-                notifications.insert(peer, notify.clone());
+                notifications.insert((session, peer), notify.clone());
                 notify
             }
         };
 
         notify.notified().await;
         let mut notifications = self.notifications.write().await;
-        notifications.remove(&peer);
+        notifications.remove(&(session, peer));
+
+        self.connections.write().await.remove(&(session, peer))
+    }
+}
+
+/// Keys used to authenticate the parties of a network IO committee.
+pub struct NetIOIdentity {
+    identity: Identity,
+    public_keys: HashMap<u64, PeerKey>,
+}
+
+impl NetIOIdentity {
+    /// `public_keys[i]` is the registered operator key of party `ids[i]`.
+    pub fn new(secret_key: SecretKey, ids: &[u64], public_keys: &[PublicKey]) -> Self {
+        Self {
+            identity: Identity::new(&secret_key.secret_bytes()).expect("Secret key is a valid identity"),
+            public_keys: ids.iter().cloned().zip(public_keys.iter().map(|pk| pk.serialize())).collect(),
+        }
+    }
+}
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// how long a responder waits for the session of an incoming connection to be opened
+const SESSION_WAIT: Duration = Duration::from_secs(5);
+
+/// Sent over the authenticated transport to join a session. The responder answers with its own
+/// hello, or with the reason it rejects the initiator.
+#[derive(Serialize, Deserialize)]
+struct Hello {
+    party: u64,
+    session: SessionId,
+}
+
+enum HandshakeError {
+    /// The peer hasn't opened the session yet, the initiator should try again later.
+    NotReady(String),
+    Rejected(String),
+}
+
+impl HandshakeError {
+    fn as_str(&self) -> &str {
+        match self {
+            HandshakeError::NotReady(e) => e.as_str(),
+            HandshakeError::Rejected(e) => e.as_str(),
+        }
+    }
+}
+
+impl From<String> for HandshakeError {
+    fn from(e: String) -> Self {
+        HandshakeError::Rejected(e)
+    }
+}
+
+async fn recv_frame(transport: &mut Transport) -> Result<Bytes, String> {
+    match transport.next().await {
+        Some(Ok(message)) => Ok(message.freeze()),
+        Some(Err(e)) => Err(format!("connection error {}", e)),
+        None => Err("connection closed".to_string()),
+    }
+}
+
+async fn send_frame<M: serde::Serialize>(transport: &mut Transport, message: &M) -> Result<(), String> {
+    let message = Bytes::from(bincode::serialize(message).unwrap());
+    transport.send(message).await.map_err(|e| format!("connection error {}", e))
+}
+
+async fn wait_for_session<T>(sessions: &Sessions, find: impl Fn(&HashMap<SessionId, Arc<NetIOIdentity>>) -> Option<T>) -> Option<T> {
+    let mut waited = Duration::ZERO;
+    loop {
+        if let Some(found) = find(&*sessions.read().await) {
+            return Some(found);
+        }
+        if waited >= SESSION_WAIT {
+            return None;
+        }
+        sleep(Duration::from_millis(100)).await;
+        waited += Duration::from_millis(100);
+    }
+}
 
-        self.connections.write().await.remove(&peer)
+/// Authenticate the peer with the transport handshake of the hotstuff network, then agree on the
+/// session. The initiator knows which session and `peer` it dials, the responder learns them from
+/// the hello message. Either side checks that the other one authenticated with the registered key
+/// of its party.
+async fn handshake(
+    stream: TcpStream,
+    peer_address: SocketAddr,
+    party: u64,
+    dial: Option<(SessionId, u64)>,
+    sessions: &Sessions,
+) -> Result<(SessionId, u64, NetIOChannel), HandshakeError> {
+    match dial {
+        Some((session, peer)) => {
+            let identity = sessions
+                .read()
+                .await
+                .get(&session)
+                .cloned()
+                .ok_or_else(|| format!("session {} is not open", hex::encode(session)))?;
+            // the responder drops the connection until one of its sessions is open
+            let (peer_key, mut transport) = handshake_with(&identity.identity, stream, peer_address, true)
+                .await
+                .map_err(|e| HandshakeError::NotReady(e.to_string()))?;
+            if identity.public_keys.get(&peer) != Some(&peer_key.key) {
+                return Err(format!("party {} failed to authenticate", peer).into());
+            }
+            send_frame(&mut transport, &Hello { party, session }).await?;
+            // the responder closes the connection if it doesn't know the session
+            let reply = recv_frame(&mut transport).await.map_err(HandshakeError::NotReady)?;
+            let peer_hello = bincode::deserialize::<Result<Hello, String>>(&reply).map_err(|e| format!("invalid hello {}", e))??;
+            if peer_hello.party != peer || peer_hello.session != session {
+                return Err(format!("unexpected party {}", peer_hello.party).into());
+            }
+            Ok((session, peer, NetIOChannel::new(transport)))
+        }
+        None => {
+            // all sessions of a party authenticate with the same key
+            let identity = wait_for_session(sessions, |sessions| sessions.values().next().map(|s| s.identity.clone()))
+                .await
+                .ok_or_else(|| "no session is open".to_string())?;
+            let (peer_key, mut transport) = handshake_with(&identity, stream, peer_address, false)
+                .await
+                .map_err(|e| e.to_string())?;
+            let hello = bincode::deserialize::<Hello>(&recv_frame(&mut transport).await?).map_err(|e| format!("invalid hello {}", e))?;
+            let identity = wait_for_session(sessions, |sessions| sessions.get(&hello.session).cloned())
+                .await
+                .ok_or_else(|| format!("session {} is not open", hex::encode(hello.session)))?;
+            let reply = if hello.party == party {
+                Err(format!("unexpected party {}", hello.party))
+            } else if identity.public_keys.get(&hello.party) != Some(&peer_key.key) {
+                Err(format!("party {} failed to authenticate", hello.party))
+            } else {
+                Ok(Hello { party, session: hello.session })
+            };
+            send_frame(&mut transport, &reply).await?;
+            reply?;
+            Ok((hello.session, hello.party, NetIOChannel::new(transport)))
+        }
     }
 }
 
-type Writer = SplitSink<Framed<TcpStream, LengthDelimitedCodec>, Bytes>;
-type Reader = SplitStream<Framed<TcpStream, LengthDelimitedCodec>>;
+type Writer = SplitSink<Transport, Bytes>;
+type Reader = SplitStream<Transport>;
 
+/// A channel to a peer of the committee, whose frames are encrypted by the transport.
 pub struct NetIOChannel {
     writer: Arc<RwLock<Writer>>,
     reader: Arc<RwLock<Reader>>,
 }
 
 impl NetIOChannel {
-    pub fn new(socket: TcpStream) -> Self {
-        let transport = Framed::new(socket, LengthDelimitedCodec::new());
+    fn new(transport: Transport) -> Self {
         let (writer, reader) = transport.split();
         Self {
             writer: Arc::new(RwLock::new(writer)),
@@ -200,11 +514,10 @@ impl IOChannel for NetIOChannel {
 
     async fn recv(&self) -> Bytes {
         let mut reader = self.reader.write().await;
-        if let Some(Ok(message)) = reader.next().await {
-            message.freeze()
-        }
-        else {
-            panic!("Failed to recv message.");
+        match reader.next().await {
+            Some(Ok(message)) => message.freeze(),
+            Some(Err(e)) => panic!("Failed to recv message. Error: {}", e),
+            None => panic!("Failed to recv message."),
         }
     }
 }
@@ -213,6 +526,11 @@ pub trait IOCommittee<T> {
     /// Return a channel that is used to for transmitting message from `from` to `to`.
     fn channel(&self, from: u64, to: u64) -> &T;
     fn ids(&self) -> &[u64];
+    /// The session the channels belong to. Committees whose channels aren't shared by sessions,
+    /// e.g. memory channels, use the zero session.
+    fn session(&self) -> SessionId {
+        [0u8; 32]
+    }
 }
 
 /// Simulate the communication among parties with memory channels.
@@ -259,17 +577,28 @@ impl IOCommittee<MemIOChannel> for MemIOCommittee {
 pub struct NetIOCommittee {
     party: u64,
     ids: Vec<u64>,
+    session: SessionId,
+    connection_manager: Arc<ConnectionManager>,
     channels: HashMap<u64, NetIOChannel>,
 }
 
 impl NetIOCommittee {
     /// Construct a network IO committee for `party` who is listening on `port`.
-    /// The committee is identified by the id set `ids` and the address set `addresses`.
-    pub async fn new(party: u64, port: u16, ids: &[u64], addresses: &[SocketAddr]) -> NetIOCommittee {
+    /// The committee is identified by `session`, the id set `ids` and the address set `addresses`,
+    /// and its parties authenticate each other with the keys in `identity`. Committees with
+    /// different sessions can run at the same time on one port.
+    pub async fn new(party: u64, port: u16, session: SessionId, ids: &[u64], addresses: &[SocketAddr], identity: NetIOIdentity) -> Result<NetIOCommittee, String> {
         debug!("{:?}", ids);
         debug!("{:?}", addresses);
-        let mut connection_manager = ConnectionManager::new(SocketAddr::new("0.0.0.0".parse().unwrap(), port));
-        let mut channels: HashMap<u64, NetIOChannel> = Default::default();
+        let connection_manager = ConnectionManager::shared(party, port);
+        connection_manager.open_session(session, identity).await?;
+        let mut committee = Self {
+            party,
+            ids: ids.to_vec(),
+            session,
+            connection_manager,
+            channels: Default::default(),
+        };
         let n = ids.len();
         for i in 0..n {
             if ids[i] == party {
@@ -277,18 +606,25 @@ impl NetIOCommittee {
             }
             let channel = {
                 if ids[i] < party {
-                    ConnectionManager::connect(party, addresses[i]).await.unwrap()
+                    committee.connection_manager.connect(session, ids[i], addresses[i]).await
                 }
                 else {
-                    connection_manager.accept(ids[i]).await.unwrap()
+                    committee.connection_manager.accept(session, ids[i]).await
                 }
-            };
-            channels.insert(ids[i], channel);
+            }
+            .ok_or_else(|| format!("Failed to connect to party {}", ids[i]))?;
+            committee.channels.insert(ids[i], channel);
         }
-        Self {
-            party,
-            ids: ids.to_vec(),
-            channels,
+        Ok(committee)
+    }
+}
+
+impl Drop for NetIOCommittee {
+    fn drop(&mut self) {
+        let connection_manager = self.connection_manager.clone();
+        let session = self.session;
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move { connection_manager.close_session(session).await });
         }
     }
 }
@@ -299,6 +635,10 @@ impl IOCommittee<NetIOChannel> for NetIOCommittee {
         self.ids.as_slice()
     }
 
+    fn session(&self) -> SessionId {
+        self.session
+    }
+
     fn channel(&self, from: u64, to: u64) -> &NetIOChannel {
         if from == to || (from != self.party && to != self.party) {
             panic!("Invalid channel");
@@ -314,4 +654,74 @@ impl IOCommittee<NetIOChannel> for NetIOCommittee {
                 .unwrap()
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::Secp256k1;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_net_io_channel_authentication() {
+        let secp = Secp256k1::new();
+        let keys: Vec<(SecretKey, PublicKey)> = (0..2).map(|_| secp.generate_keypair(&mut rand::thread_rng())).collect();
+        let public_keys: Vec<PublicKey> = keys.iter().map(|(_, pk)| *pk).collect();
+        let ids = [1, 2];
+        let session = session_id("test-authentication", &ids);
+        let address = SocketAddr::new("127.0.0.1".parse().unwrap(), 25101);
+        let responder = ConnectionManager::shared(1, 25101);
+        responder.open_session(session, NetIOIdentity::new(keys[0].0, &ids, &public_keys)).await.unwrap();
+
+        let initiator = ConnectionManager::shared(2, 25102);
+        initiator.open_session(session, NetIOIdentity::new(keys[1].0, &ids, &public_keys)).await.unwrap();
+        let channel = initiator.connect(session, 1, address).await.unwrap();
+        let peer_channel = responder.accept(session, 2).await.unwrap();
+        channel.send(Bytes::from("share")).await;
+        assert_eq!(peer_channel.recv().await, Bytes::from("share"));
+        peer_channel.send(Bytes::from("signature")).await;
+        assert_eq!(channel.recv().await, Bytes::from("signature"));
+
+        // party 2 is impersonated with a key that isn't registered
+        let (impostor_sk, _) = secp.generate_keypair(&mut rand::thread_rng());
+        let impostor = ConnectionManager::shared(2, 25103);
+        impostor.open_session(session, NetIOIdentity::new(impostor_sk, &ids, &public_keys)).await.unwrap();
+        assert!(impostor.connect(session, 1, address).await.is_none());
+
+        // the listener isn't the party 1 that party 2 has registered
+        let other_session = session_id("test-authentication-other", &ids);
+        responder.open_session(other_session, NetIOIdentity::new(keys[0].0, &ids, &public_keys)).await.unwrap();
+        initiator.open_session(other_session, NetIOIdentity::new(keys[1].0, &ids, &[public_keys[1], public_keys[1]])).await.unwrap();
+        assert!(initiator.connect(other_session, 1, address).await.is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_net_io_committee_concurrent_sessions() {
+        let secp = Secp256k1::new();
+        let ids = [1, 2, 3];
+        let keys: Vec<(SecretKey, PublicKey)> = ids.iter().map(|_| secp.generate_keypair(&mut rand::thread_rng())).collect();
+        let public_keys: Vec<PublicKey> = keys.iter().map(|(_, pk)| *pk).collect();
+        let ports: Vec<u16> = ids.iter().map(|id| 25110 + *id as u16).collect();
+        let addresses: Vec<SocketAddr> = ports.iter().map(|port| SocketAddr::new("127.0.0.1".parse().unwrap(), *port)).collect();
+
+        // every party runs two sessions among the same parties on its single port
+        let keys_ref = &keys;
+        let public_keys_ref = &public_keys;
+        let ports_ref = &ports;
+        let addresses_ref = &addresses;
+        let futs = ["session-a", "session-b"].into_iter().flat_map(move |purpose| {
+            (0..ids.len()).map(move |i| async move {
+                let identity = NetIOIdentity::new(keys_ref[i].0, &ids, public_keys_ref);
+                let session = session_id(purpose, &ids);
+                let committee = NetIOCommittee::new(ids[i], ports_ref[i], session, &ids, addresses_ref, identity).await.unwrap();
+                for peer in ids.iter().filter(|peer| **peer != ids[i]) {
+                    committee.channel(ids[i], *peer).send(Bytes::from(format!("{}-{}", purpose, ids[i]))).await;
+                }
+                for peer in ids.iter().filter(|peer| **peer != ids[i]) {
+                    let message = committee.channel(*peer, ids[i]).recv().await;
+                    assert_eq!(message, Bytes::from(format!("{}-{}", purpose, peer)));
+                }
+            })
+        });
+        futures::future::join_all(futs).await;
+    }
+}
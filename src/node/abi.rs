@@ -0,0 +1,177 @@
+use super::contract::{ContractConfig, ContractError};
+use log::error;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use web3::ethabi::{Contract as AbiContract, Event, RawLog, Token};
+use web3::types::{Address, Log, H256, U256};
+
+// event schemas of the safestake contracts, loaded from the abi json files referenced in the contract config
+#[derive(Clone, Debug)]
+pub struct ContractAbi {
+    events: HashMap<String, Event>,
+}
+
+impl ContractAbi {
+    pub fn from_config(config: &ContractConfig) -> Result<Self, ContractError> {
+        let mut events = HashMap::new();
+        // events of the network contract take precedence over the ones with the same name in the registry
+        for path in [
+            &config.safestake_registry_abi_path,
+            &config.safestake_network_abi_path,
+        ] {
+            let contract = load_abi(path)?;
+            for event in contract.events() {
+                events.insert(event.name.clone(), event.clone());
+            }
+        }
+        Ok(Self { events })
+    }
+
+    pub fn event(&self, name: &str) -> Result<&Event, ContractError> {
+        self.events.get(name).ok_or_else(|| {
+            error!("Can't find event {} in abi", name);
+            ContractError::ContractParseError
+        })
+    }
+
+    // events that only some deployments of the contracts have
+    pub fn optional_event(&self, name: &str) -> Option<&Event> {
+        self.events.get(name)
+    }
+
+    pub fn topic(&self, name: &str) -> Result<H256, ContractError> {
+        Ok(self.event(name)?.signature())
+    }
+}
+
+// truffle artifacts wrap the abi in an "abi" field
+pub fn load_abi<P: AsRef<Path>>(path: P) -> Result<AbiContract, ContractError> {
+    let raw_abi = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+        error!("Can't read from {:?} {}", path.as_ref(), e);
+        ContractError::FileError
+    })?;
+    let raw_json: Value = serde_json::from_str(&raw_abi).map_err(|e| {
+        error!("Can't parse json from {:?} {}", path.as_ref(), e);
+        ContractError::ContractParseError
+    })?;
+    let abi = raw_json["abi"].to_string();
+    AbiContract::load(abi.as_bytes()).map_err(|e| {
+        error!("Can't load abi from {:?} {}", path.as_ref(), e);
+        ContractError::ContractParseError
+    })
+}
+
+// a decoded log whose parameters are looked up by name instead of position
+#[derive(Clone, Debug)]
+pub struct EventLog {
+    params: HashMap<String, Token>,
+}
+
+impl EventLog {
+    pub fn decode(event: &Event, raw_log: Log) -> Result<Self, ContractError> {
+        let log = event
+            .parse_log(RawLog {
+                topics: raw_log.topics,
+                data: raw_log.data.0,
+            })
+            .map_err(|e| {
+                error!("Can't parse {} log {}", event.name, e);
+                ContractError::LogParseError
+            })?;
+        Ok(Self {
+            params: log
+                .params
+                .into_iter()
+                .map(|param| (param.name, param.value))
+                .collect(),
+        })
+    }
+
+    pub fn param(&self, name: &str) -> Result<Token, ContractError> {
+        self.params.get(name).cloned().ok_or_else(|| {
+            error!("Can't find param {} in log", name);
+            ContractError::LogParseError
+        })
+    }
+
+    pub fn address(&self, name: &str) -> Result<Address, ContractError> {
+        self.param(name)?
+            .into_address()
+            .ok_or(ContractError::LogParseError)
+    }
+
+    pub fn bytes(&self, name: &str) -> Result<Vec<u8>, ContractError> {
+        self.param(name)?
+            .into_bytes()
+            .ok_or(ContractError::LogParseError)
+    }
+
+    pub fn string(&self, name: &str) -> Result<String, ContractError> {
+        self.param(name)?
+            .into_string()
+            .ok_or(ContractError::LogParseError)
+    }
+
+    pub fn uint(&self, name: &str) -> Result<U256, ContractError> {
+        self.param(name)?
+            .into_uint()
+            .ok_or(ContractError::LogParseError)
+    }
+
+    pub fn u32(&self, name: &str) -> Result<u32, ContractError> {
+        let value = self.uint(name)?;
+        if value > U256::from(u32::MAX) {
+            return Err(ContractError::LogParseError);
+        }
+        Ok(value.as_u32())
+    }
+
+    pub fn u32_array(&self, name: &str) -> Result<Vec<u32>, ContractError> {
+        self.param(name)?
+            .into_array()
+            .ok_or(ContractError::LogParseError)?
+            .into_iter()
+            .map(|token| {
+                token
+                    .into_uint()
+                    .filter(|value| *value <= U256::from(u32::MAX))
+                    .map(|value| value.as_u32())
+                    .ok_or(ContractError::LogParseError)
+            })
+            .collect()
+    }
+
+    pub fn bytes_array(&self, name: &str) -> Result<Vec<Vec<u8>>, ContractError> {
+        self.param(name)?
+            .into_array()
+            .ok_or(ContractError::LogParseError)?
+            .into_iter()
+            .map(|token| token.into_bytes().ok_or(ContractError::LogParseError))
+            .collect()
+    }
+}
+
+#[test]
+fn test_topics_from_abi() {
+    use super::utils::FromFile;
+    let config = ContractConfig::from_file("contract_config/configs.yml").unwrap();
+    let abi = ContractAbi::from_config(&config).unwrap();
+    // topics that used to be configured by hand in configs.yml
+    for (name, topic) in [
+        ("ValidatorRegistration", "610eb3c1fafe229af536e91b7e00486902d54d54d8d3f0de283a467ac8f985bf"),
+        ("ValidatorRemoval", "671ada3835502b9498e4a3116c344293ec3a4ef43f90bb42283d7d66a3f772b2"),
+        ("InitializerRegistration", "4f4c981e441ec7803ad8fd83e89f9b4cc199f7eebb543b8bda1b629260b510c0"),
+        ("InitializerMiniPoolCreated", "6f65862ac449fcf43e1ddb6cf87fc8eaadc97be16c1a9328ea091c074baed7d0"),
+        ("InitializerMiniPoolReady", "a7a1f49e9189b6d804b1d64265af8d15d337dd88b38a8e4e06a31931b726f7f5"),
+    ] {
+        assert_eq!(abi.topic(name).unwrap(), H256::from_slice(&hex::decode(topic).unwrap()));
+    }
+    // operator changes are only emitted by the mock contract
+    assert!(abi.optional_event("ValidatorOperatorsChange").is_none());
+    let mock_config = ContractConfig {
+        safestake_network_abi_path: "tests/data/SafeStakeNetworkMock.json".to_string(),
+        ..config
+    };
+    assert!(ContractAbi::from_config(&mock_config).unwrap().optional_event("ValidatorOperatorsChange").is_some());
+}
@@ -7,6 +7,8 @@ use tokio::sync::OnceCell;
 /// The file name for the serialized `OperatorCommitteeDefinition` struct.
 pub const NODE_KEY_FILENAME: &str = "node_key.json";
 pub const DB_FILENAME: &str = "dvf_node_db";
+pub const INITIALIZER_DIR: &str = "initializers";
+pub const REFRESH_DIR: &str = "refresh";
 
 pub const DEFAULT_BASE_PORT: u16 = 25_000;
 pub const TRANSACTION_PORT_OFFSET: u16 = 0;
@@ -19,11 +21,67 @@ pub const BASE_ADDRESS: [u8; 4] = [127, 0, 0, 1];
 pub static API_ADDRESS: OnceCell<String> = OnceCell::const_new();
 pub static BOOT_ENR: OnceCell<String> =  OnceCell::const_new();
 pub static BOOT_SOCKETADDR: OnceCell<SocketAddr> = OnceCell::const_new();
+pub static PORT_MODE: OnceCell<PortMode> = OnceCell::const_new();
 pub const COLLECT_PERFORMANCE_URL : &str = "collect_performance";
 pub const VALIDATOR_PK_URL : &str = "validator_pk";
 pub const PRESTAKE_SIGNATURE_URL : &str = "prestake_signature";
 pub const STAKE_SIGNATURE_URL : &str = "stake_signature";
 
+/// How a node spreads its channels over TCP ports. Every node serves all channels on its base
+/// port, the modes differ in the per-channel ports it keeps open and how it addresses operators.
+/// To migrate, all operators move from `PerPort` to `Dual`, and only then to `Single`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortMode {
+    /// Listen on the per-channel ports and address operators at their port offsets.
+    PerPort,
+    /// Listen on the per-channel ports as well, but address operators at their base port only.
+    Dual,
+    /// Listen on the base port only.
+    Single,
+}
+
+impl Default for PortMode {
+    fn default() -> Self {
+        PortMode::PerPort
+    }
+}
+
+impl std::str::FromStr for PortMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "per-port" => Ok(PortMode::PerPort),
+            "dual" => Ok(PortMode::Dual),
+            "single" => Ok(PortMode::Single),
+            _ => Err(format!("unknown port mode {}, expected per-port, dual or single", s)),
+        }
+    }
+}
+
+impl PortMode {
+    pub fn per_port_listeners(&self) -> bool {
+        *self != PortMode::Single
+    }
+
+    /// Whether the node advertises its base port to the other operators.
+    pub fn advertise_base_port(&self) -> bool {
+        *self != PortMode::PerPort
+    }
+}
+
+pub fn port_mode() -> PortMode {
+    PORT_MODE.get().cloned().unwrap_or_default()
+}
+
+/// The address to reach the channel at `port_offset` of an operator whose base address is `base`.
+pub fn channel_address(base: SocketAddr, port_offset: u16) -> SocketAddr {
+    match port_mode() {
+        PortMode::PerPort => SocketAddr::new(base.ip(), base.port() + port_offset),
+        PortMode::Dual | PortMode::Single => base,
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NodeConfig {
     // pub id: u64,
@@ -36,6 +94,9 @@ pub struct NodeConfig {
     pub node_key_path: PathBuf,
     pub validator_dir: PathBuf,
     pub secrets_dir: PathBuf,
+    // refresh the key shares of distributed validators every this many epochs
+    #[serde(default)]
+    pub share_refresh_epochs: Option<u64>,
 }
 
 impl Default for NodeConfig {
@@ -73,6 +134,7 @@ impl NodeConfig {
             node_key_path,
             validator_dir,
             secrets_dir,
+            share_refresh_epochs: None,
         }
     }
 
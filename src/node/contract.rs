@@ -1,41 +1,59 @@
+use super::abi::{load_abi, ContractAbi, EventLog};
+use super::execution::execution_clients;
 use super::db::Database;
-use super::utils::{convert_va_pk_to_u64, FromFile, ToFile};
+use super::utils::{byte_array, convert_va_pk_to_u64, FromFile, ToFile};
+use crate::DEFAULT_CHANNEL_CAPACITY;
 use async_trait::async_trait;
 use hscrypto::PublicKey;
-use hsutils::monitored_channel::MonitoredSender;
+use hsutils::monitored_channel::{MonitoredChannel, MonitoredSender};
 use log::{error, info, warn};
 use tokio::sync::RwLock;
 use serde_derive::{Deserialize as DeriveDeserialize, Serialize as DeriveSerialize};
-use serde_json::Value;
 use core::panic;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use store::Store;
-use tokio::sync::OnceCell;
-use web3::ethabi::{token, Event, EventParam, ParamType, RawLog};
+use tokio::sync::{oneshot, OnceCell};
+use web3::ethabi::Event;
 use web3::{
     contract::{Contract as EthContract, Options},
     futures::TryStreamExt,
-    transports::WebSocket,
-    types::{Address, BlockNumber, FilterBuilder, Log, H256, U256, U64},
-    Web3,
+    types::{Address, BlockId, BlockNumber, Filter, FilterBuilder, Log, H256, U256, U64},
 };
 
 const CONTRACT_CONFIG_FILE: &str = "contract_config/configs.yml";
-const CONTRACT_RECORD_FILE: &str = "contract_record.yml";
-const CONTRACT_STORE_FILE: &str = "contract_store";
-const CONTRACT_DATABASE_FILE: &str = "contract_database.db";
-const CONTRACT_VA_REG_EVENT_NAME: &str = "ValidatorRegistration";
+pub const CONTRACT_RECORD_FILE: &str = "contract_record.yml";
+pub const CONTRACT_STORE_FILE: &str = "contract_store";
+pub const CONTRACT_DATABASE_FILE: &str = "contract_database.db";
+pub const CONTRACT_VA_REG_EVENT_NAME: &str = "ValidatorRegistration";
 const CONTRACT_VA_RM_EVENT_NAME: &str = "ValidatorRemoval";
 const CONTRACT_INI_REG_EVENT_NAME: &str = "InitializerRegistration";
 const CONTRACT_MINIPOOL_CREATED_EVENT_NAME: &str = "InitializerMiniPoolCreated";
 const CONTRACT_MINIPOOL_READY_EVENT_NAME: &str = "InitializerMiniPoolReady";
+const CONTRACT_OP_REG_EVENT_NAME: &str = "OperatorRegistration";
+const CONTRACT_OP_RM_EVENT_NAME: &str = "OperatorRemoval";
+const CONTRACT_FUNDS_DEPOSIT_EVENT_NAME: &str = "FundsDeposit";
+const CONTRACT_FUNDS_WITHDRAWAL_EVENT_NAME: &str = "FundsWithdrawal";
+pub const CONTRACT_VA_OPERATORS_CHANGE_EVENT_NAME: &str = "ValidatorOperatorsChange";
+pub const DEFAULT_CONFIRMATION_DEPTH: u64 = 12;
+// applied logs older than this (in blocks) are considered final and no longer checked for reorgs
+const REORG_TRACKING_BLOCKS: u64 = 128;
+// roughly one slot
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(12);
+// upper bound of blocks covered by one eth_getLogs query
+const LOG_QUERY_BLOCK_RANGE: u64 = 5000;
+// how long logs are polled over http before trying websockets again
+const HTTP_POLLING_ROUNDS: usize = 25;
+// paid blocks are also refreshed from the contract in case a funds event was missed
+const PAID_BLOCK_REFRESH_INTERVAL: Duration = Duration::from_secs(3600 * 6);
+// 32 slots of 12 seconds, used to turn service time into validator-epochs
+pub const SECONDS_PER_EPOCH: u64 = 384;
 pub static SELF_OPERATOR_ID: OnceCell<u32> = OnceCell::const_new();
-pub static DEFAULT_TRANSPORT_URL: OnceCell<String> = OnceCell::const_new();
 pub static REGISTRY_CONTRACT: OnceCell<String> = OnceCell::const_new();
 pub static NETWORK_CONTRACT: OnceCell<String> = OnceCell::const_new();
+pub static CONFIRMATION_DEPTH: OnceCell<u64> = OnceCell::const_new();
 #[derive(Debug)]
 pub enum ContractError {
     StoreError,
@@ -49,10 +67,11 @@ pub enum ContractError {
     FileError,
     ContractParseError,
     QueryError,
+    TransportError,
 }
 
 impl ContractError {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             ContractError::StoreError => "[ERROR!]: Can't interact with store.",
             ContractError::BlockNumberError => "[ERROR]: Can't get blocknumber from infura.",
@@ -67,34 +86,39 @@ impl ContractError {
             ContractError::FileError => "[ERROR]: Error happens when processing file",
             ContractError::ContractParseError => "[ERROR]: Can't parse contract from abi json",
             ContractError::QueryError => "[ERROR]: Can't query from contract",
+            ContractError::TransportError => "[ERROR]: No execution endpoint is available",
         }
     }
 }
 
 type ValidatorPublicKey = [u8; 48];
 type OperatorPublicKey = [u8; 33];
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, DeriveSerialize, DeriveDeserialize)]
 pub struct Operator {
     pub id: u32,
     pub name: String,
     pub address: Address,
+    #[serde(with = "byte_array")]
     pub public_key: OperatorPublicKey, // ecc256 public key
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, DeriveSerialize, DeriveDeserialize)]
 pub struct Validator {
     pub id: u64,
     pub owner_address: Address,
+    #[serde(with = "byte_array")]
     pub public_key: ValidatorPublicKey, // bls public key
     pub releated_operators: Vec<u32>,
-    pub active: bool
+    pub active: bool,
+    pub paid_block: u64, // the validator is stopped once the chain reaches this block
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, DeriveSerialize, DeriveDeserialize)]
 pub struct Initializer {
     pub id: u32,
     pub owner_address: Address,
     pub releated_operators: Vec<u32>,
+    #[serde(with = "byte_array::option")]
     pub validator_pk: Option<ValidatorPublicKey>,
     pub minipool_address: Option<Address>,
 }
@@ -103,22 +127,76 @@ pub type SharedPublicKeys = Vec<Vec<u8>>;
 pub type EncryptedSecretKeys = Vec<Vec<u8>>;
 pub type OperatorPublicKeys = Vec<Vec<u8>>;
 pub type OperatorIds = Vec<u32>;
-#[derive(Clone)]
+// commands are kept in the database until they are processed, see `Database::enqueue_command`
+#[derive(Clone, DeriveSerialize, DeriveDeserialize)]
 pub enum ContractCommand {
     StartValidator(
         Validator,
         OperatorPublicKeys,
         SharedPublicKeys,
         EncryptedSecretKeys,
+        // whether the encrypted keys may be legacy ciphertexts, see `ContractConfig::accepts_legacy_shares`
+        bool,
     ),
     RemoveValidator(Validator),
     ActivateValidator(Validator),
     StopValidator(Validator),
     StartInitializer(Initializer, OperatorPublicKeys),
-    MiniPoolCreated(u32, ValidatorPublicKey, OperatorPublicKeys, OperatorIds, Address),
-    MiniPoolReady(u32, ValidatorPublicKey, OperatorPublicKeys, OperatorIds, Address),
+    MiniPoolCreated(u32, #[serde(with = "byte_array")] ValidatorPublicKey, OperatorPublicKeys, OperatorIds, Address),
+    MiniPoolReady(u32, #[serde(with = "byte_array")] ValidatorPublicKey, OperatorPublicKeys, OperatorIds, Address),
+    // a deregistered operator and the committees it was in, each with the operators still registered
+    OperatorRemoved(Operator, Vec<(Validator, OperatorIds)>),
+    // proactively refresh the key shares of a validator, the new shares are used from the epoch on
+    RefreshShares(#[serde(with = "byte_array")] ValidatorPublicKey, u64),
+    // reshare a validator from the operators to the committee of the validator, which takes
+    // over at the epoch
+    ReshareValidator(Validator, OperatorPublicKeys, OperatorIds, u64),
+}
+
+impl ContractCommand {
+    // commands with the same subject are processed in the order they were received
+    pub fn subject(&self) -> String {
+        match self {
+            ContractCommand::StartValidator(validator, _, _, _, _)
+            | ContractCommand::RemoveValidator(validator)
+            | ContractCommand::ActivateValidator(validator)
+            | ContractCommand::StopValidator(validator) => format!("validator:{}", hex::encode(validator.public_key)),
+            ContractCommand::StartInitializer(initializer, _) => format!("initializer:{}", initializer.id),
+            ContractCommand::MiniPoolCreated(initializer_id, _, _, _, _)
+            | ContractCommand::MiniPoolReady(initializer_id, _, _, _, _) => format!("initializer:{}", initializer_id),
+            ContractCommand::OperatorRemoved(operator, _) => format!("operator:{}", operator.id),
+            ContractCommand::RefreshShares(validator_pk, _) => format!("validator:{}", hex::encode(validator_pk)),
+            ContractCommand::ReshareValidator(validator, _, _, _) => format!("validator:{}", hex::encode(validator.public_key)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContractCommand::StartValidator(..) => "StartValidator",
+            ContractCommand::RemoveValidator(_) => "RemoveValidator",
+            ContractCommand::ActivateValidator(_) => "ActivateValidator",
+            ContractCommand::StopValidator(_) => "StopValidator",
+            ContractCommand::StartInitializer(..) => "StartInitializer",
+            ContractCommand::MiniPoolCreated(..) => "MiniPoolCreated",
+            ContractCommand::MiniPoolReady(..) => "MiniPoolReady",
+            ContractCommand::OperatorRemoved(..) => "OperatorRemoved",
+            ContractCommand::RefreshShares(..) => "RefreshShares",
+            ContractCommand::ReshareValidator(..) => "ReshareValidator",
+        }
+    }
 }
 
+pub enum ContractLog {
+    // the block of the log has reached the confirmation depth
+    Confirmed(Log),
+    // the block of a previously reported log is no longer canonical
+    Orphaned(Log),
+    // answered once every log sent before it has been handled
+    Synced(oneshot::Sender<()>),
+}
+
+// Handlers must be idempotent: the same log can be handed over again, e.g. when the node stops
+// after a handler succeeded but before the log was marked as processed.
 #[async_trait]
 pub trait TopicHandler: Send + Sync + 'static {
     async fn process(
@@ -129,10 +207,26 @@ pub trait TopicHandler: Send + Sync + 'static {
         config: &ContractConfig,
         sender: &MonitoredSender<ContractCommand>,
     ) -> Result<(), ContractError>;
+
+    // undo the effect of an already processed log whose block has been reorganized out of the chain
+    async fn rollback(
+        &self,
+        log: Log,
+        _db: &Database,
+        _sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        warn!(
+            "no rollback available for log in tx {:?}, local state may need a resync",
+            log.transaction_hash
+        );
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
-pub struct ValidatorRegistrationHandler {}
+pub struct ValidatorRegistrationHandler {
+    pub event: Event,
+}
 
 #[async_trait]
 impl TopicHandler for ValidatorRegistrationHandler {
@@ -144,17 +238,33 @@ impl TopicHandler for ValidatorRegistrationHandler {
         config: &ContractConfig,
         sender: &MonitoredSender<ContractCommand>,
     ) -> Result<(), ContractError> {
-        process_validator_registration(log, db, operator_pk_base64, config, sender)
+        process_validator_registration(&self.event, log, db, operator_pk_base64, config, sender)
             .await
             .map_err(|e| {
                 error!("error happens when process validator registration");
                 e
             })
     }
+
+    async fn rollback(
+        &self,
+        log: Log,
+        db: &Database,
+        sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        rollback_validator_registration(&self.event, log, db, sender)
+            .await
+            .map_err(|e| {
+                error!("error happens when rollback validator registration");
+                e
+            })
+    }
 }
 
 #[derive(Clone)]
-pub struct ValidatorRemovalHandler {}
+pub struct ValidatorRemovalHandler {
+    pub event: Event,
+}
 #[async_trait]
 impl TopicHandler for ValidatorRemovalHandler {
     async fn process(
@@ -165,17 +275,33 @@ impl TopicHandler for ValidatorRemovalHandler {
         config: &ContractConfig,
         sender: &MonitoredSender<ContractCommand>,
     ) -> Result<(), ContractError> {
-        process_validator_removal(log, db, operator_pk_base64, config, sender)
+        process_validator_removal(&self.event, log, db, operator_pk_base64, config, sender)
             .await
             .map_err(|e| {
                 error!("error happens when process validator removal");
                 e
             })
     }
+
+    async fn rollback(
+        &self,
+        log: Log,
+        _db: &Database,
+        _sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        // the key share has already been deleted from disk, it can't be restored from the log
+        error!(
+            "validator removal in tx {:?} was reorganized out, the validator can't be restored automatically. Please resync local state",
+            log.transaction_hash
+        );
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
-pub struct InitializerRegistrationHandler {}
+pub struct InitializerRegistrationHandler {
+    pub event: Event,
+}
 
 #[async_trait]
 impl TopicHandler for InitializerRegistrationHandler {
@@ -187,7 +313,7 @@ impl TopicHandler for InitializerRegistrationHandler {
         config: &ContractConfig,
         sender: &MonitoredSender<ContractCommand>,
     ) -> Result<(), ContractError> {
-        process_initializer_registration(log, db, operator_pk_base64, config, sender)
+        process_initializer_registration(&self.event, log, db, operator_pk_base64, config, sender)
             .await
             .map_err(|e| {
                 error!("error happens when process initializer registration");
@@ -197,7 +323,9 @@ impl TopicHandler for InitializerRegistrationHandler {
 }
 
 #[derive(Clone)]
-pub struct MinipoolCreatedHandler {}
+pub struct MinipoolCreatedHandler {
+    pub event: Event,
+}
 
 #[async_trait]
 impl TopicHandler for MinipoolCreatedHandler {
@@ -209,7 +337,7 @@ impl TopicHandler for MinipoolCreatedHandler {
         config: &ContractConfig,
         sender: &MonitoredSender<ContractCommand>,
     ) -> Result<(), ContractError> {
-        process_minipool_created(log, db, operator_pk_base64, config, sender)
+        process_minipool_created(&self.event, log, db, operator_pk_base64, config, sender)
             .await
             .map_err(|e| {
                 error!("error happens when process initializer registration");
@@ -219,7 +347,9 @@ impl TopicHandler for MinipoolCreatedHandler {
 }
 
 #[derive(Clone)]
-pub struct MinipoolReadyHandler {}
+pub struct MinipoolReadyHandler {
+    pub event: Event,
+}
 
 #[async_trait]
 impl TopicHandler for MinipoolReadyHandler {
@@ -231,7 +361,7 @@ impl TopicHandler for MinipoolReadyHandler {
         config: &ContractConfig,
         sender: &MonitoredSender<ContractCommand>,
     ) -> Result<(), ContractError> {
-        process_minipool_ready(log, db, operator_pk_base64, config, sender)
+        process_minipool_ready(&self.event, log, db, operator_pk_base64, config, sender)
             .await
             .map_err(|e| {
                 error!("error happens when process initializer registration");
@@ -240,24 +370,157 @@ impl TopicHandler for MinipoolReadyHandler {
     }
 }
 
+#[derive(Clone)]
+pub struct OperatorRegistrationHandler {
+    pub event: Event,
+}
+
+#[async_trait]
+impl TopicHandler for OperatorRegistrationHandler {
+    async fn process(
+        &self,
+        log: Log,
+        db: &Database,
+        operator_pk_base64: &String,
+        config: &ContractConfig,
+        sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        process_operator_registration(&self.event, log, db, operator_pk_base64, config, sender)
+            .await
+            .map_err(|e| {
+                error!("error happens when process operator registration");
+                e
+            })
+    }
+
+    async fn rollback(
+        &self,
+        log: Log,
+        db: &Database,
+        _sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        // the operator is queried from the contract again the next time a committee needs it
+        let log = EventLog::decode(&self.event, log)?;
+        db.delete_operator(log.u32("id")?).await;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct OperatorRemovalHandler {
+    pub event: Event,
+}
+
+#[async_trait]
+impl TopicHandler for OperatorRemovalHandler {
+    async fn process(
+        &self,
+        log: Log,
+        db: &Database,
+        operator_pk_base64: &String,
+        config: &ContractConfig,
+        sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        process_operator_removal(&self.event, log, db, operator_pk_base64, config, sender)
+            .await
+            .map_err(|e| {
+                error!("error happens when process operator removal");
+                e
+            })
+    }
+}
+
+// deposits and withdrawals move the paid block of the account
+#[derive(Clone)]
+pub struct AccountFundsHandler {
+    pub event: Event,
+}
+
+#[async_trait]
+impl TopicHandler for AccountFundsHandler {
+    async fn process(
+        &self,
+        log: Log,
+        db: &Database,
+        operator_pk_base64: &String,
+        config: &ContractConfig,
+        sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        process_account_funds(&self.event, log, db, operator_pk_base64, config, sender)
+            .await
+            .map_err(|e| {
+                error!("error happens when process account funds");
+                e
+            })
+    }
+}
+
+#[derive(Clone)]
+pub struct ValidatorOperatorsChangeHandler {
+    pub event: Event,
+}
+
+#[async_trait]
+impl TopicHandler for ValidatorOperatorsChangeHandler {
+    async fn process(
+        &self,
+        log: Log,
+        db: &Database,
+        operator_pk_base64: &String,
+        config: &ContractConfig,
+        sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        process_validator_operators_change(&self.event, log, db, operator_pk_base64, config, sender)
+            .await
+            .map_err(|e| {
+                error!("error happens when process validator operators change");
+                e
+            })
+    }
+
+    async fn rollback(
+        &self,
+        log: Log,
+        db: &Database,
+        _sender: &MonitoredSender<ContractCommand>,
+    ) -> Result<(), ContractError> {
+        rollback_validator_operators_change(&self.event, log, db)
+            .await
+            .map_err(|e| {
+                error!("error happens when rollback validator operators change");
+                e
+            })
+    }
+}
+
 #[derive(Debug, DeriveSerialize, DeriveDeserialize, Clone)]
 pub struct ContractConfig {
     pub safestake_network_address: String,
     pub safestake_registry_address: String,
-    pub validator_registration_topic: String,
-    pub validator_removal_topic: String,
-    pub initializer_registration_topic: String,
-    pub initializer_minipool_created_topic: String,
-    pub initializer_minipool_ready_topic: String,
+    // event topics are derived from the abi files
     pub safestake_network_abi_path: String,
     pub safestake_registry_abi_path: String,
+    // registrations logged before this block may carry legacy ciphertexts instead of share
+    // envelopes, which aren't bound to the validator and the operator
+    #[serde(default)]
+    pub legacy_shares_before_block: u64,
 }
 impl FromFile<ContractConfig> for ContractConfig {}
 impl ToFile for ContractConfig {}
 
+impl ContractConfig {
+    // a registration of an unknown block is treated as a new one
+    pub fn accepts_legacy_shares(&self, block_num: Option<U64>) -> bool {
+        block_num.map_or(false, |bn| bn.as_u64() < self.legacy_shares_before_block)
+    }
+}
+
 #[derive(Clone, DeriveSerialize, DeriveDeserialize, Debug)]
 pub struct ContractRecord {
     pub block_num: u64,
+    // logs applied in the last REORG_TRACKING_BLOCKS blocks, with their block hash
+    #[serde(default)]
+    pub applied_logs: Vec<Log>,
 }
 
 impl FromFile<ContractRecord> for ContractRecord {}
@@ -265,7 +528,7 @@ impl ToFile for ContractRecord {}
 
 pub struct Contract {
     pub config: ContractConfig,
-    pub record: ContractRecord,
+    pub record: Arc<RwLock<ContractRecord>>,
     pub db: Database,
     pub operator_pk: PublicKey,
     pub store: Store,
@@ -281,7 +544,7 @@ impl Contract {
             Ok(record) => record,
             Err(err_str) => {
                 warn!("Can't recover from contract record file {}, get current block number from contract", err_str);
-                ContractRecord { block_num: 0 }
+                ContractRecord { block_num: 0, applied_logs: vec![] }
             }
         };
         let contract_store_path = base_dir
@@ -296,7 +559,7 @@ impl Contract {
             .map_err(|e| format!("can't create contract database {:?}", e))?;
         Ok(Self {
             config,
-            record,
+            record: Arc::new(RwLock::new(record)),
             db,
             operator_pk,
             store,
@@ -323,23 +586,56 @@ impl Contract {
                 .unwrap();
             contract.construct_filter().await;
             contract.check_operator_id().await;
-            contract.get_logs_from_contract(tx.clone()).await;
-            contract.monitor_validator_paidblock(tx.clone());
-            contract.listen_logs(tx).await;
+            let tx_log = contract.process_logs(tx.clone());
+            contract.get_logs_from_contract(tx_log.clone()).await;
+            contract.monitor_validator_paidblock(tx);
+            contract.listen_logs(tx_log).await;
         });
     }
 
     pub async fn construct_filter(&mut self) {
         let config = &self.config;
-        let va_reg_topic =
-            H256::from_slice(&hex::decode(&config.validator_registration_topic).unwrap());
-        let va_rm_topic = H256::from_slice(&hex::decode(&config.validator_removal_topic).unwrap());
-        let ini_reg_topic =
-            H256::from_slice(&hex::decode(&config.initializer_registration_topic).unwrap());
-        let minipool_created_topic =
-            H256::from_slice(&hex::decode(&config.initializer_minipool_created_topic).unwrap());
-        let minipool_ready_topic =
-            H256::from_slice(&hex::decode(&config.initializer_minipool_ready_topic).unwrap());
+        let abi = ContractAbi::from_config(config)
+            .map_err(|e| {
+                error!("{}", e.as_str());
+            })
+            .unwrap();
+        let va_reg_event = abi.event(CONTRACT_VA_REG_EVENT_NAME).unwrap().clone();
+        let va_rm_event = abi.event(CONTRACT_VA_RM_EVENT_NAME).unwrap().clone();
+        let ini_reg_event = abi.event(CONTRACT_INI_REG_EVENT_NAME).unwrap().clone();
+        let minipool_created_event = abi.event(CONTRACT_MINIPOOL_CREATED_EVENT_NAME).unwrap().clone();
+        let minipool_ready_event = abi.event(CONTRACT_MINIPOOL_READY_EVENT_NAME).unwrap().clone();
+        let op_reg_event = abi.event(CONTRACT_OP_REG_EVENT_NAME).unwrap().clone();
+        let op_rm_event = abi.event(CONTRACT_OP_RM_EVENT_NAME).unwrap().clone();
+        let funds_deposit_event = abi.event(CONTRACT_FUNDS_DEPOSIT_EVENT_NAME).unwrap().clone();
+        let funds_withdrawal_event = abi.event(CONTRACT_FUNDS_WITHDRAWAL_EVENT_NAME).unwrap().clone();
+        let va_operators_change_event = abi.optional_event(CONTRACT_VA_OPERATORS_CHANGE_EVENT_NAME).cloned();
+        let va_reg_topic = va_reg_event.signature();
+        let va_rm_topic = va_rm_event.signature();
+        let ini_reg_topic = ini_reg_event.signature();
+        let minipool_created_topic = minipool_created_event.signature();
+        let minipool_ready_topic = minipool_ready_event.signature();
+        let op_reg_topic = op_reg_event.signature();
+        let op_rm_topic = op_rm_event.signature();
+        let funds_deposit_topic = funds_deposit_event.signature();
+        let funds_withdrawal_topic = funds_withdrawal_event.signature();
+        let mut topics = vec![
+            va_reg_topic,
+            va_rm_topic,
+            ini_reg_topic,
+            minipool_created_topic,
+            minipool_ready_topic,
+            op_reg_topic,
+            op_rm_topic,
+            funds_deposit_topic,
+            funds_withdrawal_topic,
+        ];
+        // the deployed contracts don't emit operator changes yet, reshares are only followed with
+        // an abi that has the event
+        match &va_operators_change_event {
+            Some(event) => topics.push(event.signature()),
+            None => info!("No {} event in the abi, validator operator changes are not followed", CONTRACT_VA_OPERATORS_CHANGE_EVENT_NAME),
+        }
         let filter_builder = FilterBuilder::default()
             .address(vec![Address::from_slice(
                 &hex::decode({
@@ -350,74 +646,46 @@ impl Contract {
                 }).unwrap(),
             )])
             .topics(
-                Some(vec![
-                    va_reg_topic,
-                    va_rm_topic,
-                    ini_reg_topic,
-                    minipool_created_topic,
-                    minipool_ready_topic,
-                ]),
+                Some(topics),
                 None,
                 None,
                 None,
             );
         self.filter_builder = Some(filter_builder);
         let mut handlers = self.handlers.write().await;
-        handlers.insert(va_reg_topic, Box::new(ValidatorRegistrationHandler {}));
-        handlers.insert(va_rm_topic, Box::new(ValidatorRemovalHandler {}));
-        handlers.insert(ini_reg_topic, Box::new(InitializerRegistrationHandler {}));
-        handlers.insert(minipool_created_topic, Box::new(MinipoolCreatedHandler {}));
-        handlers.insert(minipool_ready_topic, Box::new(MinipoolReadyHandler {}));
+        handlers.insert(va_reg_topic, Box::new(ValidatorRegistrationHandler { event: va_reg_event }));
+        handlers.insert(va_rm_topic, Box::new(ValidatorRemovalHandler { event: va_rm_event }));
+        handlers.insert(ini_reg_topic, Box::new(InitializerRegistrationHandler { event: ini_reg_event }));
+        handlers.insert(minipool_created_topic, Box::new(MinipoolCreatedHandler { event: minipool_created_event }));
+        handlers.insert(minipool_ready_topic, Box::new(MinipoolReadyHandler { event: minipool_ready_event }));
+        handlers.insert(op_reg_topic, Box::new(OperatorRegistrationHandler { event: op_reg_event }));
+        handlers.insert(op_rm_topic, Box::new(OperatorRemovalHandler { event: op_rm_event }));
+        handlers.insert(funds_deposit_topic, Box::new(AccountFundsHandler { event: funds_deposit_event }));
+        handlers.insert(funds_withdrawal_topic, Box::new(AccountFundsHandler { event: funds_withdrawal_event }));
+        if let Some(event) = va_operators_change_event {
+            handlers.insert(event.signature(), Box::new(ValidatorOperatorsChangeHandler { event }));
+        }
     }
 
+    // stop or resume validators as soon as the chain crosses their paid block
     pub fn monitor_validator_paidblock(&mut self, sender: MonitoredSender<ContractCommand>) {
         let config = self.config.clone();
         let db = self.db.clone();
         tokio::spawn(async move {
-            let mut query_interval = tokio::time::interval(Duration::from_secs(3600 * 6));
+            let mut refresh_interval = tokio::time::interval(PAID_BLOCK_REFRESH_INTERVAL);
+            let mut check_interval = tokio::time::interval(CONFIRMATION_POLL_INTERVAL);
+            let mut last_checked = None;
             loop {
                 tokio::select! {
-                    _ = query_interval.tick() => {
+                    // both intervals fire at startup, paid blocks are refreshed before they are checked
+                    biased;
+                    _ = refresh_interval.tick() => {
                         match db.query_all_validator_address().await {
                             Ok(owners) => {
                                 for owner in owners {
-                                    match check_account(&config, owner).await {
-                                        Ok(t) => {
-                                            if t {
-                                                // stop validators releated to the block
-                                                match db.query_validator_by_address(owner).await {
-                                                    Ok(validators) => { 
-                                                        for va in validators {
-                                                            if va.active {
-                                                                db.disable_validator(hex::encode(&va.public_key)).await;
-                                                                let _ = sender.send(ContractCommand::StopValidator(va)).await;
-                                                            }
-                                                        }
-                                                    },
-                                                    Err(e) => {
-                                                        error!("query validator releated to the address failed {:?}", e);
-                                                    }
-                                                }
-                                            } else {
-                                                match db.query_validator_by_address(owner).await {
-                                                    Ok(validators) => { 
-                                                        for va in validators {
-                                                            if !va.active {
-                                                                db.disable_validator(hex::encode(&va.public_key)).await;
-                                                                let _ = sender.send(ContractCommand::ActivateValidator(va)).await;
-                                                                
-                                                            }
-                                                        }
-                                                    },
-                                                    Err(e) => {
-                                                        error!("query validator releated to the address failed {:?}", e);
-                                                    }
-                                                }
-                                            }
-                                        },
-                                        Err(e) => {
-                                            error!("check account failed {}", e.as_str());
-                                        }
+                                    match query_account_paid_block(&config, owner).await {
+                                        Ok(paid_block) => db.update_paid_block(owner, paid_block).await,
+                                        Err(e) => error!("check account failed {}", e.as_str()),
                                     }
                                 }
                             },
@@ -426,95 +694,133 @@ impl Contract {
                             }
                         }
                     }
+                    _ = check_interval.tick() => {
+                        let (head, timestamp) = match get_head().await {
+                            Ok(head) => head,
+                            Err(_) => continue,
+                        };
+                        if last_checked == Some(head) {
+                            continue;
+                        }
+                        last_checked = Some(head);
+                        check_paid_blocks(&db, head, timestamp, &sender).await;
+                    }
                 }
             }
         });
     }
 
-
-    pub async fn get_logs_from_contract(&mut self, sender: MonitoredSender<ContractCommand>) {
-        let mut record = self.record.clone();
-        let config = self.config.clone();
+    // the single place where contract logs are applied, both the polling path and the subscription path feed it
+    pub fn process_logs(&self, sender: MonitoredSender<ContractCommand>) -> MonitoredSender<ContractLog> {
+        let (tx_log, mut rx_log) = MonitoredChannel::new(
+            DEFAULT_CHANNEL_CAPACITY,
+            "contract-log".to_string(),
+            "info",
+        );
+        let record = self.record.clone();
         let record_path = self.base_dir.join(CONTRACT_RECORD_FILE);
+        let config = self.config.clone();
         let store = self.store.clone();
         let db = self.db.clone();
-        let operator_pk = self.operator_pk.clone();
-        let operator_pk_base64 = base64::encode(&operator_pk);
-        let filter_builder = self.filter_builder.as_ref().unwrap().clone();
+        let operator_pk_base64 = base64::encode(&self.operator_pk);
         let handlers = self.handlers.clone();
-        let transport_url = DEFAULT_TRANSPORT_URL.get().unwrap();
+        tokio::spawn(async move {
+            while let Some(contract_log) = rx_log.recv().await {
+                match contract_log {
+                    ContractLog::Confirmed(log) => {
+                        let key = match log_key(&log) {
+                            Some(key) => key,
+                            None => {
+                                warn!("pending log without block hash, tx hash or log index, skip");
+                                continue;
+                            }
+                        };
+                        if log_listened(&store, &key).await {
+                            info!("This log has been listened, continue");
+                            continue;
+                        }
+                        match apply_log(log.clone(), &handlers, &db, &operator_pk_base64, &config, &sender).await {
+                            Ok(_) => {
+                                // store log key to local database
+                                store.write(key, vec![0]).await;
+                                let mut record = record.write().await;
+                                record.applied_logs.push(log);
+                                update_record_file(&record, &record_path);
+                            }
+                            Err(e) => {
+                                error!("error hapens, reason: {}", e.as_str());
+                            }
+                        }
+                    }
+                    ContractLog::Orphaned(log) => {
+                        let key = log_key(&log);
+                        let mut record = record.write().await;
+                        match record.applied_logs.iter().position(|applied| log_key(applied) == key) {
+                            Some(index) => {
+                                let applied = record.applied_logs.remove(index);
+                                rollback_log(applied, &store, &db, &handlers, &sender).await;
+                                update_record_file(&record, &record_path);
+                            }
+                            None => {
+                                info!("orphaned log in tx {:?} was never applied, skip", log.transaction_hash);
+                            }
+                        }
+                    }
+                    ContractLog::Synced(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+            error!("contract log channel is closed unexpected");
+        });
+        tx_log
+    }
+
+    pub async fn get_logs_from_contract(&mut self, log_sender: MonitoredSender<ContractLog>) {
+        let record = self.record.clone();
+        let record_path = self.base_dir.join(CONTRACT_RECORD_FILE);
+        let filter_builder = self.filter_builder.as_ref().unwrap().clone();
         tokio::spawn(async move {
             tokio::time::sleep(Duration::from_secs(60 * 3)).await;
-            if record.block_num == 0 {
-                get_block_number(&mut record).await;
-                update_record_file(&record, &record_path);
+            {
+                let mut record = record.write().await;
+                if record.block_num == 0 {
+                    get_block_number(&mut record).await;
+                    update_record_file(&record, &record_path);
+                }
             }
             let mut query_interval = tokio::time::interval(Duration::from_secs(60 * 5));
             loop {
                 tokio::select! {
                     _ = query_interval.tick() => {
-                        let transport = WebSocket::new(transport_url).await;
-                        match &transport {
-                            Ok(_) => {},
-                            Err(e) => {
-                                warn!("can't connect to websocket {}", e);
-                                tokio::time::sleep(Duration::from_secs(60 * 3)).await;
-                                continue;
-                            }
+                        let current_block = match get_current_block().await {
+                            Ok(number) => number.as_u64(),
+                            Err(_) => continue,
+                        };
+                        check_reorged_logs(&record, current_block, &log_sender).await;
+                        let mut record = record.write().await;
+                        // only logs buried under enough blocks are processed
+                        let confirmed_block = current_block.saturating_sub(confirmation_depth());
+                        if record.block_num > confirmed_block {
+                            update_record_file(&record, &record_path);
+                            continue;
                         }
-                        let web3 = Web3::new(transport.unwrap());
+                        let to_block = confirmed_block.min(record.block_num + LOG_QUERY_BLOCK_RANGE - 1);
                         let filter = filter_builder
                             .clone()
                             .from_block(BlockNumber::Number(U64::from(record.block_num)))
-                            .limit(20)
+                            .to_block(BlockNumber::Number(U64::from(to_block)))
                             .build();
-                        match web3.eth().logs(filter).await {
+                        match get_logs(filter).await {
                             Ok(logs) => {
                                 info!("Get {} logs.", &logs.len());
-                                if logs.len() == 0 {
-                                    get_block_number(&mut record).await;
-                                }
                                 for log in logs {
-                                    record.block_num = log
-                                        .block_number
-                                        .map_or_else(|| record.block_num, |bn| bn.as_u64()) + 1;
-
-                                    let listened = match log.transaction_hash {
-                                        Some(hash) => log_listened(&store, &hash).await,
-                                        None => false,
-                                    };
-                                    if listened {
-                                        info!("This log has been listened, continue");
-                                        continue;
-                                    }
-                                    let topic = log.topics[0].clone();
-                                    match handlers.read().await.get(&topic) {
-                                        Some(handler) => {
-                                            match handler
-                                                .process(
-                                                    log,
-                                                    &db,
-                                                    &operator_pk_base64,
-                                                    &config,
-                                                    &sender,
-                                                )
-                                                .await
-                                            {
-                                                Ok(_) => {}
-                                                Err(e) => {
-                                                    error!("error hapens, reason: {}", e.as_str());
-                                                }
-                                            }
-                                        }
-                                        None => {
-                                            error!("Can't find handler");
-                                        }
-                                    };
+                                    let _ = log_sender.send(ContractLog::Confirmed(log)).await;
                                 }
-                                
+                                record.block_num = to_block + 1;
                             }
                             Err(e) => {
-                                error!("{}, {}", ContractError::LogError.as_str(), e);
+                                error!("{}", e.as_str());
                                 continue;
                             }
                         }
@@ -525,71 +831,81 @@ impl Contract {
         });
     }
 
-    pub async fn listen_logs(&self, sender: MonitoredSender<ContractCommand>) {
-        let config = self.config.clone();
-        let store = self.store.clone();
-        let db = self.db.clone();
-        let operator_pk = self.operator_pk.clone();
-        let operator_pk_base64 = base64::encode(&operator_pk);
+    // apply every log from the record cursor up to `to_block` and wait until the pipeline is done with them
+    pub async fn replay(
+        &self,
+        to_block: u64,
+        log_sender: &MonitoredSender<ContractLog>,
+    ) -> Result<Vec<Log>, ContractError> {
+        let filter_builder = self.filter_builder.as_ref().ok_or(ContractError::LogError)?.clone();
+        let mut from_block = self.record.read().await.block_num;
+        let mut logs = vec![];
+        while from_block <= to_block {
+            let range_end = to_block.min(from_block + LOG_QUERY_BLOCK_RANGE - 1);
+            let filter = filter_builder
+                .clone()
+                .from_block(BlockNumber::Number(U64::from(from_block)))
+                .to_block(BlockNumber::Number(U64::from(range_end)))
+                .build();
+            let range_logs = get_logs(filter).await?;
+            info!("replay {} logs from block {} to {}", range_logs.len(), from_block, range_end);
+            for log in range_logs {
+                let _ = log_sender.send(ContractLog::Confirmed(log.clone())).await;
+                logs.push(log);
+            }
+            from_block = range_end + 1;
+        }
+        let (done, synced) = oneshot::channel();
+        let _ = log_sender.send(ContractLog::Synced(done)).await;
+        let _ = synced.await;
+        let mut record = self.record.write().await;
+        record.block_num = from_block;
+        update_record_file(&record, self.base_dir.join(CONTRACT_RECORD_FILE));
+        Ok(logs)
+    }
+
+    pub async fn listen_logs(&self, log_sender: MonitoredSender<ContractLog>) {
         let filter_builder = self.filter_builder.as_ref().unwrap().clone();
         let filter = filter_builder.build();
-        let handlers = self.handlers.clone();
-        let transport_url = DEFAULT_TRANSPORT_URL.get().unwrap();
         tokio::spawn(async move {
+            let clients = execution_clients();
             loop {
-                let transport = WebSocket::new(transport_url).await;
-                match &transport {
-                    Ok(_) => {},
+                let (index, web3) = match clients.websocket().await {
+                    Some(websocket) => websocket,
+                    None => {
+                        warn!("no websocket endpoint is available, polling logs over http");
+                        poll_logs(&filter_builder, &log_sender).await;
+                        continue;
+                    }
+                };
+                let mut sub = match web3.eth_subscribe().subscribe_logs(filter.clone()).await {
+                    Ok(sub) => sub,
                     Err(e) => {
-                        warn!("can't connect to websocket {}", e);
-                        tokio::time::sleep(Duration::from_secs(60 * 3)).await;
+                        warn!("can't subscribe logs {}, polling logs over http", e);
+                        clients.report(index, false);
+                        poll_logs(&filter_builder, &log_sender).await;
                         continue;
                     }
-                }
-                let web3 = Web3::new(transport.unwrap());
-                let mut sub = web3
-                    .eth_subscribe()
-                    .subscribe_logs(filter.clone())
-                    .await
-                    .unwrap();
+                };
                 loop {
-                    match sub.try_next().await.unwrap() {
-                        Some(log) => {
-                            let topic = log.topics[0].clone();
-                            let transaction_hash = log.transaction_hash.clone();
-                            match handlers.read().await.get(&topic) {
-                                Some(handler) => {
-                                    match handler
-                                        .process(
-                                            log,
-                                            &db,
-                                            &operator_pk_base64,
-                                            &config,
-                                            &sender,
-                                        )
-                                        .await
-                                    {
-                                        Ok(_) => {
-                                            // store hash to local database
-                                            match transaction_hash {
-                                                Some(hash) => store.write(hash.as_bytes().to_vec(), vec![0]).await,
-                                                None => {}
-                                            };
-                                        }
-                                        Err(e) => {
-                                            error!("error hapens, reason: {}", e.as_str());
-                                        }
-                                    }
-                                }
-                                None => {
-                                    error!("Can't find handler");
-                                }
-                            };
+                    match sub.try_next().await {
+                        Ok(Some(log)) => {
+                            if log.removed == Some(true) {
+                                // the block of this log has been reorganized out
+                                let _ = log_sender.send(ContractLog::Orphaned(log)).await;
+                                continue;
+                            }
+                            tokio::spawn(wait_for_confirmation(log, log_sender.clone()));
                         }
-                        None => {
+                        Ok(None) => {
                             error!("none event");
                             break;
                         }
+                        Err(e) => {
+                            warn!("log subscription failed {}", e);
+                            clients.report(index, false);
+                            break;
+                        }
                     }
                 }
             }
@@ -597,26 +913,266 @@ impl Contract {
     }
 }
 
+// fallback of the subscription: poll new logs for a while, then give websockets another try
+pub async fn poll_logs(filter_builder: &FilterBuilder, log_sender: &MonitoredSender<ContractLog>) {
+    let mut from_block: Option<u64> = None;
+    for _ in 0..HTTP_POLLING_ROUNDS {
+        if let Ok(current_block) = get_current_block().await {
+            let current_block = current_block.as_u64();
+            let from = from_block.unwrap_or(current_block);
+            if from <= current_block {
+                let filter = filter_builder
+                    .clone()
+                    .from_block(BlockNumber::Number(U64::from(from)))
+                    .to_block(BlockNumber::Number(U64::from(current_block)))
+                    .build();
+                if let Ok(logs) = get_logs(filter).await {
+                    for log in logs {
+                        tokio::spawn(wait_for_confirmation(log, log_sender.clone()));
+                    }
+                    from_block = Some(current_block + 1);
+                }
+            }
+        }
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+    }
+}
+
+pub fn confirmation_depth() -> u64 {
+    *CONFIRMATION_DEPTH.get().unwrap_or(&DEFAULT_CONFIRMATION_DEPTH)
+}
+
+// logs are identified by (block hash, transaction hash, log index): one transaction can emit several events,
+// and the same transaction included again after a reorg is a different log
+pub fn log_key(log: &Log) -> Option<Vec<u8>> {
+    let block_hash = log.block_hash?;
+    let transaction_hash = log.transaction_hash?;
+    let log_index = log.log_index?;
+    let mut key = Vec::with_capacity(96);
+    key.extend_from_slice(block_hash.as_bytes());
+    key.extend_from_slice(transaction_hash.as_bytes());
+    let mut index_bytes = [0u8; 32];
+    log_index.to_big_endian(&mut index_bytes);
+    key.extend_from_slice(&index_bytes);
+    Some(key)
+}
+
+pub async fn canonical_block_hash(block_num: u64) -> Result<Option<H256>, ContractError> {
+    let block = execution_clients()
+        .request(|web3| async move {
+            web3.eth()
+                .block(BlockId::Number(BlockNumber::Number(U64::from(block_num))))
+                .await
+        })
+        .await
+        .map_err(|e| {
+            error!("Can't get block {} {}", block_num, e.as_str());
+            ContractError::BlockNumberError
+        })?;
+    Ok(block.and_then(|b| b.hash))
+}
+
+pub async fn get_logs(filter: Filter) -> Result<Vec<Log>, ContractError> {
+    execution_clients()
+        .request(|web3| {
+            let filter = filter.clone();
+            async move { web3.eth().logs(filter).await }
+        })
+        .await
+        .map_err(|_| ContractError::LogError)
+}
+
+pub async fn apply_log(
+    log: Log,
+    handlers: &Arc<RwLock<HashMap<H256, Box<dyn TopicHandler>>>>,
+    db: &Database,
+    operator_pk_base64: &String,
+    config: &ContractConfig,
+    sender: &MonitoredSender<ContractCommand>,
+) -> Result<(), ContractError> {
+    let topic = log.topics[0].clone();
+    match handlers.read().await.get(&topic) {
+        Some(handler) => {
+            handler
+                .process(log, db, operator_pk_base64, config, sender)
+                .await
+        }
+        None => {
+            error!("Can't find handler");
+            Ok(())
+        }
+    }
+}
+
+pub async fn rollback_log(
+    log: Log,
+    store: &Store,
+    db: &Database,
+    handlers: &Arc<RwLock<HashMap<H256, Box<dyn TopicHandler>>>>,
+    sender: &MonitoredSender<ContractCommand>,
+) {
+    warn!(
+        "rolling back log in tx {:?} of orphaned block {:?}",
+        log.transaction_hash, log.block_hash
+    );
+    let topic = log.topics[0].clone();
+    let key = log_key(&log);
+    match handlers.read().await.get(&topic) {
+        Some(handler) => {
+            if let Err(e) = handler.rollback(log, db, sender).await {
+                error!("error hapens when rollback, reason: {}", e.as_str());
+            }
+        }
+        None => {
+            error!("Can't find handler");
+        }
+    }
+    if let Some(key) = key {
+        store.delete(key).await;
+    }
+}
+
+// check that every recently applied log is still in the canonical chain, report the ones that are not
+pub async fn check_reorged_logs(
+    record: &Arc<RwLock<ContractRecord>>,
+    current_block: u64,
+    log_sender: &MonitoredSender<ContractLog>,
+) {
+    let applied_logs = {
+        let mut record = record.write().await;
+        record.applied_logs.retain(|log| {
+            log.block_number
+                .map_or(false, |bn| bn.as_u64() + REORG_TRACKING_BLOCKS >= current_block)
+        });
+        record.applied_logs.clone()
+    };
+    let mut rewind_to: Option<u64> = None;
+    for log in applied_logs {
+        let block_num = match log.block_number {
+            Some(bn) => bn.as_u64(),
+            None => continue,
+        };
+        match canonical_block_hash(block_num).await {
+            Ok(Some(hash)) if Some(hash) == log.block_hash => {}
+            Ok(_) => {
+                let _ = log_sender.send(ContractLog::Orphaned(log)).await;
+                rewind_to = Some(rewind_to.map_or(block_num, |bn| bn.min(block_num)));
+            }
+            // can't tell now, check again next time
+            Err(_) => {}
+        }
+    }
+    if let Some(block_num) = rewind_to {
+        let mut record = record.write().await;
+        if block_num < record.block_num {
+            info!("reorg detected, rescan logs from block {}", block_num);
+            record.block_num = block_num;
+        }
+    }
+}
+
+// wait until the block of the log is confirmed, then hand it over if it's still canonical
+pub async fn wait_for_confirmation(log: Log, log_sender: MonitoredSender<ContractLog>) {
+    let block_num = match log.block_number {
+        Some(bn) => bn.as_u64(),
+        None => {
+            warn!("log without block number from subscription, skip");
+            return;
+        }
+    };
+    loop {
+        if let Ok(current_block) = get_current_block().await {
+            if current_block.as_u64() >= block_num + confirmation_depth() {
+                match canonical_block_hash(block_num).await {
+                    Ok(Some(hash)) if Some(hash) == log.block_hash => break,
+                    Ok(_) => {
+                        info!("block {} of log in tx {:?} is orphaned, skip", block_num, log.transaction_hash);
+                        return;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+        tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+    }
+    let _ = log_sender.send(ContractLog::Confirmed(log)).await;
+}
+
 pub async fn get_block_number(record: &mut ContractRecord) {
-    let transport_url = DEFAULT_TRANSPORT_URL.get().unwrap();
-    let web3 = Web3::new(WebSocket::new(transport_url).await.unwrap());
     // if can't get block number, reset to zero.
-    record.block_num = web3.eth().block_number().await.map_or_else(
-        |_| {
-            error!("{}", ContractError::BlockNumberError.as_str());
-            0
-        },
+    record.block_num = get_current_block().await.map_or_else(
+        |_| 0,
         |number| number.as_u64(),
     );
 }
 
+// number and timestamp of the latest block
+pub async fn get_head() -> Result<(u64, u64), ContractError> {
+    let block = execution_clients()
+        .request(|web3| async move { web3.eth().block(BlockId::Number(BlockNumber::Latest)).await })
+        .await
+        .map_err(|e| {
+            error!("Can't get latest block {}", e.as_str());
+            ContractError::BlockNumberError
+        })?
+        .ok_or(ContractError::BlockNumberError)?;
+    let number = block.number.ok_or(ContractError::BlockNumberError)?;
+    Ok((number.as_u64(), block.timestamp.as_u64()))
+}
+
+pub async fn check_paid_blocks(
+    db: &Database,
+    head: u64,
+    timestamp: u64,
+    sender: &MonitoredSender<ContractCommand>,
+) {
+    let owners = match db.query_all_validator_address().await {
+        Ok(owners) => owners,
+        Err(e) => {
+            error!("query validator address failed {:?}", e);
+            return;
+        }
+    };
+    for owner in owners {
+        let validators = match db.query_validator_by_address(owner).await {
+            Ok(validators) => validators,
+            Err(e) => {
+                error!("query validator releated to the address failed {:?}", e);
+                continue;
+            }
+        };
+        for va in validators {
+            // unknown until refreshed from the contract, e.g. validators stored before paid blocks were recorded
+            if va.paid_block == 0 {
+                continue;
+            }
+            let va_str = hex::encode(&va.public_key);
+            let paid = head < va.paid_block;
+            if va.active && !paid {
+                info!("validator {} reached paid block {} at block {}", va.id, va.paid_block, head);
+                db.disable_validator(va_str.clone()).await;
+                db.end_validator_service(va_str, head, timestamp).await;
+                let _ = sender.send(ContractCommand::StopValidator(va)).await;
+            } else if !va.active && paid {
+                info!("validator {} paid until block {}, resume at block {}", va.id, va.paid_block, head);
+                db.enable_validator(va_str.clone()).await;
+                db.start_validator_service(va_str, owner, head, timestamp).await;
+                let _ = sender.send(ContractCommand::ActivateValidator(va)).await;
+            } else if va.active {
+                db.start_validator_service(va_str, owner, head, timestamp).await;
+            }
+        }
+    }
+}
+
 pub async fn get_current_block() -> Result<U64, ContractError> {
-    let transport_url = DEFAULT_TRANSPORT_URL.get().unwrap();
-    let web3 = Web3::new(WebSocket::new(transport_url).await.unwrap());
-    web3.eth().block_number().await.or_else(|e| {
-        error!("{:?} {}", e, ContractError::BlockNumberError.as_str());
-        Err(ContractError::BlockNumberError)
-    })
+    execution_clients()
+        .request(|web3| async move { web3.eth().block_number().await })
+        .await
+        .or_else(|_| {
+            error!("{}", ContractError::BlockNumberError.as_str());
+            Err(ContractError::BlockNumberError)
+        })
 }
 
 pub fn update_record_file<P: AsRef<Path>>(record: &ContractRecord, path: P) {
@@ -628,8 +1184,8 @@ pub fn update_record_file<P: AsRef<Path>>(record: &ContractRecord, path: P) {
         .unwrap();
 }
 
-pub async fn log_listened(store: &Store, log_hash: &H256) -> bool {
-    match store.read(log_hash.as_fixed_bytes().to_vec()).await {
+pub async fn log_listened(store: &Store, log_key: &Vec<u8>) -> bool {
+    match store.read(log_key.clone()).await {
         Ok(res) => res.map_or_else(|| false, |_| true),
         Err(_) => {
             error!("{}", ContractError::StoreError.as_str());
@@ -639,6 +1195,7 @@ pub async fn log_listened(store: &Store, log_hash: &H256) -> bool {
 }
 
 pub async fn process_validator_registration(
+    event: &Event,
     raw_log: Log,
     db: &Database,
     operator_pk_base64: &String,
@@ -646,71 +1203,12 @@ pub async fn process_validator_registration(
     sender: &MonitoredSender<ContractCommand>,
 ) -> Result<(), ContractError> {
     info!("process_validator_registration");
-    let validator_reg_event = Event {
-        name: CONTRACT_VA_REG_EVENT_NAME.to_string(),
-        inputs: vec![
-            EventParam {
-                name: "ownerAddress".to_string(),
-                kind: ParamType::Address,
-                indexed: true,
-            },
-            EventParam {
-                name: "publicKey".to_string(),
-                kind: ParamType::Bytes,
-                indexed: false,
-            },
-            EventParam {
-                name: "operatorIds".to_string(),
-                kind: ParamType::Array(Box::new(ParamType::Uint(32))),
-                indexed: false,
-            },
-            EventParam {
-                name: "sharesPublicKeys".to_string(),
-                kind: ParamType::Array(Box::new(ParamType::Bytes)),
-                indexed: false,
-            },
-            EventParam {
-                name: "encryptedKeys".to_string(),
-                kind: ParamType::Array(Box::new(ParamType::Bytes)),
-                indexed: false,
-            },
-            EventParam {
-                name: "paidBlockNumber".to_string(),
-                kind: ParamType::Uint(256),
-                indexed: false,
-            },
-        ],
-        anonymous: false,
-    };
-    let log = validator_reg_event
-        .parse_log(RawLog {
-            topics: raw_log.topics,
-            data: raw_log.data.0,
-        })
-        .map_err(|_| ContractError::LogParseError)?;
-    let address = log.params[0]
-        .value
-        .clone()
-        .into_address()
-        .ok_or(ContractError::LogParseError)?;
-    let va_pk = log.params[1]
-        .value
-        .clone()
-        .into_bytes()
-        .ok_or(ContractError::LogParseError)?;
+    let accept_legacy_shares = config.accepts_legacy_shares(raw_log.block_number);
+    let log = EventLog::decode(event, raw_log)?;
+    let address = log.address("ownerAddress")?;
+    let va_pk = log.bytes("publicKey")?;
     let validator_id = convert_va_pk_to_u64(&va_pk);
-    let operator_tokens = log.params[2]
-        .value
-        .clone()
-        .into_array()
-        .ok_or(ContractError::LogParseError)?;
-    let op_ids: Vec<u32> = operator_tokens
-        .into_iter()
-        .map(|token| {
-            let op_id = token.into_uint().unwrap();
-            op_id.as_u32()
-        })
-        .collect();
+    let op_ids = log.u32_array("operatorIds")?;
     info!("operator ids {:?}", op_ids);
     let mut operator_pks: Vec<String> = Vec::new();
     // query local database first, if not existing, query from contract
@@ -732,15 +1230,9 @@ pub async fn process_validator_registration(
     }
     if operator_pks.contains(operator_pk_base64) {
         set_global_operator_id(&operator_pks, &operator_pk_base64, &op_ids);
-        let shared_pks = parse_bytes_token(log.params[3].value.clone())?;
-        let encrypted_sks = parse_bytes_token(log.params[4].value.clone())?;
-        // TODO paid block should store for tokenomics
-        let _paid_block_number = log.params[5]
-            .value
-            .clone()
-            .into_uint()
-            .ok_or(ContractError::LogParseError)?
-            .as_u64();
+        let shared_pks = log.bytes_array("sharesPublicKeys")?;
+        let encrypted_sks = log.bytes_array("encryptedKeys")?;
+        let paid_block = log.uint("paidBlockNumber")?.low_u64();
         // check array length should be same
         if shared_pks.len() != encrypted_sks.len() {
             return Err(ContractError::InvalidArgumentError);
@@ -756,23 +1248,150 @@ pub async fn process_validator_registration(
             owner_address: address,
             public_key: va_pk.try_into().unwrap(),
             releated_operators: op_ids,
-            active: true
+            active: true,
+            paid_block,
         };
         // save validator in local database
-        db.insert_validator(validator.clone()).await;
+        let existing = db
+            .query_validator_by_public_key(hex::encode(&validator.public_key))
+            .await
+            .map_err(|_| ContractError::DatabaseError)?;
+        if existing.is_none() {
+            db.insert_validator(validator.clone()).await;
+        }
+        // the paid block belongs to the account, it applies to all validators of the owner
+        db.update_paid_block(address, paid_block).await;
         let _ = sender
             .send(ContractCommand::StartValidator(
                 validator,
                 op_pk_bn,
                 shared_pks,
                 encrypted_sks,
+                accept_legacy_shares,
             ))
             .await;
     }
     Ok(())
 }
 
+// there is no separate update event, an operator changing its name or public key is registered again
+pub async fn process_operator_registration(
+    event: &Event,
+    raw_log: Log,
+    db: &Database,
+    _operator_pk_base64: &String,
+    _config: &ContractConfig,
+    _sender: &MonitoredSender<ContractCommand>,
+) -> Result<(), ContractError> {
+    let log = EventLog::decode(event, raw_log)?;
+    let operator = Operator {
+        id: log.u32("id")?,
+        name: log.string("name")?,
+        address: log.address("ownerAddress")?,
+        public_key: log
+            .bytes("publicKey")?
+            .try_into()
+            .map_err(|_| ContractError::InvalidArgumentError)?,
+    };
+    info!("process_operator_registration {} {}", operator.id, operator.name);
+    if let Some(pk_str) = db
+        .query_operator_public_key_by_id(operator.id)
+        .await
+        .map_err(|_| ContractError::DatabaseError)?
+    {
+        if pk_str != base64::encode(&operator.public_key) {
+            let validators = db
+                .query_validators_by_operator(operator.id)
+                .await
+                .map_err(|_| ContractError::DatabaseError)?;
+            warn!(
+                "operator {} changed its public key, {} local committees still use the old key",
+                operator.id,
+                validators.len()
+            );
+        }
+    }
+    db.insert_operator(operator).await;
+    Ok(())
+}
+
+pub async fn process_operator_removal(
+    event: &Event,
+    raw_log: Log,
+    db: &Database,
+    _operator_pk_base64: &String,
+    _config: &ContractConfig,
+    sender: &MonitoredSender<ContractCommand>,
+) -> Result<(), ContractError> {
+    let log = EventLog::decode(event, raw_log)?;
+    let id = log.u32("operatorId")?;
+    info!("process_operator_removal {}", id);
+    let operator = match db
+        .query_operator_by_id(id)
+        .await
+        .map_err(|_| ContractError::DatabaseError)?
+    {
+        Some(operator) => operator,
+        // never seen by this node, so no local committee includes it
+        None => return Ok(()),
+    };
+    db.delete_operator(id).await;
+    if Some(&id) == SELF_OPERATOR_ID.get() {
+        error!("this operator {} has been removed from the network contract", id);
+    }
+    let mut committees = Vec::new();
+    for validator in db
+        .query_validators_by_operator(id)
+        .await
+        .map_err(|_| ContractError::DatabaseError)?
+    {
+        let mut registered = Vec::new();
+        for op_id in &validator.releated_operators {
+            if db
+                .query_operator_public_key_by_id(*op_id)
+                .await
+                .map_err(|_| ContractError::DatabaseError)?
+                .is_some()
+            {
+                registered.push(*op_id);
+            }
+        }
+        committees.push((validator, registered));
+    }
+    if !committees.is_empty() {
+        let _ = sender
+            .send(ContractCommand::OperatorRemoved(operator, committees))
+            .await;
+    }
+    Ok(())
+}
+
+pub async fn process_account_funds(
+    event: &Event,
+    raw_log: Log,
+    db: &Database,
+    _operator_pk_base64: &String,
+    config: &ContractConfig,
+    _sender: &MonitoredSender<ContractCommand>,
+) -> Result<(), ContractError> {
+    let log = EventLog::decode(event, raw_log)?;
+    let owner = log.address("ownerAddress")?;
+    if db
+        .query_validator_by_address(owner)
+        .await
+        .map_err(|_| ContractError::DatabaseError)?
+        .is_empty()
+    {
+        return Ok(());
+    }
+    // the event only carries the amount, the contract computes the new paid block
+    let paid_block = query_account_paid_block(config, owner).await?;
+    db.update_paid_block(owner, paid_block).await;
+    Ok(())
+}
+
 pub async fn process_validator_removal(
+    event: &Event,
     raw_log: Log,
     db: &Database,
     _operator_pk_base64: &String,
@@ -780,43 +1399,18 @@ pub async fn process_validator_removal(
     sender: &MonitoredSender<ContractCommand>,
 ) -> Result<(), ContractError> {
     info!("process_validator_removal");
-    let validator_rm_event = Event {
-        name: CONTRACT_VA_RM_EVENT_NAME.to_string(),
-        inputs: vec![
-            EventParam {
-                name: "ownerAddress".to_string(),
-                kind: ParamType::Address,
-                indexed: true,
-            },
-            EventParam {
-                name: "publicKey".to_string(),
-                kind: ParamType::Bytes,
-                indexed: false,
-            },
-        ],
-        anonymous: false,
-    };
-    let log = validator_rm_event
-        .parse_log(RawLog {
-            topics: raw_log.topics,
-            data: raw_log.data.0,
-        })
-        .map_err(|_| ContractError::LogParseError)?;
-    let owner_address = log.params[0]
-        .value
-        .clone()
-        .into_address()
-        .ok_or(ContractError::LogParseError)?;
-    let va_pk = log.params[1]
-        .value
-        .clone()
-        .into_bytes()
-        .ok_or(ContractError::LogParseError)?;
+    let log = EventLog::decode(event, raw_log)?;
+    let owner_address = log.address("ownerAddress")?;
+    let va_pk = log.bytes("publicKey")?;
     let id = convert_va_pk_to_u64(&va_pk);
 
     let va_str = hex::encode(&va_pk);
 
-    db.delete_validator(va_str).await;
+    db.delete_validator(va_str.clone()).await;
+    match get_head().await {
+        Ok((head, timestamp)) => db.end_validator_service(va_str, head, timestamp).await,
+        Err(_) => error!("Can't end service of validator {}", id),
+    }
 
     let _ = sender
         .send(ContractCommand::RemoveValidator(Validator {
@@ -824,69 +1418,182 @@ pub async fn process_validator_removal(
             owner_address,
             public_key: va_pk.try_into().unwrap(),
             releated_operators: vec![],
-            active: true
+            active: true,
+            paid_block: 0,
         }))
         .await;
     Ok(())
 }
 
-pub async fn process_initializer_registration(
+// the operators that keep the validator reshare it to the new committee, so the operators that
+// have to know about it are the ones in either committee. The local record is kept until the
+// reshared keys are in use.
+pub async fn process_validator_operators_change(
+    event: &Event,
     raw_log: Log,
     db: &Database,
     operator_pk_base64: &String,
     config: &ContractConfig,
     sender: &MonitoredSender<ContractCommand>,
 ) -> Result<(), ContractError> {
-    let ini_reg_event = Event {
-        name: CONTRACT_INI_REG_EVENT_NAME.to_string(),
-        inputs: vec![
-            EventParam {
-                name: "initializerId".to_string(),
-                kind: ParamType::Uint(32),
-                indexed: false,
-            },
-            EventParam {
-                name: "ownerAddress".to_string(),
-                kind: ParamType::Address,
-                indexed: false,
-            },
-            EventParam {
-                name: "operatorIds".to_string(),
-                kind: ParamType::Array(Box::new(ParamType::Uint(32))),
-                indexed: false,
-            },
-        ],
-        anonymous: false,
+    info!("process_validator_operators_change");
+    let log = EventLog::decode(event, raw_log)?;
+    let owner_address = log.address("ownerAddress")?;
+    let va_pk = log.bytes("publicKey")?;
+    let old_op_ids = log.u32_array("oldOperatorIds")?;
+    let op_ids = log.u32_array("operatorIds")?;
+    let epoch = log.uint("epoch")?.low_u64();
+    let mut operator_pks: Vec<String> = Vec::new();
+    for op_id in &op_ids {
+        match db
+            .query_operator_public_key_by_id(*op_id)
+            .await
+            .map_err(|_| ContractError::DatabaseError)?
+        {
+            Some(pk_str) => {
+                operator_pks.push(pk_str);
+            }
+            None => {
+                let operator = query_operator_from_contract(config, *op_id).await?;
+                operator_pks.push(base64::encode(&operator.public_key));
+                db.insert_operator(operator).await;
+            }
+        };
+    }
+    let va_str = hex::encode(&va_pk);
+    let existing = db
+        .query_validator_by_public_key(va_str.clone())
+        .await
+        .map_err(|_| ContractError::DatabaseError)?;
+    if !operator_pks.contains(operator_pk_base64) && existing.is_none() {
+        return Ok(());
+    }
+    if operator_pks.contains(operator_pk_base64) {
+        set_global_operator_id(&operator_pks, &operator_pk_base64, &op_ids);
+    }
+    let paid_block = match &existing {
+        Some(validator) => validator.paid_block,
+        None => query_account_paid_block(config, owner_address).await?,
     };
-    let log = ini_reg_event
-        .parse_log(RawLog {
-            topics: raw_log.topics,
-            data: raw_log.data.0,
-        })
-        .map_err(|_| ContractError::LogParseError)?;
-    let id = log.params[0]
-        .value
-        .clone()
-        .into_uint()
-        .ok_or(ContractError::LogParseError)?
-        .as_u32();
-    let address = log.params[1]
-        .value
-        .clone()
-        .into_address()
-        .ok_or(ContractError::LogParseError)?;
-    let operator_tokens = log.params[2]
-        .value
-        .clone()
-        .into_array()
-        .ok_or(ContractError::LogParseError)?;
-    let op_ids: Vec<u32> = operator_tokens
+    let validator = Validator {
+        id: convert_va_pk_to_u64(&va_pk),
+        owner_address,
+        public_key: va_pk.try_into().map_err(|_| ContractError::LogParseError)?,
+        releated_operators: op_ids,
+        active: true,
+        paid_block,
+    };
+    // the change may be back after a reorg had cancelled it
+    db.set_reshare_cancelled(va_str, epoch, false).await;
+    let op_pk_bn: Vec<Vec<u8>> = operator_pks
         .into_iter()
-        .map(|token| {
-            let op_id = token.into_uint().unwrap();
-            op_id.as_u32()
-        })
+        .map(|s| base64::decode(s).unwrap())
         .collect();
+    let _ = sender
+        .send(ContractCommand::ReshareValidator(validator, op_pk_bn, old_op_ids, epoch))
+        .await;
+    Ok(())
+}
+
+// the pending reshare is dropped, the local record still belongs to the current committee
+pub async fn rollback_validator_operators_change(
+    event: &Event,
+    raw_log: Log,
+    db: &Database,
+) -> Result<(), ContractError> {
+    info!("rollback_validator_operators_change");
+    let log = EventLog::decode(event, raw_log)?;
+    let va_pk = log.bytes("publicKey")?;
+    let op_ids = log.u32_array("operatorIds")?;
+    let epoch = log.uint("epoch")?.low_u64();
+    let va_str = hex::encode(&va_pk);
+    db.set_reshare_cancelled(va_str.clone(), epoch, true).await;
+    let existing = db
+        .query_validator_by_public_key(va_str)
+        .await
+        .map_err(|_| ContractError::DatabaseError)?;
+    if existing.map_or(false, |va| va.releated_operators == op_ids) {
+        warn!(
+            "validator {} was reshared at epoch {} already, local state may need a resync",
+            convert_va_pk_to_u64(&va_pk),
+            epoch
+        );
+    }
+    Ok(())
+}
+
+// the local record follows the new committee of the validator once its reshared keys are in use
+pub async fn apply_committee_change(db: &Database, validator: Validator) {
+    let va_str = hex::encode(&validator.public_key);
+    let existing = match db.query_validator_by_public_key(va_str.clone()).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            error!("query validator {} failed {:?}", validator.id, e);
+            None
+        }
+    };
+    db.delete_validator(va_str.clone()).await;
+    if SELF_OPERATOR_ID.get().map_or(false, |id| validator.releated_operators.contains(id)) {
+        // a stopped validator stays stopped with the new committee
+        let (active, paid_block) = existing.map_or((true, validator.paid_block), |va| (va.active, va.paid_block));
+        db.insert_validator(Validator { paid_block, ..validator }).await;
+        if !active {
+            db.disable_validator(va_str).await;
+        }
+    } else if let Ok((head, timestamp)) = get_head().await {
+        db.end_validator_service(va_str, head, timestamp).await;
+    }
+}
+
+pub async fn rollback_validator_registration(
+    event: &Event,
+    raw_log: Log,
+    db: &Database,
+    sender: &MonitoredSender<ContractCommand>,
+) -> Result<(), ContractError> {
+    info!("rollback_validator_registration");
+    let log = EventLog::decode(event, raw_log)?;
+    let owner_address = log.address("ownerAddress")?;
+    let va_pk = log.bytes("publicKey")?;
+    let va_str = hex::encode(&va_pk);
+    // nothing to undo if this node is not a member of the committee
+    if db
+        .query_validator_by_public_key(va_str.clone())
+        .await
+        .map_err(|_| ContractError::DatabaseError)?
+        .is_none()
+    {
+        return Ok(());
+    }
+    db.delete_validator(va_str.clone()).await;
+    if let Ok((head, timestamp)) = get_head().await {
+        db.end_validator_service(va_str, head, timestamp).await;
+    }
+    let _ = sender
+        .send(ContractCommand::RemoveValidator(Validator {
+            id: convert_va_pk_to_u64(&va_pk),
+            owner_address,
+            public_key: va_pk.try_into().map_err(|_| ContractError::LogParseError)?,
+            releated_operators: vec![],
+            active: true,
+            paid_block: 0,
+        }))
+        .await;
+    Ok(())
+}
+
+pub async fn process_initializer_registration(
+    event: &Event,
+    raw_log: Log,
+    db: &Database,
+    operator_pk_base64: &String,
+    config: &ContractConfig,
+    sender: &MonitoredSender<ContractCommand>,
+) -> Result<(), ContractError> {
+    let log = EventLog::decode(event, raw_log)?;
+    let id = log.u32("initializerId")?;
+    let address = log.address("ownerAddress")?;
+    let op_ids = log.u32_array("operatorIds")?;
 
     let mut operator_pks: Vec<String> = Vec::new();
     println!("operator ids {:?}", op_ids);
@@ -908,6 +1615,15 @@ pub async fn process_initializer_registration(
     }
     if operator_pks.contains(operator_pk_base64) {
         set_global_operator_id(&operator_pks, &operator_pk_base64, &op_ids);
+        if db
+            .query_initializer(id)
+            .await
+            .map_err(|_| ContractError::DatabaseError)?
+            .is_some()
+        {
+            info!("Initializer {} has been registered, continue.", id);
+            return Ok(());
+        }
         let initializer = Initializer {
             id,
             owner_address: address,
@@ -928,55 +1644,17 @@ pub async fn process_initializer_registration(
 }
 
 pub async fn process_minipool_created(
+    event: &Event,
     raw_log: Log,
     db: &Database,
     _operator_pk_base64: &String,
     _config: &ContractConfig,
     sender: &MonitoredSender<ContractCommand>,
 ) -> Result<(), ContractError> {
-    let minipool_created_event = Event {
-        name: CONTRACT_MINIPOOL_CREATED_EVENT_NAME.to_string(),
-        inputs: vec![
-            EventParam {
-                name: "initializerId".to_string(),
-                kind: ParamType::Uint(32),
-                indexed: false,
-            },
-            EventParam {
-                name: "validatorPublicKey".to_string(),
-                kind: ParamType::Bytes,
-                indexed: false,
-            },
-            EventParam {
-                name: "minipoolAddress".to_string(),
-                kind: ParamType::Address,
-                indexed: false,
-            },
-        ],
-        anonymous: false,
-    };
-    let log = minipool_created_event
-        .parse_log(RawLog {
-            topics: raw_log.topics,
-            data: raw_log.data.0,
-        })
-        .map_err(|_| ContractError::LogParseError)?;
-    let id = log.params[0]
-        .value
-        .clone()
-        .into_uint()
-        .ok_or(ContractError::LogParseError)?
-        .as_u32();
-    let va_pk = log.params[1]
-        .value
-        .clone()
-        .into_bytes()
-        .ok_or(ContractError::LogParseError)?;
-    let minipool_address = log.params[2]
-        .value
-        .clone()
-        .into_address()
-        .ok_or(ContractError::LogParseError)?;
+    let log = EventLog::decode(event, raw_log)?;
+    let id = log.u32("initializerId")?;
+    let va_pk = log.bytes("validatorPublicKey")?;
+    let minipool_address = log.address("minipoolAddress")?;
 
     match db
         .update_initializer(id, hex::encode(&va_pk), format!("{0:0x}", minipool_address))
@@ -1012,33 +1690,15 @@ pub async fn process_minipool_created(
 }
 
 pub async fn process_minipool_ready(
+    event: &Event,
     raw_log: Log,
     db: &Database,
     _operator_pk_base64: &String,
     _config: &ContractConfig,
     sender: &MonitoredSender<ContractCommand>,
 ) -> Result<(), ContractError> {
-    let minipool_ready_event = Event {
-        name: CONTRACT_MINIPOOL_READY_EVENT_NAME.to_string(),
-        inputs: vec![EventParam {
-            name: "initializerId".to_string(),
-            kind: ParamType::Uint(32),
-            indexed: false,
-        }],
-        anonymous: false,
-    };
-    let log = minipool_ready_event
-        .parse_log(RawLog {
-            topics: raw_log.topics,
-            data: raw_log.data.0,
-        })
-        .map_err(|_| ContractError::LogParseError)?;
-    let id = log.params[0]
-        .value
-        .clone()
-        .into_uint()
-        .ok_or(ContractError::LogParseError)?
-        .as_u32();
+    let log = EventLog::decode(event, raw_log)?;
+    let id = log.u32("initializerId")?;
     match db.query_initializer(id).await {
         Ok(initializer_option) => match initializer_option {
             Some(initializer) => {
@@ -1078,89 +1738,64 @@ pub async fn query_operator_from_contract(
     config: &ContractConfig,
     id: u32,
 ) -> Result<Operator, ContractError> {
-    let transport_url = DEFAULT_TRANSPORT_URL.get().unwrap();
-    let web3 = Web3::new(WebSocket::new(transport_url).await.unwrap());
-    let raw_abi = std::fs::read_to_string(&config.safestake_registry_abi_path).or_else(|e| {
-        error!(
-            "Can't read from {} {}",
-            &config.safestake_registry_abi_path, e
-        );
-        Err(ContractError::FileError)
-    })?;
-    let raw_json: Value = serde_json::from_str(&raw_abi).unwrap();
-    let abi = raw_json["abi"].to_string();
+    let abi = load_abi(&config.safestake_registry_abi_path)?;
     let address = Address::from_slice(&hex::decode({
         match REGISTRY_CONTRACT.get() {
             Some(registry_contract) => registry_contract,
             None => &config.safestake_registry_address
         }
     }).unwrap());
-    let contract = EthContract::from_json(web3.eth(), address, abi.as_bytes()).or_else(|e| {
-        error!("Can't create contract from json {}", e);
-        Err(ContractError::ContractParseError)
-    })?;
     let (name, address, pk, _, _, _, _): (String, Address, Vec<u8>, U256, U256, U256, bool) =
-        contract
-            .query("getOperatorById", (id,), None, Options::default(), None)
+        execution_clients()
+            .request(|web3| {
+                let contract = EthContract::new(web3.eth(), address, abi.clone());
+                async move {
+                    contract
+                        .query("getOperatorById", (id,), None, Options::default(), None)
+                        .await
+                }
+            })
             .await
-            .or_else(|e| {
-                error!("Can't query from contract {}", e);
+            .or_else(|_| {
+                error!("Can't query operator {} from contract", id);
                 Err(ContractError::QueryError)
             })?;
     Ok(Operator {
         id,
         name,
         address,
-        public_key: pk.try_into().unwrap(),
+        public_key: pk.try_into().map_err(|_| ContractError::InvalidArgumentError)?,
     })
 }
 
-// check the paid block number of address. If the block is behind the current block number, stop validators releated to the address
-pub async fn check_account (
+// the block until which the account has paid for its validators
+pub async fn query_account_paid_block(
     config: &ContractConfig,
     owner: Address
-) -> Result<bool, ContractError> {
-    let transport_url = DEFAULT_TRANSPORT_URL.get().unwrap();
-    let web3 = Web3::new(WebSocket::new(transport_url).await.unwrap());
-    let raw_abi = std::fs::read_to_string(&config.safestake_network_abi_path).or_else(|e| {
-        error!(
-            "Can't read from {} {}",
-            &config.safestake_network_abi_path, e
-        );
-        Err(ContractError::FileError)
-    })?;
-    let raw_json: Value = serde_json::from_str(&raw_abi).unwrap();
-    let abi = raw_json["abi"].to_string();
+) -> Result<u64, ContractError> {
+    let abi = load_abi(&config.safestake_network_abi_path)?;
     let address = Address::from_slice(&hex::decode({
         match NETWORK_CONTRACT.get() {
             Some(network_contract) => network_contract,
             None => &config.safestake_network_address
         }
     }).unwrap());
-    let contract = EthContract::from_json(web3.eth(), address, abi.as_bytes()).or_else(|e| {
-        error!("Can't create contract from json {}", e);
-        Err(ContractError::ContractParseError)
-    })?;
-    let paid_block: U256 = contract.query("getAccountPaidBlockNumber", (owner, ), None, Options::default(), None).await.or_else(|e| {
-        error!("Can't getAccountPaidBlockNumber from contract {}", e);
-        Err(ContractError::QueryError)
-    })?;
-    let current_block = get_current_block().await?;
-    info!("current block {:?}, paid block {:?} , account {}", current_block, paid_block,  owner);
-    if current_block.as_u64() >= paid_block.as_u64() {
-        Ok(true)
-    } else {    
-        Ok(false)
-    }
-}
-
-pub fn parse_bytes_token(tk: token::Token) -> Result<Vec<Vec<u8>>, ContractError> {
-    let token_arr = tk.into_array().ok_or(ContractError::LogParseError)?;
-    let res: Vec<Vec<u8>> = token_arr
-        .into_iter()
-        .map(|token| token.into_bytes().unwrap())
-        .collect();
-    Ok(res)
+    let paid_block: U256 = execution_clients()
+        .request(|web3| {
+            let contract = EthContract::new(web3.eth(), address, abi.clone());
+            async move {
+                contract
+                    .query("getAccountPaidBlockNumber", (owner, ), None, Options::default(), None)
+                    .await
+            }
+        })
+        .await
+        .or_else(|_| {
+            error!("Can't getAccountPaidBlockNumber from contract");
+            Err(ContractError::QueryError)
+        })?;
+    info!("paid block {:?}, account {}", paid_block, owner);
+    Ok(paid_block.low_u64())
 }
 
 // only call once
@@ -2,12 +2,25 @@ use rusqlite::{params, Connection, Result, DropBehavior};
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::sync::oneshot;
 use log::{error};
-use crate::node::contract::{Operator, Validator, Initializer};
+use crate::node::contract::{ContractCommand, Operator, Validator, Initializer};
 use web3::types::Address;
 use std::path::{Path};
+use std::time::Duration;
 pub type DbError = rusqlite::Error;
 type DbResult<T> = Result<T, DbError>;
 
+// states of a queued contract command
+const COMMAND_PENDING: i64 = 0;
+const COMMAND_DEAD: i64 = 1;
+
+#[derive(Clone)]
+pub struct QueuedCommand {
+    pub id: i64,
+    pub command: ContractCommand,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
 pub enum DbCommand {
     InsertOperator(Operator),
     InsertValidator(Validator),
@@ -15,6 +28,7 @@ pub enum DbCommand {
     DeleteValidator(String), // delete validator by pk
     QueryOperatorById(u32, oneshot::Sender<DbResult<Option<Operator>>>), // query operator by operator id
     QueryValidatorByPublicKey(String, oneshot::Sender<DbResult<Option<Validator>>>),
+    QueryValidatorsByOperator(u32, oneshot::Sender<DbResult<Vec<Validator>>>), // validators whose committee includes the operator
     QueryOperatorPublicKeyByIds(Vec<u32>, oneshot::Sender<DbResult<Option<Vec<String>>>>),
     QueryOperatorPublicKeyById(u32, oneshot::Sender<DbResult<Option<String>>>),
     InsertInitializer(Initializer),
@@ -25,7 +39,20 @@ pub enum DbCommand {
     QueryValidatorByAddress(Address, oneshot::Sender<DbResult<Vec<Validator>>>),
     DisableValidator(String),
     EnableValidator(String),
-    ValidatorActive(String, oneshot::Sender<DbResult<bool>>)
+    ValidatorActive(String, oneshot::Sender<DbResult<bool>>),
+    UpdatePaidBlock(Address, u64), // update paid block of all validators of the owner
+    StartValidatorService(String, Address, u64, u64), // validator pk, owner, block number, block timestamp
+    EndValidatorService(String, u64, u64),
+    QueryServedEpochs(u64, u64, oneshot::Sender<DbResult<Vec<(Address, u64)>>>), // now, seconds per epoch
+    EnqueueCommand(String, String, u64, oneshot::Sender<DbResult<i64>>), // subject, serialized command, now
+    QueryDueCommands(u64, oneshot::Sender<DbResult<Vec<QueuedCommand>>>),
+    CompleteCommand(i64),
+    RetryCommand(i64, u64, String), // id, next attempt time, error
+    DeadLetterCommand(i64, String),
+    QueryDeadCommands(oneshot::Sender<DbResult<Vec<QueuedCommand>>>),
+    SetReshareCancelled(String, u64, bool), // validator pk, activation epoch, cancelled
+    QueryReshareCancelled(String, u64, oneshot::Sender<DbResult<bool>>),
+    Close(oneshot::Sender<()>), // answered once the connection is closed
 }
 
 #[derive(Clone)]
@@ -37,6 +64,8 @@ pub struct Database {
 impl Database {
     pub fn new<P: AsRef<Path>>(path: P) -> DbResult<Self> {
         let mut conn = Connection::open(path)?;
+        // the node and the contract watcher open the same database
+        conn.busy_timeout(Duration::from_secs(5))?;
 
         // public_key is base64 encoded
         // address is in hex
@@ -53,7 +82,37 @@ impl Database {
             public_key CHARACTER(96) PRIMARY KEY,
             id CHARACTER(32) NOT NULL, 
             owner_address CHARACTER(40) NOT NULL,
-            active INTEGER DEFAULT 1 NOT NULL
+            active INTEGER DEFAULT 1 NOT NULL,
+            paid_block INTEGER DEFAULT 0 NOT NULL
+        )";
+
+        // periods in which a validator was run by this operator, open periods have no end
+        let create_validator_service_sql = "CREATE TABLE IF NOT EXISTS validator_service(
+            id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+            validator_pk CHARACTER(96) NOT NULL,
+            owner_address CHARACTER(40) NOT NULL,
+            start_block INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            end_block INTEGER,
+            end_time INTEGER
+        )";
+
+        // contract commands waiting to be processed, command is serialized in json
+        let create_contract_commands_sql = "CREATE TABLE IF NOT EXISTS contract_commands(
+            id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+            subject TEXT NOT NULL,
+            command TEXT NOT NULL,
+            state INTEGER DEFAULT 0 NOT NULL,
+            attempts INTEGER DEFAULT 0 NOT NULL,
+            next_attempt INTEGER NOT NULL,
+            last_error TEXT
+        )";
+
+        // committee changes that were reorganized out of the chain before their reshare was in use
+        let create_cancelled_reshares_sql = "CREATE TABLE IF NOT EXISTS cancelled_reshares(
+            validator_pk CHARACTER(96) NOT NULL,
+            epoch INTEGER NOT NULL,
+            PRIMARY KEY(validator_pk, epoch)
         )";
 
         let create_releation_sql = "CREATE TABLE IF NOT EXISTS validator_operators_mapping(
@@ -84,6 +143,11 @@ impl Database {
         conn.execute(create_releation_sql, [],)?;
         conn.execute(create_initializer_sql,[])?;
         conn.execute(create_initializer_releation_sql, [])?;
+        conn.execute(create_validator_service_sql, [])?;
+        conn.execute(create_contract_commands_sql, [])?;
+        conn.execute(create_cancelled_reshares_sql, [])?;
+        // databases created before paid blocks were recorded, fails if the column already exists
+        let _ = conn.execute("ALTER TABLE validators ADD COLUMN paid_block INTEGER DEFAULT 0 NOT NULL", []);
         let (tx, mut rx) = channel(1000);
 
         tokio::spawn(async move {
@@ -109,6 +173,10 @@ impl Database {
                         let response = query_validator_by_public_key(&conn, &validator_pk);
                         let _ = sender.send(response);
                     },
+                    DbCommand::QueryValidatorsByOperator(operator_id, sender) => {
+                        let response = query_validators_by_operator(&conn, operator_id);
+                        let _ = sender.send(response);
+                    },
                     DbCommand::QueryOperatorPublicKeyByIds(operator_ids, sender) => {
                         let response = query_operators_public_key_by_ids(&conn, operator_ids);
                         let _ = sender.send(response);
@@ -149,6 +217,52 @@ impl Database {
                     DbCommand::ValidatorActive(public_key, sender) => {
                         let response = if_validator_active(&conn, public_key);
                         let _ = sender.send(response);
+                    },
+                    DbCommand::UpdatePaidBlock(owner, paid_block) => {
+                        update_paid_block(&conn, owner, paid_block);
+                    },
+                    DbCommand::StartValidatorService(public_key, owner, block, time) => {
+                        start_validator_service(&conn, public_key, owner, block, time);
+                    },
+                    DbCommand::EndValidatorService(public_key, block, time) => {
+                        end_validator_service(&conn, public_key, block, time);
+                    },
+                    DbCommand::QueryServedEpochs(now, seconds_per_epoch, sender) => {
+                        let response = query_served_epochs(&conn, now, seconds_per_epoch);
+                        let _ = sender.send(response);
+                    },
+                    DbCommand::EnqueueCommand(subject, command, now, sender) => {
+                        let response = enqueue_command(&conn, subject, command, now);
+                        let _ = sender.send(response);
+                    },
+                    DbCommand::QueryDueCommands(now, sender) => {
+                        let response = query_due_commands(&conn, now);
+                        let _ = sender.send(response);
+                    },
+                    DbCommand::CompleteCommand(id) => {
+                        complete_command(&conn, id);
+                    },
+                    DbCommand::RetryCommand(id, next_attempt, error) => {
+                        retry_command(&conn, id, next_attempt, error);
+                    },
+                    DbCommand::DeadLetterCommand(id, error) => {
+                        dead_letter_command(&conn, id, error);
+                    },
+                    DbCommand::QueryDeadCommands(sender) => {
+                        let response = query_dead_commands(&conn);
+                        let _ = sender.send(response);
+                    }
+                    DbCommand::SetReshareCancelled(validator_pk, epoch, cancelled) => {
+                        set_reshare_cancelled(&conn, validator_pk, epoch, cancelled);
+                    }
+                    DbCommand::QueryReshareCancelled(validator_pk, epoch, sender) => {
+                        let response = query_reshare_cancelled(&conn, validator_pk, epoch);
+                        let _ = sender.send(response);
+                    }
+                    DbCommand::Close(sender) => {
+                        drop(conn);
+                        let _ = sender.send(());
+                        break;
                     }
                 }
             }
@@ -156,6 +270,14 @@ impl Database {
         Ok(Self { channel: tx})
     }
 
+    // close the connection, the database can't be used afterwards
+    pub async fn close(&self) {
+        let (sender, receiver) = oneshot::channel();
+        if self.channel.send(DbCommand::Close(sender)).await.is_ok() {
+            let _ = receiver.await;
+        }
+    }
+
     pub async fn insert_operator(&self, operator: Operator) {
         if let Err(e) = self.channel.send(DbCommand::InsertOperator(operator)).await {
             panic!("Failed to send command to store: {}", e);
@@ -184,6 +306,14 @@ impl Database {
         receiver.await.expect("Failed to receive reply to query validator command from db")
     }
 
+    pub async fn query_validators_by_operator(&self, operator_id: u32) -> DbResult<Vec<Validator>> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(DbCommand::QueryValidatorsByOperator(operator_id, sender)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+        receiver.await.expect("Failed to receive reply to query validator command from db")
+    }
+
     pub async fn query_validator_by_address(&self, address: Address) -> DbResult<Vec<Validator>> {
         let (sender, receiver) = oneshot::channel();
         if let Err(e) = self.channel.send(DbCommand::QueryValidatorByAddress(address, sender)).await {
@@ -277,11 +407,99 @@ impl Database {
             panic!("Failed to send query validator owners command to store: {}", e);
         }
     }
+
+    pub async fn update_paid_block(&self, owner: Address, paid_block: u64) {
+        if let Err(e) = self.channel.send(DbCommand::UpdatePaidBlock(owner, paid_block)).await {
+            panic!("Failed to send update paid block command to store: {}", e);
+        }
+    }
+
+    pub async fn start_validator_service(&self, public_key: String, owner: Address, block: u64, time: u64) {
+        if let Err(e) = self.channel.send(DbCommand::StartValidatorService(public_key, owner, block, time)).await {
+            panic!("Failed to send start validator service command to store: {}", e);
+        }
+    }
+
+    pub async fn end_validator_service(&self, public_key: String, block: u64, time: u64) {
+        if let Err(e) = self.channel.send(DbCommand::EndValidatorService(public_key, block, time)).await {
+            panic!("Failed to send end validator service command to store: {}", e);
+        }
+    }
+
+    // validator-epochs served by this operator for each owner
+    pub async fn query_served_epochs(&self, now: u64, seconds_per_epoch: u64) -> DbResult<Vec<(Address, u64)>> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(DbCommand::QueryServedEpochs(now, seconds_per_epoch, sender)).await {
+            panic!("Failed to send query served epochs command to store: {}", e);
+        }
+        receiver.await.expect("Failed to receive reply to query served epochs command from db")
+    }
+
+    pub async fn enqueue_command(&self, command: &ContractCommand, now: u64) -> DbResult<i64> {
+        let serialized = serde_json::to_string(command).map_err(|e| DbError::ToSqlConversionFailure(Box::new(e)))?;
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(DbCommand::EnqueueCommand(command.subject(), serialized, now, sender)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+        receiver.await.expect("Failed to receive reply to EnqueueCommand command from db")
+    }
+
+    // pending commands whose backoff is over, skipping those queued behind an older command of the same subject
+    pub async fn query_due_commands(&self, now: u64) -> DbResult<Vec<QueuedCommand>> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(DbCommand::QueryDueCommands(now, sender)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+        receiver.await.expect("Failed to receive reply to QueryDueCommands command from db")
+    }
+
+    pub async fn complete_command(&self, id: i64) {
+        if let Err(e) = self.channel.send(DbCommand::CompleteCommand(id)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+    }
+
+    pub async fn retry_command(&self, id: i64, next_attempt: u64, error: String) {
+        if let Err(e) = self.channel.send(DbCommand::RetryCommand(id, next_attempt, error)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+    }
+
+    pub async fn dead_letter_command(&self, id: i64, error: String) {
+        if let Err(e) = self.channel.send(DbCommand::DeadLetterCommand(id, error)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+    }
+
+    pub async fn query_dead_commands(&self) -> DbResult<Vec<QueuedCommand>> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(DbCommand::QueryDeadCommands(sender)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+        receiver.await.expect("Failed to receive reply to QueryDeadCommands command from db")
+    }
+
+    // a pending reshare of the validator to the committee used from `epoch` is dropped when cancelled
+    pub async fn set_reshare_cancelled(&self, validator_pk: String, epoch: u64, cancelled: bool) {
+        if let Err(e) = self.channel.send(DbCommand::SetReshareCancelled(validator_pk, epoch, cancelled)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+    }
+
+    pub async fn is_reshare_cancelled(&self, validator_pk: String, epoch: u64) -> DbResult<bool> {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(e) = self.channel.send(DbCommand::QueryReshareCancelled(validator_pk, epoch, sender)).await {
+            panic!("Failed to send command to store: {}", e);
+        }
+        receiver.await.expect("Failed to receive reply to QueryReshareCancelled command from db")
+    }
  
 }
 
 fn insert_operator(conn: &Connection, operator: Operator) {
-    if let Err(e) = conn.execute("INSERT INTO operators(id, name, address, public_key) values (?1, ?2, ?3, ?4)", params![&operator.id, &operator.name, format!("{0:0x}", operator.address), base64::encode(&operator.public_key)],) {
+    // operators are re-registered when their name or key changes, the latest registration wins
+    if let Err(e) = conn.execute("INSERT INTO operators(id, name, address, public_key) values (?1, ?2, ?3, ?4)
+        ON CONFLICT(id) DO UPDATE SET name = excluded.name, address = excluded.address, public_key = excluded.public_key", params![&operator.id, &operator.name, format!("{0:0x}", operator.address), base64::encode(&operator.public_key)],) {
         error!("Can't insert into operators, error: {} {:?}", e, operator);
     }
 }
@@ -319,7 +537,7 @@ fn insert_validator(conn: &mut Connection, validator: Validator) {
     match conn.transaction() {
         Ok(mut tx) => {
             tx.set_drop_behavior(DropBehavior::Commit);
-            if let Err(e) = &tx.execute("INSERT INTO validators(public_key, id, owner_address) values(?1, ?2, ?3)", params![hex::encode(&validator.public_key), validator.id.to_string(), format!("{0:0x}", validator.owner_address)] ) {
+            if let Err(e) = &tx.execute("INSERT INTO validators(public_key, id, owner_address, paid_block) values(?1, ?2, ?3, ?4)", params![hex::encode(&validator.public_key), validator.id.to_string(), format!("{0:0x}", validator.owner_address), validator.paid_block as i64] ) {
                 error!("Can't insert into validators, error: {} {:?}", e, validator);
                 let _ = &tx.set_drop_behavior(DropBehavior::Rollback);
             }
@@ -342,7 +560,7 @@ fn insert_validator(conn: &mut Connection, validator: Validator) {
 }
 
 fn delete_operator(conn: &Connection, operator_id: u32) {
-    if let Err(e) = conn.execute("DELETE FROM operators WHERE operator_id = ?1", params![operator_id]) {
+    if let Err(e) = conn.execute("DELETE FROM operators WHERE id = ?1", params![operator_id]) {
         error!("Can't delete from operators {} operator_id {}", e, operator_id);
     }
 }
@@ -351,10 +569,14 @@ fn delete_validator(conn: &Connection, validator_pk: &str) {
     if let Err(e) = conn.execute("DELETE FROM validators WHERE public_key = ?1", params![validator_pk]) {
         error!("Can't delete from validators {} validator_pk {}", e, validator_pk);
     }
+    // foreign keys are not enforced, so the mapping isn't deleted by the cascade
+    if let Err(e) = conn.execute("DELETE FROM validator_operators_mapping WHERE validator_pk = ?1", params![validator_pk]) {
+        error!("Can't delete from validator_operators_mapping {} validator_pk {}", e, validator_pk);
+    }
 }
 
 fn query_operator_by_id(conn: &Connection, operator_id: &u32) -> DbResult<Option<Operator>> {
-    match conn.prepare("SELECT id, name, address, public_key FROM WHERE id = (?)") {
+    match conn.prepare("SELECT id, name, address, public_key FROM operators WHERE id = (?)") {
         Ok(mut stmt) => {
             let mut rows = stmt.query([operator_id])?;
             match rows.next()? {
@@ -382,7 +604,7 @@ fn query_operator_by_id(conn: &Connection, operator_id: &u32) -> DbResult<Option
 fn query_validator_by_public_key(conn: &Connection, validator_pk: &str) -> DbResult<Option<Validator>> {
     // select releated operators
     let mut releated_operators: Vec<u32> = Vec::new();
-    match conn.prepare("SELECT operator_id from validator_operators_mapping where public_key = (?)") {
+    match conn.prepare("SELECT operator_id from validator_operators_mapping where validator_pk = (?)") {
         Ok(mut stmt) => {
             let mut rows = stmt.query([validator_pk])?;
             while let Some(row) = rows.next()? {
@@ -395,7 +617,7 @@ fn query_validator_by_public_key(conn: &Connection, validator_pk: &str) -> DbRes
         }
     };
 
-    match conn.prepare("SELECT public_key, id, owner_address, active FROM validators where public_key = (?)") {
+    match conn.prepare("SELECT public_key, id, owner_address, active, paid_block FROM validators where public_key = (?)") {
         Ok(mut stmt) => {
             let mut rows = stmt.query([validator_pk])?;
             match rows.next()? {
@@ -408,7 +630,8 @@ fn query_validator_by_public_key(conn: &Connection, validator_pk: &str) -> DbRes
                         id: id.parse().unwrap(),
                         owner_address: Address::from_slice(&hex::decode(owner_address).unwrap()),
                         releated_operators: releated_operators,
-                        active: row.get(3)?
+                        active: row.get(3)?,
+                        paid_block: row.get::<_, i64>(4)? as u64
                     }))
                 },
                 None => { Ok(None) }
@@ -421,6 +644,30 @@ fn query_validator_by_public_key(conn: &Connection, validator_pk: &str) -> DbRes
     }
 }
 
+// the mapping is kept when an operator is deleted, so committees of removed operators can still be found
+fn query_validators_by_operator(conn: &Connection, operator_id: u32) -> DbResult<Vec<Validator>> {
+    let mut validator_pks: Vec<String> = Vec::new();
+    match conn.prepare("SELECT validator_pk from validator_operators_mapping where operator_id = (?)") {
+        Ok(mut stmt) => {
+            let mut rows = stmt.query([operator_id])?;
+            while let Some(row) = rows.next()? {
+                validator_pks.push(row.get(0)?);
+            }
+        },
+        Err(e) => {
+            error!("Can't prepare statement {}", e);
+            return Err(e);
+        }
+    };
+    let mut validators = Vec::new();
+    for validator_pk in validator_pks {
+        if let Some(validator) = query_validator_by_public_key(conn, &validator_pk)? {
+            validators.push(validator);
+        }
+    }
+    Ok(validators)
+}
+
 fn query_operators_public_key_by_ids(conn: &Connection, operator_ids: Vec<u32>) -> DbResult<Option<Vec<String>>> {
     let mut public_keys: Vec<String> = Vec::new();
     assert_ne!(operator_ids.len(), 0);
@@ -472,14 +719,15 @@ fn query_initializer(conn: &Connection, id: u32) -> DbResult<Option<Initializer>
             match rows.next()? {
                 Some(row) => { 
                     let address: String = row.get(1)?;
-                    let validator_pk: String = row.get(2)?;
-                    let minipool_address: String = row.get(3)?;
-                    let va_pk_option = if validator_pk.len() == 0 { None } else {
-                        Some(hex::decode(&validator_pk).unwrap().try_into().unwrap())
-                    };
-                    let minipool_address_option = if minipool_address.len() == 0 { None } else {
-                        Some(Address::from_slice(&hex::decode(minipool_address).unwrap()))
-                    };
+                    // both are NULL until the minipool is created
+                    let validator_pk: Option<String> = row.get(2)?;
+                    let minipool_address: Option<String> = row.get(3)?;
+                    let va_pk_option = validator_pk.filter(|pk| pk.len() != 0).map(|pk| {
+                        hex::decode(&pk).unwrap().try_into().unwrap()
+                    });
+                    let minipool_address_option = minipool_address.filter(|address| address.len() != 0).map(|address| {
+                        Address::from_slice(&hex::decode(address).unwrap())
+                    });
 
                     Ok(Some(Initializer {
                         id: row.get(0)?,
@@ -536,7 +784,7 @@ fn query_all_validator_address(conn: &Connection) -> DbResult<Vec<Address>>{
 fn query_validator_by_address(conn: &Connection, address: Address) -> DbResult<Vec<Validator>> {
     let mut validators = Vec::new();
     let address_str = format!("{0:0x}", address);
-    match conn.prepare("select public_key, id, owner_address, active from validators where owner_address = (?)") {
+    match conn.prepare("select public_key, id, owner_address, active, paid_block from validators where owner_address = (?)") {
         Ok(mut stmt) => {
             let mut rows = stmt.query([address_str])?;
             
@@ -548,7 +796,8 @@ fn query_validator_by_address(conn: &Connection, address: Address) -> DbResult<V
                     id: id.parse().unwrap(),
                     owner_address: address, 
                     releated_operators: vec![],
-                    active: row.get(3)?
+                    active: row.get(3)?,
+                    paid_block: row.get::<_, i64>(4)? as u64
                 });
             }
         },
@@ -571,6 +820,152 @@ fn enable_validator(conn: &Connection, public_key: String) {
     }
 }
 
+fn update_paid_block(conn: &Connection, owner: Address, paid_block: u64) {
+    if let Err(e) = conn.execute("UPDATE validators SET paid_block = ?1 WHERE owner_address = ?2", params![paid_block as i64, format!("{0:0x}", owner)]) {
+        error!("Can't update paid block of {}, error {}", owner, e);
+    }
+}
+
+// no-op if the validator is already in service
+fn start_validator_service(conn: &Connection, public_key: String, owner: Address, block: u64, time: u64) {
+    if let Err(e) = conn.execute("INSERT INTO validator_service(validator_pk, owner_address, start_block, start_time)
+        SELECT ?1, ?2, ?3, ?4 WHERE NOT EXISTS (SELECT 1 FROM validator_service WHERE validator_pk = ?1 AND end_block IS NULL)",
+        params![public_key, format!("{0:0x}", owner), block as i64, time as i64]) {
+        error!("Can't start service of validator {}, error {}", public_key, e);
+    }
+}
+
+fn end_validator_service(conn: &Connection, public_key: String, block: u64, time: u64) {
+    if let Err(e) = conn.execute("UPDATE validator_service SET end_block = ?2, end_time = ?3 WHERE validator_pk = ?1 AND end_block IS NULL",
+        params![public_key, block as i64, time as i64]) {
+        error!("Can't end service of validator {}, error {}", public_key, e);
+    }
+}
+
+// Service accounting, queued commands and cancelled reshares can't be rebuilt from the chain. Carry them over from
+// the database at `from` to the one at `to`, and end the service periods of validators that are
+// no longer active there.
+pub fn carry_over_local_tables<P: AsRef<Path>>(from: P, to: P, block: u64, time: u64) -> DbResult<()> {
+    let mut conn = Connection::open(to)?;
+    conn.execute("ATTACH DATABASE ?1 AS previous", params![from.as_ref().to_string_lossy()])?;
+    let tx = conn.transaction()?;
+    for table in ["validator_service", "contract_commands", "cancelled_reshares"] {
+        // databases of older versions may not have the table yet
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM previous.sqlite_master WHERE type = 'table' AND name = ?1)",
+            params![table],
+            |row| row.get(0),
+        )?;
+        if exists {
+            tx.execute(&format!("DELETE FROM {}", table), [])?;
+            tx.execute(&format!("INSERT INTO {0} SELECT * FROM previous.{0}", table), [])?;
+        }
+    }
+    tx.execute("UPDATE validator_service SET end_block = ?1, end_time = ?2 WHERE end_block IS NULL
+        AND validator_pk NOT IN (SELECT public_key FROM validators WHERE active = 1)", params![block as i64, time as i64])?;
+    tx.commit()?;
+    conn.execute("DETACH DATABASE previous", [])?;
+    Ok(())
+}
+
+// only whole epochs of each service period are counted
+fn query_served_epochs(conn: &Connection, now: u64, seconds_per_epoch: u64) -> DbResult<Vec<(Address, u64)>> {
+    let mut epochs = Vec::new();
+    match conn.prepare("select owner_address, SUM(MAX(COALESCE(end_time, ?1) - start_time, 0) / ?2) from validator_service group by owner_address") {
+        Ok(mut stmt) => {
+            let mut rows = stmt.query(params![now as i64, seconds_per_epoch as i64])?;
+            while let Some(row) = rows.next()? {
+                let address: String = row.get(0)?;
+                let served: i64 = row.get(1)?;
+                epochs.push((Address::from_slice(&hex::decode(&address).unwrap()), served as u64));
+            }
+        },
+        Err(e) => { error!("Can't prepare statement {}", e); return Err(e); }
+    }
+    Ok(epochs)
+}
+
+fn enqueue_command(conn: &Connection, subject: String, command: String, now: u64) -> DbResult<i64> {
+    conn.execute("INSERT INTO contract_commands(subject, command, state, next_attempt) values(?1, ?2, ?3, ?4)",
+        params![subject, command, COMMAND_PENDING, now as i64])?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn query_due_commands(conn: &Connection, now: u64) -> DbResult<Vec<QueuedCommand>> {
+    query_commands(conn, "select id, command, attempts, last_error from contract_commands c
+        where state = ?1 and next_attempt <= ?2
+        and not exists (select 1 from contract_commands e where e.subject = c.subject and e.state = ?1 and e.id < c.id)
+        order by id", params![COMMAND_PENDING, now as i64])
+}
+
+fn query_dead_commands(conn: &Connection) -> DbResult<Vec<QueuedCommand>> {
+    query_commands(conn, "select id, command, attempts, last_error from contract_commands where state = ?1 order by id",
+        params![COMMAND_DEAD])
+}
+
+fn query_commands(conn: &Connection, sql: &str, params: &[&dyn rusqlite::ToSql]) -> DbResult<Vec<QueuedCommand>> {
+    let mut commands = Vec::new();
+    let mut undecodable = Vec::new();
+    match conn.prepare(sql) {
+        Ok(mut stmt) => {
+            let mut rows = stmt.query(params)?;
+            while let Some(row) = rows.next()? {
+                let id: i64 = row.get(0)?;
+                let command: String = row.get(1)?;
+                let attempts: i64 = row.get(2)?;
+                let last_error: Option<String> = row.get(3)?;
+                match serde_json::from_str(&command) {
+                    Ok(command) => commands.push(QueuedCommand { id, command, attempts: attempts as u32, last_error }),
+                    Err(e) => undecodable.push((id, format!("can't decode command: {}", e))),
+                }
+            }
+        },
+        Err(e) => { error!("Can't prepare statement {}", e); return Err(e); }
+    }
+    // commands written by an incompatible version can never be processed
+    for (id, e) in undecodable {
+        error!("contract command {} {}", id, e);
+        dead_letter_command(conn, id, e);
+    }
+    Ok(commands)
+}
+
+fn complete_command(conn: &Connection, id: i64) {
+    if let Err(e) = conn.execute("DELETE FROM contract_commands WHERE id = ?1", params![id]) {
+        error!("Can't delete contract command {}, error {}", id, e);
+    }
+}
+
+fn retry_command(conn: &Connection, id: i64, next_attempt: u64, error: String) {
+    if let Err(e) = conn.execute("UPDATE contract_commands SET attempts = attempts + 1, next_attempt = ?2, last_error = ?3 WHERE id = ?1",
+        params![id, next_attempt as i64, error]) {
+        error!("Can't reschedule contract command {}, error {}", id, e);
+    }
+}
+
+fn dead_letter_command(conn: &Connection, id: i64, error: String) {
+    if let Err(e) = conn.execute("UPDATE contract_commands SET state = ?2, attempts = attempts + 1, last_error = ?3 WHERE id = ?1",
+        params![id, COMMAND_DEAD, error]) {
+        error!("Can't move contract command {} to dead letters, error {}", id, e);
+    }
+}
+
+fn set_reshare_cancelled(conn: &Connection, validator_pk: String, epoch: u64, cancelled: bool) {
+    let result = if cancelled {
+        conn.execute("INSERT OR IGNORE INTO cancelled_reshares(validator_pk, epoch) values(?1, ?2)", params![validator_pk, epoch as i64])
+    } else {
+        conn.execute("DELETE FROM cancelled_reshares WHERE validator_pk = ?1 AND epoch = ?2", params![validator_pk, epoch as i64])
+    };
+    if let Err(e) = result {
+        error!("Can't update cancelled reshare of {} at epoch {}, error {}", validator_pk, epoch, e);
+    }
+}
+
+fn query_reshare_cancelled(conn: &Connection, validator_pk: String, epoch: u64) -> DbResult<bool> {
+    conn.query_row("SELECT EXISTS (SELECT 1 FROM cancelled_reshares WHERE validator_pk = ?1 AND epoch = ?2)",
+        params![validator_pk, epoch as i64], |row| row.get(0))
+}
+
 fn if_validator_active(conn: &Connection, public_key: String) -> DbResult<bool> {
     match conn.prepare("select active from validators where public_key = (?)") {
         Ok(mut stmt) => {
@@ -615,4 +1010,42 @@ fn test_database() {
         }
     };
 
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_command_queue() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = Database::new(dir.path().join("test.db")).unwrap();
+    let validator = Validator {
+        id: 1,
+        owner_address: Address::random(),
+        public_key: [1u8; 48],
+        releated_operators: vec![1, 2, 3, 4],
+        active: true,
+        paid_block: 100,
+    };
+    let other = Validator { public_key: [2u8; 48], ..validator.clone() };
+    let start = db.enqueue_command(&ContractCommand::StopValidator(validator.clone()), 10).await.unwrap();
+    let then = db.enqueue_command(&ContractCommand::RemoveValidator(validator.clone()), 10).await.unwrap();
+    let unrelated = db.enqueue_command(&ContractCommand::StopValidator(other), 10).await.unwrap();
+
+    // a command waits for older commands of the same validator
+    let due: Vec<i64> = db.query_due_commands(10).await.unwrap().iter().map(|c| c.id).collect();
+    assert_eq!(due, vec![start, unrelated]);
+    db.complete_command(unrelated).await;
+    db.retry_command(start, 20, "no peer".to_string()).await;
+    assert!(db.query_due_commands(15).await.unwrap().is_empty());
+    let due = db.query_due_commands(20).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].attempts, 1);
+    assert_eq!(due[0].last_error.as_deref(), Some("no peer"));
+    match &due[0].command {
+        ContractCommand::StopValidator(va) => assert_eq!(va.public_key, validator.public_key),
+        _ => panic!("expect StopValidator"),
+    }
+
+    db.dead_letter_command(start, "no peer".to_string()).await;
+    let due: Vec<i64> = db.query_due_commands(20).await.unwrap().iter().map(|c| c.id).collect();
+    assert_eq!(due, vec![then]);
+    let dead: Vec<i64> = db.query_dead_commands().await.unwrap().iter().map(|c| c.id).collect();
+    assert_eq!(dead, vec![start]);
+}
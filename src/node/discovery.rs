@@ -1,4 +1,4 @@
-use super::config::BOOT_SOCKETADDR;
+use super::config::{port_mode, BOOT_SOCKETADDR};
 use discv5::enr::EnrPublicKey;
 use discv5::{
     enr::{CombinedKey, Enr},
@@ -17,8 +17,9 @@ pub struct Discovery {}
 impl Discovery {
     pub fn spawn(
         ip_address: IpAddr,
+        base_port: u16,
         udp_port: u16,
-        key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>>,
+        key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
         secret: Secret,
         boot_enr: String,
     ) {
@@ -31,6 +32,10 @@ impl Discovery {
             builder.ip(ip_address);
             // don't need to set udp port
             builder.udp4(udp_port);
+            // operators that don't advertise a port are assumed to use the same base port as us
+            if port_mode().advertise_base_port() {
+                builder.tcp4(base_port);
+            }
             builder.build(&enr_key).unwrap()
         };
         info!(
@@ -90,8 +95,8 @@ impl Discovery {
                         for enr in v {
                           if let Some(ip) = enr.ip4() {
                             let public_key = base64::encode(&enr.public_key().encode()[..]);
-                            // update public key ip
-                            m.insert(public_key, IpAddr::V4(ip));
+                            // update public key address
+                            m.insert(public_key, SocketAddr::new(IpAddr::V4(ip), enr.tcp4().unwrap_or(base_port)));
                           };
                         };
                       }
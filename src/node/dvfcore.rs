@@ -3,13 +3,14 @@ use hsutils::monitored_channel::{MonitoredChannel, MonitoredSender};
 use consensus::Committee as ConsensusCommittee;
 use mempool::Committee as MempoolCommittee;
 use consensus::{Block, Consensus};
-use hscrypto::SignatureService;
+use hscrypto::{PublicKey as HotstuffPublicKey, SignatureService};
 use log::{info, error, warn};
 use mempool::{Mempool, MempoolMessage};
 use store::Store;
-use tokio::sync::mpsc::{Receiver, Sender};
-use network::{MessageHandler, Writer};
+use tokio::sync::mpsc::Receiver;
+use network::{MessageHandler, PeerKey, Writer};
 use std::sync::{Arc};
+use std::collections::HashSet;
 use async_trait::async_trait;
 use bytes::Bytes;
 use std::error::Error;
@@ -18,14 +19,14 @@ use std::fmt;
 use bls::{Hash256, Signature};
 use types::{Keypair, EthSpec};
 use crate::validation::operator::{LocalOperator, TOperator};
+use crate::validation::partial_signatures::{PartialSignature, PartialSignatures};
 use futures::SinkExt;
 use crate::node::node::Node;
 use crate::utils::error::DvfError;
 use crate::validation::{OperatorCommittee};
 use tokio::sync::{RwLock};
 use crate::validation::operator_committee_definitions::OperatorCommitteeDefinition; 
-use crate::node::config::{TRANSACTION_PORT_OFFSET, MEMPOOL_PORT_OFFSET, CONSENSUS_PORT_OFFSET, SIGNATURE_PORT_OFFSET};
-use std::net::SocketAddr;
+use crate::node::config::{channel_address, TRANSACTION_PORT_OFFSET, MEMPOOL_PORT_OFFSET, CONSENSUS_PORT_OFFSET, SIGNATURE_PORT_OFFSET};
 use crate::DEFAULT_CHANNEL_CAPACITY;
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -45,22 +46,6 @@ impl fmt::Debug for DvfInfo {
   }
 }
 
-#[derive(Clone)]
-pub struct DvfReceiverHandler {
-  pub tx_dvfinfo : Sender<DvfInfo>
-}
-
-#[async_trait]
-impl MessageHandler for DvfReceiverHandler {
-    async fn dispatch(&self, _writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>> {
-        let dvfinfo = serde_json::from_slice(&message.to_vec())?;
-        self.tx_dvfinfo.send(dvfinfo).await.unwrap();
-        // Give the change to schedule other tasks.
-        tokio::task::yield_now().await;
-        Ok(())
-    }
-}
-
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BlsSignature {
   pub pk : bls::PublicKey,
@@ -91,12 +76,31 @@ impl fmt::Debug for SignatureInfo {
 
 #[derive(Clone)]
 pub struct DvfSignatureReceiverHandler {
-  pub store : Store
+  pub store : Store,
+  // node keys of the operators in the validator's committee
+  pub members : Arc<HashSet<HotstuffPublicKey>>,
+  // shares pushed to us when we aggregate
+  pub partial_signatures : Arc<PartialSignatures>,
 }
 
 #[async_trait]
 impl MessageHandler for DvfSignatureReceiverHandler {
     async fn dispatch(&self, writer: &mut Writer, message: Bytes) -> Result<(), Box<dyn Error>> {
+      // a signing root asks for our share, anything else is a share pushed to us
+      if message.len() != Hash256::len_bytes() {
+        let share: PartialSignature = bincode::deserialize(&message)?;
+        let operator_id = share.operator_id;
+        match self.partial_signatures.insert(share) {
+          Ok(()) => {
+            let _ = writer.send(Bytes::from("Ack")).await;
+          }
+          Err(e) => {
+            let _ = writer.send(Bytes::from("Invalid signature")).await;
+            warn!("Invalid signature pushed by operator {}: {:?}", operator_id, e);
+          }
+        }
+        return Ok(());
+      }
       let msg: Vec<u8> = message.slice(..).to_vec();  
       match self.store.read(msg).await {
         Ok(value) => {
@@ -118,12 +122,17 @@ impl MessageHandler for DvfSignatureReceiverHandler {
     //   tokio::task::yield_now().await;
       Ok(())
     }
+
+    fn accepts(&self, peer: &PeerKey) -> bool {
+      self.members.contains(&HotstuffPublicKey(*peer))
+    }
 }
 
 
 pub struct DvfSigner {
     pub signal: Option<exit_future::Signal>,
     pub operator_id: u64,
+    pub operator_ids: Vec<u64>,
     pub operator_committee: OperatorCommittee,
     pub local_keypair: Keypair,
     pub store: Store,
@@ -171,9 +180,9 @@ impl DvfSigner {
                     let addr = committee_def.base_socket_addresses[i]; 
                     (pk.clone(), 
                      stake, 
-                     SocketAddr::new(addr.ip(), addr.port() + TRANSACTION_PORT_OFFSET), 
-                     SocketAddr::new(addr.ip(), addr.port() + MEMPOOL_PORT_OFFSET), 
-                     SocketAddr::new(addr.ip(), addr.port() + SIGNATURE_PORT_OFFSET), 
+                     channel_address(addr, TRANSACTION_PORT_OFFSET), 
+                     channel_address(addr, MEMPOOL_PORT_OFFSET), 
+                     channel_address(addr, SIGNATURE_PORT_OFFSET), 
                     )
                 })
                 .collect(),
@@ -187,7 +196,7 @@ impl DvfSigner {
                     let addr = committee_def.base_socket_addresses[i]; 
                     (pk.clone(), 
                      stake,
-                     SocketAddr::new(addr.ip(), addr.port() + CONSENSUS_PORT_OFFSET),
+                     channel_address(addr, CONSENSUS_PORT_OFFSET),
                     )
                 })
                 .collect(),
@@ -209,11 +218,13 @@ impl DvfSigner {
             keypair.clone(),
             tx_consensus,
             store.clone(),
+            operator_committee.partial_signatures(),
         ).await;
 
         Self {
             signal: Some(signal),
             operator_id,
+            operator_ids: committee_def.operator_ids.clone(),
             operator_committee,
             local_keypair: keypair,
             store,
@@ -228,17 +239,35 @@ impl DvfSigner {
         self.local_keypair.sk.sign(message)
     }
 
-    pub async fn local_sign_and_store(&self, message: Hash256) {
+    // sign locally, and hand the share to the `aggregators` of the duty right away
+    pub async fn local_sign_and_store(&self, message: Hash256, aggregators: &[u64]) {
         let sig = self.local_sign(message);
         let serialized_signature = bincode::serialize(&sig).unwrap();
-        // save to local db
+        // save to local db, for aggregators that ask for it
         let key = message.as_bytes().into();
         self.store.write(key, serialized_signature).await;
+
+        let share = PartialSignature { operator_id: self.operator_id, root: message, signature: sig };
+        if aggregators.contains(&self.operator_id) {
+            if let Err(e) = self.operator_committee.partial_signatures().insert(share.clone()) {
+                error!("[Dvf {}/{}] Invalid local signature: {:?}", self.operator_id, self.operator_committee.validator_id(), e);
+            }
+        }
+        let others: Vec<u64> = aggregators.iter().filter(|id| **id != self.operator_id).cloned().collect();
+        self.operator_committee.push(&share, &others).await;
     }
 
-    pub async fn is_aggregator(&self, nonce: u64) -> bool {
-        self.operator_committee.get_leader(nonce).await == self.operator_id
-        || self.operator_committee.get_leader(nonce + 1).await == self.operator_id
+    // the operators that aggregate a duty, either the leaders for `nonce` or all of them
+    pub async fn aggregators(&self, nonce: u64, only_aggregator: bool) -> Vec<u64> {
+        if !only_aggregator {
+            return self.operator_ids.clone();
+        }
+        let mut aggregators = vec![
+            self.operator_committee.get_leader(nonce).await,
+            self.operator_committee.get_leader(nonce + 1).await,
+        ];
+        aggregators.dedup();
+        aggregators
     }
 
     pub fn validator_public_key(&self) -> String {
@@ -286,6 +315,7 @@ impl DvfCore {
         keypair: Keypair,
         tx_consensus: MonitoredSender<Hash256>,
         store: Store,
+        partial_signatures: Arc<PartialSignatures>,
     ) -> exit_future::Signal {
         let node = node.read().await;
 
@@ -304,7 +334,11 @@ impl DvfCore {
         node.signature_handler_map
             .write()
             .await
-            .insert(validator_id, DvfSignatureReceiverHandler{store : store.clone()});
+            .insert(validator_id, DvfSignatureReceiverHandler{
+                store : store.clone(),
+                members : Arc::new(committee.consensus.authorities.keys().cloned().collect()),
+                partial_signatures,
+            });
         info!("Insert signature handler for validator: {}", validator_id);
 
         let (signal, exit) = exit_future::signal();
@@ -0,0 +1,148 @@
+use super::contract::ContractError;
+use log::{error, info, warn};
+use parking_lot::RwLock;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use web3::transports::{Either, Http, WebSocket};
+use web3::Web3;
+
+pub type EthTransport = Either<WebSocket, Http>;
+pub static EXECUTION_CLIENTS: OnceCell<ExecutionClients> = OnceCell::const_new();
+
+const MAX_SCORE: i64 = 10;
+const MIN_SCORE: i64 = -100;
+const FAILURE_PENALTY: i64 = 5;
+
+struct Endpoint {
+    url: String,
+    score: i64,
+    // cached connection, dropped when a request on it fails
+    web3: Option<Web3<EthTransport>>,
+}
+
+// execution layer rpc endpoints with health scoring. Requests go to the healthiest endpoint
+// and fail over to the next one, so a single bad provider can't stop the contract watcher.
+#[derive(Clone)]
+pub struct ExecutionClients {
+    endpoints: Arc<RwLock<Vec<Endpoint>>>,
+}
+
+pub fn is_websocket(url: &str) -> bool {
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+pub fn execution_clients() -> &'static ExecutionClients {
+    EXECUTION_CLIENTS
+        .get()
+        .expect("execution endpoints are not configured")
+}
+
+impl ExecutionClients {
+    pub fn new(urls: Vec<String>) -> Self {
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                url,
+                score: 0,
+                web3: None,
+            })
+            .collect();
+        Self {
+            endpoints: Arc::new(RwLock::new(endpoints)),
+        }
+    }
+
+    // endpoint indexes ordered by health, ties keep the configured order
+    fn ranked(&self, websocket_only: bool) -> Vec<usize> {
+        let endpoints = self.endpoints.read();
+        let mut indexes: Vec<usize> = (0..endpoints.len())
+            .filter(|i| !websocket_only || is_websocket(&endpoints[*i].url))
+            .collect();
+        indexes.sort_by_key(|i| std::cmp::Reverse(endpoints[*i].score));
+        indexes
+    }
+
+    async fn connect(&self, index: usize) -> Result<Web3<EthTransport>, String> {
+        let url = {
+            let endpoints = self.endpoints.read();
+            if let Some(web3) = &endpoints[index].web3 {
+                return Ok(web3.clone());
+            }
+            endpoints[index].url.clone()
+        };
+        let transport = if is_websocket(&url) {
+            Either::Left(WebSocket::new(&url).await.map_err(|e| e.to_string())?)
+        } else {
+            Either::Right(Http::new(&url).map_err(|e| e.to_string())?)
+        };
+        let web3 = Web3::new(transport);
+        self.endpoints.write()[index].web3 = Some(web3.clone());
+        Ok(web3)
+    }
+
+    pub fn report(&self, index: usize, success: bool) {
+        let mut endpoints = self.endpoints.write();
+        let endpoint = &mut endpoints[index];
+        if success {
+            endpoint.score = (endpoint.score + 1).min(MAX_SCORE);
+        } else {
+            endpoint.score = (endpoint.score - FAILURE_PENALTY).max(MIN_SCORE);
+            endpoint.web3 = None;
+            warn!(
+                "execution endpoint {} failed, health score {}",
+                endpoint.url, endpoint.score
+            );
+        }
+    }
+
+    // run the request against the healthiest endpoints until one of them succeeds
+    pub async fn request<F, Fut, R, E>(&self, f: F) -> Result<R, ContractError>
+    where
+        F: Fn(Web3<EthTransport>) -> Fut,
+        Fut: Future<Output = Result<R, E>>,
+        E: Display,
+    {
+        for index in self.ranked(false) {
+            let web3 = match self.connect(index).await {
+                Ok(web3) => web3,
+                Err(e) => {
+                    warn!("can't connect to execution endpoint {}", e);
+                    self.report(index, false);
+                    continue;
+                }
+            };
+            match f(web3).await {
+                Ok(response) => {
+                    self.report(index, true);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    warn!("request to execution endpoint failed {}", e);
+                    self.report(index, false);
+                }
+            }
+        }
+        error!("{}", ContractError::TransportError.as_str());
+        Err(ContractError::TransportError)
+    }
+
+    // a dedicated websocket connection for subscriptions, from the healthiest websocket endpoint
+    pub async fn websocket(&self) -> Option<(usize, Web3<WebSocket>)> {
+        for index in self.ranked(true) {
+            let url = self.endpoints.read()[index].url.clone();
+            match WebSocket::new(&url).await {
+                Ok(transport) => {
+                    info!("subscribe contract logs from {}", url);
+                    return Some((index, Web3::new(transport)));
+                }
+                Err(e) => {
+                    warn!("can't connect to websocket {}", e);
+                    self.report(index, false);
+                }
+            }
+        }
+        None
+    }
+}
@@ -3,5 +3,9 @@ pub mod dvfcore;
 pub mod config;
 pub mod discovery;
 pub mod contract;
+pub mod abi;
+pub mod execution;
 pub mod db;
-pub mod utils;
\ No newline at end of file
+pub mod utils;
+pub mod resync;
+pub mod refresh;
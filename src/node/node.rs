@@ -1,25 +1,30 @@
 use crate::crypto::dkg::{DKG, SimpleDistributedSigner};
-use crate::crypto::elgamal::{Ciphertext, Elgamal};
+use crate::crypto::elgamal::Elgamal;
 use crate::deposit::get_distributed_deposit;
-use crate::network::io_committee::{NetIOCommittee, NetIOChannel};
+use crate::network::io_committee::{
+    forward_connections, listen, session_id, NetIOCommittee, NetIOChannel, NetIOIdentity, CONNECTION_PREAMBLE,
+};
 use crate::node::config::{
-    NodeConfig, API_ADDRESS, BOOT_ENR, DB_FILENAME, DISCOVERY_PORT_OFFSET, DKG_PORT_OFFSET,
-    PRESTAKE_SIGNATURE_URL, STAKE_SIGNATURE_URL, VALIDATOR_PK_URL,
+    channel_address, port_mode, NodeConfig, API_ADDRESS, BOOT_ENR, DB_FILENAME, DISCOVERY_PORT_OFFSET, DKG_PORT_OFFSET,
+    INITIALIZER_DIR, PRESTAKE_SIGNATURE_URL, STAKE_SIGNATURE_URL, VALIDATOR_PK_URL,
 };
+use crate::node::db::Database;
+use crate::node::refresh::{refresh_shares, reshare_validator, spawn_share_refresh};
 use crate::node::discovery::Discovery;
 /// The default channel capacity for this module.
 use crate::node::dvfcore::DvfSignatureReceiverHandler;
 use crate::node::contract::{
-    Contract, ContractCommand, EncryptedSecretKeys, Initializer, OperatorPublicKeys,
+    Contract, ContractCommand, CONTRACT_DATABASE_FILE, EncryptedSecretKeys, Initializer, Operator, OperatorPublicKeys,
     SharedPublicKeys, Validator, SELF_OPERATOR_ID, OperatorIds
 };
-use crate::node::utils::{get_operator_ips, request_to_web_server, convert_address_to_withdraw_crendentials, ValidatorPkRequest, DepositRequest};
+use crate::node::utils::{get_operator_ips, request_to_web_server, committee_threshold, convert_address_to_withdraw_crendentials, convert_va_pk_to_u64, ValidatorPkRequest, DepositRequest};
 use crate::validation::account_utils::default_keystore_share_password_path;
 use crate::validation::account_utils::default_keystore_share_path;
 use crate::validation::account_utils::default_operator_committee_definition_path;
+use crate::validation::account_utils::read_password;
 use crate::validation::eth2_keystore_share::keystore_share::KeystoreShare;
-use crate::validation::operator_committee_definitions::OperatorCommitteeDefinition;
-use crate::validation::validator_dir::share_builder::{insecure_kdf, ShareBuilder};
+use crate::validation::operator_committee_definitions::{OperatorCommitteeDefinition, OPERATOR_COMMITTEE_DEFINITION_FILENAME};
+use crate::validation::validator_dir::share_builder::{insecure_kdf, ShareBuilder, VOTING_KEYSTORE_SHARE_FILE};
 use crate::validation::validator_store::ValidatorStore;
 use crate::DEFAULT_CHANNEL_CAPACITY;
 use bls::{Keypair as BlsKeypair, PublicKey as BlsPublicKey, SecretKey as BlsSecretKey};
@@ -27,24 +32,32 @@ use consensus::ConsensusReceiverHandler;
 use eth2_keystore::KeystoreBuilder;
 use hsconfig::Export as _;
 use hsconfig::{ConfigError, Secret};
-use hsutils::monitored_channel::{MonitoredChannel, MonitoredSender};
+use hsutils::monitored_channel::MonitoredChannel;
 use log::{error, info, warn};
 use mempool::{MempoolReceiverHandler, TxReceiverHandler};
-use network::{Receiver as NetworkReceiver};
+use network::{set_identity, Channel, Identity, Receiver as NetworkReceiver, Routes};
+use rand::Rng;
 use slot_clock::SystemTimeSlotClock;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{remove_dir_all, remove_file};
-use std::net::{IpAddr, SocketAddr};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::RwLock;
-use tokio::time::{sleep, Duration};
+use tokio::sync::Notify;
+use tokio::time::{timeout, Duration};
 use types::EthSpec;
 use types::PublicKey;
 use validator_dir::insecure_keys::INSECURE_PASSWORD;
 use web3::types::H160;
-const THRESHOLD: u64 = 3;
+use zeroize::Zeroize;
+// a failed contract command is retried with exponential backoff, then moved to the dead letter list
+const COMMAND_MAX_ATTEMPTS: u32 = 12;
+const COMMAND_MIN_BACKOFF: Duration = Duration::from_secs(10);
+const COMMAND_MAX_BACKOFF: Duration = Duration::from_secs(3600);
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 type InitializerStore =
     Arc<RwLock<HashMap<u32, (BlsKeypair, BlsPublicKey, HashMap<u64, BlsPublicKey>)>>>;
@@ -72,57 +85,74 @@ impl<T: EthSpec> Node<T> {
         let secret = Node::<T>::open_or_create_secret(config.node_key_path.clone())?;
 
         info!("node public key {}", secret.name.encode_base64());
+        // hotstuff and signature connections authenticate with the node key
+        let identity = Identity::new(&secret.secret.0).map_err(|e| ConfigError::ReadError {
+            file: config.node_key_path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        set_identity(identity);
 
         let tx_handler_map = Arc::new(RwLock::new(HashMap::new()));
         let mempool_handler_map = Arc::new(RwLock::new(HashMap::new()));
         let consensus_handler_map = Arc::new(RwLock::new(HashMap::new()));
         let signature_handler_map = Arc::new(RwLock::new(HashMap::new()));
 
-        let key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>> =
+        let key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>> =
             Arc::new(RwLock::new(HashMap::from([(
                 base64::encode(&secret.name),
-                config.base_address.ip().clone(),
+                config.base_address.clone(),
             )])));
 
-        let transaction_address = with_wildcard_ip(config.transaction_address.clone());
-        NetworkReceiver::spawn(
-            transaction_address,
-            Arc::clone(&tx_handler_map),
-            "transaction",
-        );
+        // all channels are served on the base port, DKG connections are told apart by their preamble
+        let base_port = config.base_address.port();
+        let dkg_connections = forward_connections(base_port + DKG_PORT_OFFSET);
+        let routes = Routes::new()
+            .route(Channel::Transaction, Arc::clone(&tx_handler_map))
+            .route(Channel::Mempool, Arc::clone(&mempool_handler_map))
+            .route(Channel::Consensus, Arc::clone(&consensus_handler_map))
+            .route(Channel::Signature, Arc::clone(&signature_handler_map))
+            .forward(CONNECTION_PREAMBLE, dkg_connections.clone());
+        let base_address = with_wildcard_ip(config.base_address.clone());
+        NetworkReceiver::spawn_multiplexed(base_address, routes, "multiplexed");
         info!(
-            "Node {} listening to client transactions on {}",
-            secret.name, transaction_address
+            "Node {} listening to all channels on {} ({:?} port mode)",
+            secret.name, base_address, port_mode()
         );
 
-        let mempool_address = with_wildcard_ip(config.mempool_address.clone());
-        NetworkReceiver::spawn(mempool_address, Arc::clone(&mempool_handler_map), "mempool");
-        info!(
-            "Node {} listening to mempool messages on {}",
-            secret.name, mempool_address
-        );
+        // operators that haven't moved to the base port yet still use the per-channel ports
+        if port_mode().per_port_listeners() {
+            let mempool_address = with_wildcard_ip(config.mempool_address.clone());
+            NetworkReceiver::spawn(mempool_address, Arc::clone(&mempool_handler_map), "mempool");
+            info!(
+                "Node {} listening to mempool messages on {}",
+                secret.name, mempool_address
+            );
 
-        let consensus_address = with_wildcard_ip(config.consensus_address.clone());
-        NetworkReceiver::spawn(
-            consensus_address,
-            Arc::clone(&consensus_handler_map),
-            "consensus",
-        );
-        info!(
-            "Node {} listening to consensus messages on {}",
-            secret.name, consensus_address
-        );
+            let consensus_address = with_wildcard_ip(config.consensus_address.clone());
+            NetworkReceiver::spawn(
+                consensus_address,
+                Arc::clone(&consensus_handler_map),
+                "consensus",
+            );
+            info!(
+                "Node {} listening to consensus messages on {}",
+                secret.name, consensus_address
+            );
 
-        let signature_address = with_wildcard_ip(config.signature_address.clone());
-        NetworkReceiver::spawn(
-            signature_address,
-            Arc::clone(&signature_handler_map),
-            "signature",
-        );
-        info!(
-            "Node {} listening to signature messages on {}",
-            secret.name, signature_address
-        );
+            let signature_address = with_wildcard_ip(config.signature_address.clone());
+            NetworkReceiver::spawn(
+                signature_address,
+                Arc::clone(&signature_handler_map),
+                "signature",
+            );
+            info!(
+                "Node {} listening to signature messages on {}",
+                secret.name, signature_address
+            );
+
+            let dkg_address = with_wildcard_ip(SocketAddr::new(self_address, base_port + DKG_PORT_OFFSET));
+            tokio::spawn(listen(dkg_address, dkg_connections));
+        }
 
         let (tx_validator_command, rx_validator_command) = MonitoredChannel::new(
             DEFAULT_CHANNEL_CAPACITY,
@@ -131,7 +161,6 @@ impl<T: EthSpec> Node<T> {
         );
 
         info!("Node {} successfully booted", secret.name);
-        let base_port = config.base_address.port();
         let node = Self {
             config,
             secret: secret.clone(),
@@ -144,24 +173,35 @@ impl<T: EthSpec> Node<T> {
         };
         Discovery::spawn(
             self_address,
+            base_port,
             base_port + DISCOVERY_PORT_OFFSET,
             Arc::clone(&key_ip_map),
             node.secret.clone(),
             BOOT_ENR.get().unwrap().clone(),
         );
 
+        let command_db = Database::new(secret_dir.parent().unwrap().join(CONTRACT_DATABASE_FILE))
+            .map_err(|e| ConfigError::ReadError {
+                file: CONTRACT_DATABASE_FILE.to_string(),
+                message: e.to_string(),
+            })?;
+        let share_refresh_epochs = node.config.share_refresh_epochs;
+        let tx_refresh_command = tx_validator_command.clone();
         Contract::spawn(
             secret_dir.parent().unwrap().to_path_buf(),
             secret.name,
-            tx_validator_command.clone(),
+            tx_validator_command,
         );
         let node = Arc::new(RwLock::new(node));
-        let initializer_store = Arc::new(RwLock::new(HashMap::new()));
+        if let Some(period) = share_refresh_epochs {
+            spawn_share_refresh(Arc::clone(&node), tx_refresh_command, period);
+        }
+        let initializer_store = Arc::new(RwLock::new(load_initializer_shares(&secret_dir)));
         Node::process_contract_command(
             Arc::clone(&node),
             Arc::clone(&key_ip_map),
             rx_validator_command,
-            tx_validator_command,
+            command_db,
             initializer_store,
         );
 
@@ -179,160 +219,198 @@ impl<T: EthSpec> Node<T> {
         }
     }
 
+    // commands are stored in the contract database before they are processed, so they survive restarts
+    // and a command that fails is retried later without holding up the others
     pub fn process_contract_command(
         node: Arc<RwLock<Node<T>>>,
-        operator_key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>>,
+        operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
         mut rx_contract_command: Receiver<ContractCommand>,
-        tx_contract_command: MonitoredSender<ContractCommand>,
+        db: Database,
         initializer_store: InitializerStore,
     ) {
+        let queued = Arc::new(Notify::new());
+        let notify = queued.clone();
+        let queue_db = db.clone();
+        tokio::spawn(async move {
+            while let Some(command) = rx_contract_command.recv().await {
+                match queue_db.enqueue_command(&command, unix_now()).await {
+                    Ok(id) => info!("queued contract command {} {}", id, command.name()),
+                    Err(e) => error!("Can't queue contract command {}: {}", command.name(), e),
+                }
+                notify.notify_one();
+            }
+            error!("channel is closed unexpected");
+        });
+        let in_flight: Arc<std::sync::Mutex<HashSet<i64>>> = Default::default();
         tokio::spawn(async move {
+            match db.query_dead_commands().await {
+                Ok(dead) if !dead.is_empty() => {
+                    warn!("{} contract commands are in the dead letter list", dead.len())
+                }
+                _ => {}
+            }
             loop {
-                match rx_contract_command.recv().await {
-                    Some(validator_command) => match validator_command {
-                        ContractCommand::StartValidator(
-                            validator,
-                            operator_pks,
-                            shared_pks,
-                            encrypted_sks,
-                        ) => {
-                            info!("StartValidator");
-                            match add_validator(
-                                node.clone(),
-                                validator,
-                                operator_pks,
-                                shared_pks,
-                                encrypted_sks,
-                                operator_key_ip_map.clone(),
-                                tx_contract_command.clone(),
-                            )
-                            .await
-                            {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to add validator: {}", e);
-                                }
-                            }
-                        }
-                        ContractCommand::RemoveValidator(validator) => {
-                            info!("RemoveValidator");
-                            match remove_validator(node.clone(), validator).await {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to remove validator: {}", e);
-                                }
-                            }
-                        }
-                        ContractCommand::ActivateValidator(validator) => {
-                            info!("ActivateValidator");
-                            match activate_validator(node.clone(), validator).await {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to remove validator: {}", e);
-                                }
-                            }
-                        }
-                        ContractCommand::StopValidator(validator) => {
-                            info!("StopValidator");
-                            match stop_validator(node.clone(), validator).await {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to stop validator: {}", e);
-                                }
-                            }
-                        }
-                        ContractCommand::StartInitializer(initializer, operator_pks) => {
-                            info!("StartInitializer");
-                            match start_initializer(
-                                node.clone(),
-                                initializer,
-                                operator_pks,
-                                operator_key_ip_map.clone(),
-                                initializer_store.clone(),
-                                tx_contract_command.clone(),
-                            )
-                            .await
-                            {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to start initializer: {}", e);
-                                }
-                            }
-                        }
-                        ContractCommand::MiniPoolCreated(
-                            initializer_id,
-                            validator_pk,
-                            op_pks,
-                            op_ids,
-                            minipool_address,
-                        ) => {
-                            info!("MiniPoolCreated");
-                            match minipool_deposit(
-                                node.clone(),
-                                initializer_id,
-                                validator_pk,
-                                op_pks,
-                                op_ids,
-                                operator_key_ip_map.clone(),
-                                minipool_address,
-                                initializer_store.clone(),
-                                8
-                            )
-                            .await
-                            {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to process minpool created: {}", e);
-                                }
+                let due = match db.query_due_commands(unix_now()).await {
+                    Ok(due) => due,
+                    Err(e) => {
+                        error!("Can't query queued contract commands: {}", e);
+                        vec![]
+                    }
+                };
+                // commands of different subjects run concurrently, e.g. the dkg of several initializers,
+                // while the database holds back later commands of a subject until the earlier one is done
+                for queued_command in due {
+                    if !in_flight.lock().unwrap().insert(queued_command.id) {
+                        continue;
+                    }
+                    let node = node.clone();
+                    let operator_key_ip_map = operator_key_ip_map.clone();
+                    let initializer_store = initializer_store.clone();
+                    let db = db.clone();
+                    let in_flight = in_flight.clone();
+                    let queued = queued.clone();
+                    tokio::spawn(async move {
+                        let name = queued_command.command.name();
+                        info!("{} (attempt {})", name, queued_command.attempts + 1);
+                        match execute_contract_command(
+                            node,
+                            queued_command.command,
+                            operator_key_ip_map,
+                            initializer_store,
+                            db.clone(),
+                        )
+                        .await
+                        {
+                            Ok(_) => db.complete_command(queued_command.id).await,
+                            Err(e) if queued_command.attempts + 1 >= COMMAND_MAX_ATTEMPTS => {
+                                error!("Failed to process {}, giving up after {} attempts: {}", name, COMMAND_MAX_ATTEMPTS, e);
+                                db.dead_letter_command(queued_command.id, e).await;
                             }
-                        }
-                        ContractCommand::MiniPoolReady(
-                            initializer_id,
-                            validator_pk,
-                            op_pks,
-                            op_ids,
-                            minipool_address,
-                        ) => {
-                            info!("MiniPoolReady");
-                            match minipool_deposit(
-                                node.clone(),
-                                initializer_id,
-                                validator_pk,
-                                op_pks,
-                                op_ids,
-                                operator_key_ip_map.clone(),
-                                minipool_address,
-                                initializer_store.clone(),
-                                24
-                            )
-                            .await
-                            {
-                                Ok(_) => {}
-                                Err(e) => {
-                                    error!("Failed to process minpool created: {}", e);
-                                }
+                            Err(e) => {
+                                let backoff = command_backoff(queued_command.attempts);
+                                error!("Failed to process {}, retry in {:?}: {}", name, backoff, e);
+                                db.retry_command(queued_command.id, unix_now() + backoff.as_secs(), e).await;
                             }
-                            let _ = initializer_store.write().await.remove(&initializer_id);
                         }
-                    },
-                    None => {
-                        error!("channel is closed unexpected");
-                        break;
-                    }
+                        in_flight.lock().unwrap().remove(&queued_command.id);
+                        // the next command of the subject may be due now
+                        queued.notify_one();
+                    });
                 }
+                let _ = timeout(COMMAND_POLL_INTERVAL, queued.notified()).await;
             }
         });
     }
 }
 
+pub async fn execute_contract_command<T: EthSpec>(
+    node: Arc<RwLock<Node<T>>>,
+    command: ContractCommand,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
+    initializer_store: InitializerStore,
+    db: Database,
+) -> Result<(), String> {
+    match command {
+        ContractCommand::StartValidator(validator, operator_pks, shared_pks, encrypted_sks, accept_legacy_shares) => {
+            add_validator(
+                node,
+                validator,
+                operator_pks,
+                shared_pks,
+                encrypted_sks,
+                accept_legacy_shares,
+                operator_key_ip_map,
+            )
+            .await
+        }
+        ContractCommand::RemoveValidator(validator) => remove_validator(node, validator).await,
+        ContractCommand::ActivateValidator(validator) => activate_validator(node, validator).await,
+        ContractCommand::StopValidator(validator) => stop_validator(node, validator).await,
+        ContractCommand::StartInitializer(initializer, operator_pks) => {
+            start_initializer(
+                node,
+                initializer,
+                operator_pks,
+                operator_key_ip_map,
+                initializer_store,
+            )
+            .await
+        }
+        ContractCommand::MiniPoolCreated(
+            initializer_id,
+            validator_pk,
+            op_pks,
+            op_ids,
+            minipool_address,
+        ) => {
+            minipool_deposit(
+                node,
+                initializer_id,
+                validator_pk,
+                op_pks,
+                op_ids,
+                operator_key_ip_map,
+                minipool_address,
+                initializer_store,
+                8
+            )
+            .await
+        }
+        ContractCommand::MiniPoolReady(
+            initializer_id,
+            validator_pk,
+            op_pks,
+            op_ids,
+            minipool_address,
+        ) => {
+            minipool_deposit(
+                node,
+                initializer_id,
+                validator_pk,
+                op_pks,
+                op_ids,
+                operator_key_ip_map,
+                minipool_address,
+                initializer_store.clone(),
+                24
+            )
+            .await?;
+            let _ = initializer_store.write().await.remove(&initializer_id);
+            Ok(())
+        }
+        ContractCommand::OperatorRemoved(operator, committees) => {
+            remove_operator(node, operator, committees, operator_key_ip_map).await;
+            Ok(())
+        }
+        ContractCommand::RefreshShares(validator_pk, epoch) => {
+            refresh_shares(node, validator_pk, epoch, operator_key_ip_map).await
+        }
+        ContractCommand::ReshareValidator(validator, operator_pks, old_operator_ids, epoch) => {
+            reshare_validator(node, db, validator, operator_pks, old_operator_ids, epoch, operator_key_ip_map).await
+        }
+    }
+}
+
+fn command_backoff(attempts: u32) -> Duration {
+    COMMAND_MIN_BACKOFF
+        .saturating_mul(1 << attempts.min(16))
+        .min(COMMAND_MAX_BACKOFF)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
 pub async fn add_validator<T: EthSpec>(
     node: Arc<RwLock<Node<T>>>,
     validator: Validator,
     operator_public_keys: OperatorPublicKeys,
     shared_public_keys: SharedPublicKeys,
     encrypted_secret_keys: EncryptedSecretKeys,
-    operator_key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>>,
-    tx_validator_command: MonitoredSender<ContractCommand>,
+    accept_legacy_shares: bool,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
 ) -> Result<(), String> {
     let node = node.read().await;
     let base_port = node.config.base_address.port();
@@ -350,138 +428,205 @@ pub async fn add_validator<T: EthSpec>(
     info!("[VA {}] adding validator {}", validator_id, validator_pk);
     let added_validator_dir = validator_dir.join(format!("{}", validator_pk));
     if added_validator_dir.exists() {
-        return Err("Validator exists".to_string());
+        // a previous attempt may have stopped half way, only a complete validator dir is kept
+        match complete_keystore_share(&validator_dir, &secret_dir, &validator_pk, &validator.releated_operators) {
+            Some(keystore_share) => {
+                if let Some(validator_store) = &node.validator_store {
+                    if validator_store.initialized_validators().read().await.is_enabled(&validator_pk).is_some() {
+                        info!("[VA {}] validator {} exists, skip", validator_id, validator_pk);
+                        return Ok(());
+                    }
+                }
+                info!("[VA {}] validator {} exists but is not running, add it", validator_id, validator_pk);
+                return register_keystore_share(&node, &keystore_share, &validator_dir, &secret_dir, &validator_pk, validator_id).await;
+            }
+            None => {
+                warn!("[VA {}] validator dir of {} is incomplete, rebuild it", validator_id, validator_pk);
+                cleanup_validator_dir(&validator_dir, &validator_pk, validator_id)?;
+                cleanup_password_dir(&secret_dir, &validator_pk, validator_id)?;
+            }
+        }
     }
 
     let operator_base_address =
         match get_operator_ips(operator_key_ip_map, &operator_public_keys, base_port).await {
             Ok(address) => address,
             Err(e) => {
-                sleep(Duration::from_secs(60)).await;
-                let _ = tx_validator_command
-                    .send(ContractCommand::StartValidator(
-                        validator,
-                        operator_public_keys,
-                        shared_public_keys,
-                        encrypted_secret_keys,
-                    ))
-                    .await;
-                info!("Will process the validator again");
                 cleanup_validator_dir(&validator_dir, &validator_pk, validator_id)?;
                 cleanup_password_dir(&secret_dir, &validator_pk, validator_id)?;
                 return Err(e);
             }
         };
 
-    let operator_ids: Vec<u64> = validator
-        .releated_operators
-        .iter()
-        .map(|x| *x as u64)
-        .collect();
-    let operator_bls_pks: Vec<Result<PublicKey, String>> = shared_public_keys
-        .iter()
-        .map(|pk| {
-            Ok(PublicKey::deserialize(&pk).map_err(|e| {
-                error!("Unable to deserialize operator pk {:?}", e);
-                format!("deserialize operator pk failed")
-            })?)
-        })
-        .collect();
-    if operator_bls_pks.iter().any(|x| !x.is_ok()) {
-        return Err("Some operators pk can't be deserialized!".to_string());
-    }
-    let operator_bls_pks = operator_bls_pks.into_iter().map(|x| x.unwrap()).collect();
-    let operator_node_pks: Vec<hscrypto::PublicKey> = operator_public_keys
-        .into_iter()
-        .map(|shared_pk| hscrypto::PublicKey(shared_pk.try_into().unwrap()))
-        .collect();
+    // validators created by an initializer sign with the share from the DKG
+    let keystore_share = match take_initializer_share(&secret_dir, &validator_dir, &validator_pk, operator_base_address.clone())? {
+        Some(keystore_share) => {
+            info!("[VA {}] use the dkg output as validator share", validator_id);
+            keystore_share
+        }
+        None => {
+            let operator_ids: Vec<u64> = validator
+                .releated_operators
+                .iter()
+                .map(|x| *x as u64)
+                .collect();
+            let operator_bls_pks: Vec<Result<PublicKey, String>> = shared_public_keys
+                .iter()
+                .map(|pk| {
+                    Ok(PublicKey::deserialize(&pk).map_err(|e| {
+                        error!("Unable to deserialize operator pk {:?}", e);
+                        format!("deserialize operator pk failed")
+                    })?)
+                })
+                .collect();
+            if operator_bls_pks.iter().any(|x| !x.is_ok()) {
+                return Err("Some operators pk can't be deserialized!".to_string());
+            }
+            let operator_bls_pks = operator_bls_pks.into_iter().map(|x| x.unwrap()).collect();
+            let operator_node_pks: Vec<hscrypto::PublicKey> = operator_public_keys
+                .into_iter()
+                .map(|shared_pk| hscrypto::PublicKey(shared_pk.try_into().unwrap()))
+                .collect();
 
-    let self_index: Vec<usize> = operator_node_pks
-        .iter()
-        .enumerate()
-        .filter(|&(_i, x)| *self_pk == *x)
-        .map(|(i, _)| i)
-        .collect();
-    if self_index.len() != 1 {
-        return Err("Can't find self public key".to_string());
-    }
+            let self_index: Vec<usize> = operator_node_pks
+                .iter()
+                .enumerate()
+                .filter(|&(_i, x)| *self_pk == *x)
+                .map(|(i, _)| i)
+                .collect();
+            if self_index.len() != 1 {
+                return Err("Can't find self public key".to_string());
+            }
 
-    // decrypt
-    let shared_key_pair = {
-        let rng = rand::thread_rng();
-        let mut elgamal = Elgamal::new(rng);
-        let ciphertext = Ciphertext::from_bytes(&encrypted_secret_keys[self_index[0]]);
-        let plain_shared_key = elgamal.decrypt(&ciphertext, &secret_key).map_err(|_e| {
-            format!(
-                "Unable to decrypt: ciphertext({:?}), secret_key({})",
-                hex::encode(&encrypted_secret_keys[self_index[0]]),
-                secret_key.display_secret()
-            )
-        })?;
-        let shared_secret_key = BlsSecretKey::deserialize(&plain_shared_key)
-            .map_err(|e| format!("Unable to deserialize secret key: {:?}", e))?;
-        let shared_public_key = shared_secret_key.public_key();
-        BlsKeypair::from_components(shared_public_key.clone(), shared_secret_key)
-    };
+            // decrypt
+            let shared_key_pair = {
+                let rng = rand::thread_rng();
+                let mut elgamal = Elgamal::new(rng);
+                let encrypted_key = encrypted_secret_keys
+                    .get(self_index[0])
+                    .ok_or_else(|| "Missing encrypted share".to_string())?;
+                let mut plain_shared_key = elgamal
+                    .decrypt_encrypted_key(
+                        encrypted_key,
+                        &secret_key,
+                        &validator_pk.serialize(),
+                        validator.releated_operators[self_index[0]] as u64,
+                        accept_legacy_shares,
+                    )
+                    .map_err(|e| {
+                        format!(
+                            "Unable to decrypt share for validator {} and operator {}: {}, ciphertext({:?})",
+                            validator_pk,
+                            validator.releated_operators[self_index[0]],
+                            e,
+                            hex::encode(encrypted_key),
+                        )
+                    })?;
+                let shared_secret_key = BlsSecretKey::deserialize(&plain_shared_key)
+                    .map_err(|e| format!("Unable to deserialize secret key: {:?}", e));
+                plain_shared_key.zeroize();
+                let shared_secret_key = shared_secret_key?;
+                let shared_public_key = shared_secret_key.public_key();
+                BlsKeypair::from_components(shared_public_key.clone(), shared_secret_key)
+            };
 
-    let keystore = KeystoreBuilder::new(&shared_key_pair, INSECURE_PASSWORD, "".into())
-        .map_err(|e| format!("Unable to create keystore builder: {:?}", e))?
-        .kdf(insecure_kdf())
-        .build()
-        .map_err(|e| format!("Unable to build keystore: {:?}", e))?;
+            let keystore = KeystoreBuilder::new(&shared_key_pair, INSECURE_PASSWORD, "".into())
+                .map_err(|e| format!("Unable to create keystore builder: {:?}", e))?
+                .kdf(insecure_kdf())
+                .build()
+                .map_err(|e| format!("Unable to build keystore: {:?}", e))?;
 
-    let keystore_share = KeystoreShare::new(
-        keystore,
-        validator_pk.clone(),
-        validator_id,
-        validator.releated_operators[self_index[0]] as u64,
-    );
+            let keystore_share = KeystoreShare::new(
+                keystore,
+                validator_pk.clone(),
+                validator_id,
+                validator.releated_operators[self_index[0]] as u64,
+            );
 
-    match ShareBuilder::new(validator_dir.clone())
-        .password_dir(secret_dir.clone())
-        .voting_keystore_share(keystore_share.clone(), INSECURE_PASSWORD)
-        .build()
-    {
-        Ok(va_dir) => {
-            info!("build validator dir in {:?}", va_dir);
-        }
-        Err(e) => {
-            return Err(format!("keystore share build failed: {:?}", e));
+            match ShareBuilder::new(validator_dir.clone())
+                .password_dir(secret_dir.clone())
+                .voting_keystore_share(keystore_share.clone(), INSECURE_PASSWORD)
+                .build()
+            {
+                Ok(va_dir) => {
+                    info!("build validator dir in {:?}", va_dir);
+                }
+                Err(e) => {
+                    return Err(format!("keystore share build failed: {:?}", e));
+                }
+            };
+
+            // generate keypair
+            let def = OperatorCommitteeDefinition {
+                total: total as u64,
+                threshold: committee_threshold(total),
+                validator_id: validator_id,
+                validator_public_key: validator_pk.clone(),
+                operator_ids: operator_ids,
+                operator_public_keys: operator_bls_pks,
+                node_public_keys: operator_node_pks,
+                base_socket_addresses: operator_base_address,
+            };
+
+            let committee_def_path =
+                default_operator_committee_definition_path(&validator_pk, validator_dir.clone());
+            info!("path {:?}, pk {:?}", &committee_def_path, &validator_pk);
+            match def
+                .to_file(committee_def_path.clone())
+                .map_err(|e| format!("Unable to save committee definition: error:{:?}", e))
+            {
+                Ok(_) => {}
+                Err(e) => {
+                    return Err(e.to_string());
+                }
+            }
+            keystore_share
         }
     };
+    register_keystore_share(&node, &keystore_share, &validator_dir, &secret_dir, &validator_pk, validator_id).await
+}
 
-    // generate keypair
-    let def = OperatorCommitteeDefinition {
-        total: total as u64,
-        threshold: THRESHOLD,
-        validator_id: validator_id,
-        validator_public_key: validator_pk.clone(),
-        operator_ids: operator_ids,
-        operator_public_keys: operator_bls_pks,
-        node_public_keys: operator_node_pks,
-        base_socket_addresses: operator_base_address,
-    };
+// the keystore share of this operator in the validator dir, if the share, its password and the
+// committee definition are all there
+fn complete_keystore_share(
+    validator_dir: &Path,
+    secret_dir: &Path,
+    validator_pk: &PublicKey,
+    operator_ids: &[u32],
+) -> Option<KeystoreShare> {
+    if !default_operator_committee_definition_path(validator_pk, validator_dir).exists() {
+        return None;
+    }
+    let share_path = operator_ids
+        .iter()
+        .map(|id| validator_dir.join(format!("{}", validator_pk)).join(id.to_string()).join(VOTING_KEYSTORE_SHARE_FILE))
+        .find(|path| path.exists())?;
+    let keystore_share = KeystoreShare::from_json_file(&share_path).ok()?;
+    if !default_keystore_share_password_path(&keystore_share, secret_dir).exists() {
+        return None;
+    }
+    Some(keystore_share)
+}
 
+// start signing with a keystore share stored in the validator dir
+async fn register_keystore_share<T: EthSpec>(
+    node: &Node<T>,
+    keystore_share: &KeystoreShare,
+    validator_dir: &Path,
+    secret_dir: &Path,
+    validator_pk: &PublicKey,
+    validator_id: u64,
+) -> Result<(), String> {
     let committee_def_path =
-        default_operator_committee_definition_path(&validator_pk, validator_dir.clone());
-    info!("path {:?}, pk {:?}", &committee_def_path, &validator_pk);
-    match def
-        .to_file(committee_def_path.clone())
-        .map_err(|e| format!("Unable to save committee definition: error:{:?}", e))
-    {
-        Ok(_) => {}
-        Err(e) => {
-            return Err(e.to_string());
-        }
-    }
+        default_operator_committee_definition_path(validator_pk, validator_dir);
 
     let voting_keystore_share_path =
-        default_keystore_share_path(&keystore_share, validator_dir.clone());
+        default_keystore_share_path(keystore_share, validator_dir);
     let voting_keystore_share_password_path =
-        default_keystore_share_password_path(&keystore_share, secret_dir.clone());
+        default_keystore_share_password_path(keystore_share, secret_dir);
     match &node.validator_store {
         Some(validator_store) => {
-            let _ = validator_store
+            validator_store
                 .add_validator_keystore_share(
                     voting_keystore_share_path,
                     voting_keystore_share_password_path,
@@ -494,7 +639,8 @@ pub async fn add_validator<T: EthSpec>(
                     keystore_share.master_id,
                     keystore_share.share_id,
                 )
-                .await;
+                .await
+                .map_err(|e| format!("[VA {}] failed to add validator {}: {}", validator_id, validator_pk, e))?;
             info!("[VA {}] added validator {}", validator_id, validator_pk);
         }
         _ => {
@@ -595,6 +741,35 @@ pub async fn remove_validator<T: EthSpec>(
     Ok(())
 }
 
+// a committee keeps signing as long as enough of its operators are still registered
+pub async fn remove_operator<T: EthSpec>(
+    node: Arc<RwLock<Node<T>>>,
+    operator: Operator,
+    committees: Vec<(Validator, OperatorIds)>,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
+) {
+    operator_key_ip_map
+        .write()
+        .await
+        .remove(&base64::encode(&operator.public_key));
+    for (validator, registered_operators) in committees {
+        if registered_operators.len() < committee_threshold(validator.releated_operators.len()) as usize {
+            error!(
+                "[VA {}] operator {} is removed, only {} operators of the committee are left, stopping validator",
+                validator.id, operator.id, registered_operators.len()
+            );
+            if let Err(e) = stop_validator(node.clone(), validator).await {
+                error!("Failed to stop validator: {}", e);
+            }
+        } else {
+            warn!(
+                "[VA {}] operator {} is removed, committee continues with operators {:?}",
+                validator.id, operator.id, registered_operators
+            );
+        }
+    }
+}
+
 pub async fn stop_validator<T: EthSpec>(
     node: Arc<RwLock<Node<T>>>,
     validator: Validator
@@ -624,31 +799,21 @@ pub async fn start_initializer<T: EthSpec>(
     node: Arc<RwLock<Node<T>>>,
     initializer: Initializer,
     operator_public_keys: OperatorPublicKeys,
-    operator_key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>>,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
     initializer_store: Arc<
         RwLock<HashMap<u32, (BlsKeypair, BlsPublicKey, HashMap<u64, BlsPublicKey>)>>,
     >,
-    tx_contract_command: MonitoredSender<ContractCommand>,
 ) -> Result<(), String> {
     let node = node.read().await;
     let base_port = node.config.base_address.port();
     let mut operator_ips =
         match get_operator_ips(operator_key_ip_map, &operator_public_keys, base_port).await {
             Ok(ips) => ips,
-            Err(e) => {
-                sleep(Duration::from_secs(10)).await;
-                let _ = tx_contract_command
-                    .send(ContractCommand::StartInitializer(
-                        initializer,
-                        operator_public_keys,
-                    ))
-                    .await;
-                info!("Will process the validator again");
-                return Err(e);
-            }
+            Err(e) => return Err(e),
         };
+    let base_socket_addresses = operator_ips.clone();
     for x in operator_ips.iter_mut() {
-        (*x).set_port(base_port + DKG_PORT_OFFSET);
+        *x = channel_address(*x, DKG_PORT_OFFSET);
     }
     let self_op_id = *SELF_OPERATOR_ID
         .get()
@@ -658,20 +823,43 @@ pub async fn start_initializer<T: EthSpec>(
         .iter()
         .map(|x| *x as u64)
         .collect();
+    let identity = io_identity(&node.secret, &op_ids, &operator_public_keys)?;
     let io = Arc::new(
         NetIOCommittee::new(
             self_op_id as u64,
             base_port + DKG_PORT_OFFSET,
+            session_id(&format!("dkg-initializer-{}", initializer.id), &op_ids),
             op_ids.as_slice(),
             operator_ips.as_slice(),
+            identity,
         )
-        .await,
+        .await?,
     );
-    let dkg = DKG::new(self_op_id as u64, io, THRESHOLD as usize);
+    let dkg = DKG::new(self_op_id as u64, io, committee_threshold(op_ids.len()) as usize);
     let (keypair, va_pk, shared_pks) = dkg
         .run()
         .await
         .map_err(|e| format!("run dkg failed {:?}", e))?;
+    // the share has to survive restarts until the minipool deposits are signed and the validator is registered
+    let node_public_keys = operator_public_keys
+        .iter()
+        .map(|pk| {
+            pk.clone()
+                .try_into()
+                .map(hscrypto::PublicKey)
+                .map_err(|_| "invalid operator public key".to_string())
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    persist_initializer_share(
+        &node.config.secrets_dir,
+        initializer.id,
+        self_op_id as u64,
+        &keypair,
+        &va_pk,
+        &shared_pks,
+        node_public_keys,
+        base_socket_addresses,
+    )?;
 
     let pk_str: String = va_pk.as_hex_string()[2..].to_string();
     // push va pk to web server
@@ -699,7 +887,7 @@ pub async fn minipool_deposit<T: EthSpec>(
     validator_pk: [u8; 48],
     operator_public_keys: OperatorPublicKeys,
     operator_ids: OperatorIds,
-    operator_key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>>,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
     minipool_address: H160,
     initializer_store: InitializerStore,
     amount: u64
@@ -709,13 +897,13 @@ pub async fn minipool_deposit<T: EthSpec>(
     let mut operator_ips = match get_operator_ips(operator_key_ip_map, &operator_public_keys, base_port).await {
         Ok(ips) => ips,
         Err(e) => {
-            error!("Some operators are not online, it's critical error, minipool exiting");
-            initializer_store.write().await.remove(&initializer_id).ok_or(format!("can't remove initializer store id: {}", initializer_id))?;
+            // the share is kept, the deposit is signed when the command is retried
+            error!("Some operators are not online, minipool deposit of initializer {} is delayed", initializer_id);
             return Err(e);
         }
     };
     for x in operator_ips.iter_mut() {
-        (*x).set_port(base_port + DKG_PORT_OFFSET);
+        *x = channel_address(*x, DKG_PORT_OFFSET);
     }
     let op_ids: Vec<u64> = operator_ids.into_iter().map(|x| x as u64).collect();
     let self_op_id = *SELF_OPERATOR_ID
@@ -723,8 +911,11 @@ pub async fn minipool_deposit<T: EthSpec>(
         .ok_or("Self operator has not been set".to_string())?;
     let store = initializer_store.read().await;
     let (keypair, va_pk, op_bls_pks) = store.get(&initializer_id).ok_or(format!("can't get initializer store id: {}", initializer_id))?;
-    let io_committee = Arc::new(NetIOCommittee::new(self_op_id as u64, base_port + DKG_PORT_OFFSET, &op_ids, &operator_ips).await);
-    let signer = SimpleDistributedSigner::new(self_op_id as u64, keypair.clone(), va_pk.clone(), op_bls_pks.clone(), io_committee, THRESHOLD as usize);
+    let identity = io_identity(&node.secret, &op_ids, &operator_public_keys)?;
+    // deposits of different initializers and amounts can be signed at the same time
+    let session = session_id(&format!("deposit-initializer-{}-{}", initializer_id, amount), &op_ids);
+    let io_committee = Arc::new(NetIOCommittee::new(self_op_id as u64, base_port + DKG_PORT_OFFSET, session, &op_ids, &operator_ips, identity).await?);
+    let signer = SimpleDistributedSigner::new(self_op_id as u64, keypair.clone(), va_pk.clone(), op_bls_pks.clone(), io_committee, committee_threshold(op_ids.len()) as usize);
     let mpk = BlsPublicKey::deserialize(&validator_pk).map_err(|e| format!("Can't deserilize bls pk {:?}", e))?;
     if mpk != *va_pk {
         return Err(format!("validator pks don't match, local stored: {:?}, received {:?}", va_pk, mpk));
@@ -744,6 +935,16 @@ pub async fn minipool_deposit<T: EthSpec>(
     Ok(())
 }
 
+// operators authenticate each other with their registered keys on the dkg port
+pub fn io_identity(secret: &Secret, op_ids: &[u64], operator_public_keys: &OperatorPublicKeys) -> Result<NetIOIdentity, String> {
+    let secret_key = secp256k1::SecretKey::from_slice(&secret.secret.0).map_err(|e| format!("Unable to load secret key {}", e))?;
+    let public_keys = operator_public_keys
+        .iter()
+        .map(|pk| secp256k1::PublicKey::from_slice(pk).map_err(|e| format!("invalid operator public key {}", e)))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(NetIOIdentity::new(secret_key, op_ids, &public_keys))
+}
+
 pub async fn cleanup_handler<T: EthSpec>(node: Arc<RwLock<Node<T>>>, validator_id: u64) {
     let node_ = node.read().await;
     let _ = node_.tx_handler_map.write().await.remove(&validator_id);
@@ -776,6 +977,161 @@ pub async fn cleanup_keystore<T: EthSpec>(
     }
 }
 
+// the DKG output of an initializer is kept under base_dir/initializers/<id> until its validator is registered,
+// outside the validator dir so that it is not discovered and run before that
+fn initializer_dir(secret_dir: &Path, initializer_id: u32) -> PathBuf {
+    secret_dir
+        .parent()
+        .unwrap_or(secret_dir)
+        .join(INITIALIZER_DIR)
+        .join(initializer_id.to_string())
+}
+
+// write the share as an encrypted keystore share together with its committee definition
+pub fn persist_initializer_share(
+    secret_dir: &Path,
+    initializer_id: u32,
+    self_op_id: u64,
+    keypair: &BlsKeypair,
+    va_pk: &BlsPublicKey,
+    shared_pks: &HashMap<u64, BlsPublicKey>,
+    node_public_keys: Vec<hscrypto::PublicKey>,
+    base_socket_addresses: Vec<SocketAddr>,
+) -> Result<(), String> {
+    let dir = initializer_dir(secret_dir, initializer_id);
+    let validator_id = convert_va_pk_to_u64(&va_pk.serialize());
+    // a previous run of the same initializer is replaced
+    if dir.exists() {
+        remove_dir_all(&dir).map_err(|e| format!("[INI {}] Failed to remove initializer dir ({})", initializer_id, e))?;
+    }
+    cleanup_password_dir(secret_dir, va_pk, validator_id)?;
+
+    let password = hex::encode(rand::thread_rng().gen::<[u8; 32]>());
+    let keystore = KeystoreBuilder::new(keypair, password.as_bytes(), "".into())
+        .map_err(|e| format!("Unable to create keystore builder: {:?}", e))?
+        .build()
+        .map_err(|e| format!("Unable to build keystore: {:?}", e))?;
+    let keystore_share = KeystoreShare::new(keystore, va_pk.clone(), validator_id, self_op_id);
+    ShareBuilder::new(dir.clone())
+        .password_dir(secret_dir.to_path_buf())
+        .voting_keystore_share(keystore_share, password.as_bytes())
+        .build()
+        .map_err(|e| format!("keystore share build failed: {:?}", e))?;
+
+    let mut operator_ids: Vec<u64> = shared_pks.keys().cloned().collect();
+    operator_ids.sort();
+    let def = OperatorCommitteeDefinition {
+        total: operator_ids.len() as u64,
+        threshold: committee_threshold(operator_ids.len()),
+        validator_id,
+        validator_public_key: va_pk.clone(),
+        operator_public_keys: operator_ids.iter().map(|id| shared_pks[id].clone()).collect(),
+        operator_ids,
+        node_public_keys,
+        base_socket_addresses,
+    };
+    def.to_file(default_operator_committee_definition_path(va_pk, &dir))
+        .map_err(|e| format!("Unable to save committee definition: error:{:?}", e))
+}
+
+// DKG outputs of all initializers that are not registered as validators yet
+pub fn load_initializer_shares(secret_dir: &Path) -> HashMap<u32, (BlsKeypair, BlsPublicKey, HashMap<u64, BlsPublicKey>)> {
+    let mut shares = HashMap::new();
+    let initializers_dir = secret_dir.parent().unwrap_or(secret_dir).join(INITIALIZER_DIR);
+    let entries = match initializers_dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return shares,
+    };
+    for entry in entries.flatten() {
+        let initializer_id = match entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) {
+            Some(initializer_id) => initializer_id,
+            None => continue,
+        };
+        match load_initializer_share(secret_dir, initializer_id) {
+            Ok(share) => {
+                info!("[INI {}] loaded dkg output", initializer_id);
+                shares.insert(initializer_id, share);
+            }
+            Err(e) => error!("[INI {}] can't load dkg output: {}", initializer_id, e),
+        }
+    }
+    shares
+}
+
+fn load_initializer_share(
+    secret_dir: &Path,
+    initializer_id: u32,
+) -> Result<(BlsKeypair, BlsPublicKey, HashMap<u64, BlsPublicKey>), String> {
+    let (share_path, def) = find_initializer_share(&initializer_dir(secret_dir, initializer_id))?;
+    let keystore_share = KeystoreShare::from_json_file(&share_path)
+        .map_err(|e| format!("Unable to read keystore share {:?}: {:?}", share_path, e))?;
+    let password_path = default_keystore_share_password_path(&keystore_share, secret_dir);
+    let password = read_password(&password_path)
+        .map_err(|e| format!("Unable to read password {:?}: {}", password_path, e))?;
+    let keypair = keystore_share
+        .keystore
+        .decrypt_keypair(password.as_bytes())
+        .map_err(|e| format!("Unable to decrypt keystore share: {:?}", e))?;
+    let shared_pks = def
+        .operator_ids
+        .iter()
+        .cloned()
+        .zip(def.operator_public_keys.iter().cloned())
+        .collect();
+    Ok((keypair, def.validator_public_key, shared_pks))
+}
+
+// the keystore share and committee definition in an initializer dir, laid out like a validator dir
+fn find_initializer_share(dir: &Path) -> Result<(PathBuf, OperatorCommitteeDefinition), String> {
+    for entry in dir.read_dir().map_err(|e| format!("Unable to read {:?}: {}", dir, e))?.flatten() {
+        let va_dir = entry.path();
+        if !va_dir.is_dir() {
+            continue;
+        }
+        for share_entry in va_dir.read_dir().map_err(|e| format!("Unable to read {:?}: {}", va_dir, e))?.flatten() {
+            let share_path = share_entry.path().join(VOTING_KEYSTORE_SHARE_FILE);
+            if share_path.exists() {
+                let def = OperatorCommitteeDefinition::from_file(va_dir.join(OPERATOR_COMMITTEE_DEFINITION_FILENAME))
+                    .map_err(|e| format!("Unable to read committee definition in {:?}: {:?}", va_dir, e))?;
+                return Ok((share_path, def));
+            }
+        }
+    }
+    Err(format!("no keystore share in {:?}", dir))
+}
+
+// move the DKG output of the validator, if any, into the validator dir so it becomes the signing key
+fn take_initializer_share(
+    secret_dir: &Path,
+    validator_dir: &Path,
+    validator_pk: &PublicKey,
+    base_socket_addresses: Vec<SocketAddr>,
+) -> Result<Option<KeystoreShare>, String> {
+    let initializers_dir = secret_dir.parent().unwrap_or(secret_dir).join(INITIALIZER_DIR);
+    let entries = match initializers_dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return Ok(None),
+    };
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !dir.join(format!("{}", validator_pk)).exists() {
+            continue;
+        }
+        let (share_path, mut def) = find_initializer_share(&dir)?;
+        let keystore_share = KeystoreShare::from_json_file(&share_path)
+            .map_err(|e| format!("Unable to read keystore share {:?}: {:?}", share_path, e))?;
+        std::fs::rename(dir.join(format!("{}", validator_pk)), validator_dir.join(format!("{}", validator_pk)))
+            .map_err(|e| format!("Unable to move dkg output of {}: {}", validator_pk, e))?;
+        // operators may have moved since the DKG
+        def.base_socket_addresses = base_socket_addresses;
+        def.to_file(default_operator_committee_definition_path(validator_pk, validator_dir))
+            .map_err(|e| format!("Unable to save committee definition: error:{:?}", e))?;
+        let _ = remove_dir_all(&dir);
+        return Ok(Some(keystore_share));
+    }
+    Ok(None)
+}
+
 pub fn cleanup_db(base_dir: &Path, validator_id: u64) -> Result<(), String> {
     let db_dir = base_dir.join(DB_FILENAME).join(validator_id.to_string());
     if db_dir.exists() {
@@ -846,3 +1202,36 @@ pub fn cleanup_password_dir(
     }
     Ok(())
 }
+
+#[test]
+fn test_initializer_share_persistence() {
+    let base_dir = tempfile::tempdir().unwrap();
+    let secret_dir = base_dir.path().join("secrets");
+    let validator_dir = base_dir.path().join("validators");
+    std::fs::create_dir_all(&secret_dir).unwrap();
+    std::fs::create_dir_all(&validator_dir).unwrap();
+    let keypair = BlsKeypair::random();
+    let va_pk = BlsKeypair::random().pk;
+    let shared_pks: HashMap<u64, BlsPublicKey> =
+        (1..=4).map(|id| (id, if id == 2 { keypair.pk.clone() } else { BlsKeypair::random().pk })).collect();
+    let addresses: Vec<SocketAddr> = (0..4).map(|i| SocketAddr::from(([127, 0, 0, 1], 25000 + i))).collect();
+    let node_pks: Vec<hscrypto::PublicKey> = (0..4).map(|_| Secret::new().name).collect();
+    persist_initializer_share(&secret_dir, 7, 2, &keypair, &va_pk, &shared_pks, node_pks, addresses.clone()).unwrap();
+
+    // reloaded as after a restart
+    let loaded = load_initializer_shares(&secret_dir);
+    let (loaded_keypair, loaded_va_pk, loaded_shared_pks) = loaded.get(&7).unwrap();
+    assert_eq!(loaded_keypair.pk, keypair.pk);
+    assert_eq!(*loaded_va_pk, va_pk);
+    assert_eq!(*loaded_shared_pks, shared_pks);
+    // not discovered as a validator before it is registered
+    assert!(!validator_dir.join(format!("{}", va_pk)).exists());
+
+    let keystore_share = take_initializer_share(&secret_dir, &validator_dir, &va_pk, addresses)
+        .unwrap()
+        .unwrap();
+    assert_eq!(keystore_share.share_id, 2);
+    assert!(default_keystore_share_path(&keystore_share, &validator_dir).exists());
+    assert!(default_operator_committee_definition_path(&va_pk, &validator_dir).exists());
+    assert!(load_initializer_shares(&secret_dir).is_empty());
+}
@@ -0,0 +1,520 @@
+use super::config::{channel_address, DKG_PORT_OFFSET, REFRESH_DIR};
+use super::contract::{
+    apply_committee_change, ContractCommand, OperatorIds, OperatorPublicKeys, Validator, SELF_OPERATOR_ID,
+};
+use super::db::Database;
+use super::node::{cleanup_password_dir, cleanup_validator_dir, io_identity, Node};
+use super::utils::{committee_threshold, get_operator_ips};
+use crate::crypto::dkg::DKG;
+use crate::network::io_committee::{session_id, NetIOCommittee};
+use crate::validation::account_utils::validator_definitions::ValidatorDefinitions;
+use crate::validation::account_utils::{
+    default_keystore_share_password_path, default_operator_committee_definition_path, read_password,
+};
+use crate::validation::eth2_keystore_share::keystore_share::KeystoreShare;
+use crate::validation::operator_committee_definitions::{OperatorCommitteeDefinition, OPERATOR_COMMITTEE_DEFINITION_FILENAME};
+use crate::validation::validator_dir::share_builder::{write_password_to_file, VOTING_KEYSTORE_SHARE_FILE};
+use crate::validation::validator_store::ValidatorStore;
+use bls::Keypair;
+use eth2_keystore::KeystoreBuilder;
+use hsutils::monitored_channel::MonitoredSender;
+use log::{error, info};
+use rand::Rng;
+use slot_clock::SystemTimeSlotClock;
+use std::collections::HashMap;
+use std::fs::{create_dir_all, remove_dir_all, rename, File};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tokio::time::{sleep, timeout, Duration};
+use types::{Epoch, EthSpec, PublicKey};
+
+// the refreshed shares are used this many epochs after the refresh is triggered, which leaves
+// time for all operators of the committee to run it
+pub const REFRESH_ACTIVATION_DELAY: u64 = 2;
+// written to the staging dir once the refreshed shares are complete, and once they are in use
+const STAGED_FILE: &str = "staged";
+const ACTIVATED_FILE: &str = "activated";
+// the committee setup and DKG for new shares have to end this long before the shares are used,
+// so that they are staged in time
+const STAGING_MARGIN: Duration = Duration::from_secs(12);
+
+// base/refresh/<validator id>/<epoch>
+fn staging_dir(base_dir: &Path, validator_id: u64, epoch: u64) -> PathBuf {
+    base_dir
+        .join(REFRESH_DIR)
+        .join(validator_id.to_string())
+        .join(epoch.to_string())
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// every `period` epochs, all operators ask for a refresh of the shares of their enabled
+// distributed validators. The epochs come from the beacon chain clock, so the operators of a
+// committee agree on them without talking to each other.
+pub fn spawn_share_refresh<T: EthSpec>(
+    node: Arc<RwLock<Node<T>>>,
+    tx: MonitoredSender<ContractCommand>,
+    period: u64,
+) {
+    tokio::spawn(async move {
+        loop {
+            let validator_store = node.read().await.validator_store.clone();
+            let (validator_store, epoch) = match validator_store {
+                Some(validator_store) => match validator_store.current_epoch() {
+                    Some(epoch) => (validator_store, epoch.as_u64()),
+                    None => {
+                        sleep(Duration::from_secs(12)).await;
+                        continue;
+                    }
+                },
+                None => {
+                    sleep(Duration::from_secs(12)).await;
+                    continue;
+                }
+            };
+            let next = (epoch / period + 1) * period;
+            if let Some(start) = validator_store.epoch_start(Epoch::new(next)) {
+                sleep(start.saturating_sub(unix_now())).await;
+            }
+
+            let validator_dir = node.read().await.config.validator_dir.clone();
+            let definitions = match ValidatorDefinitions::open(&validator_dir) {
+                Ok(definitions) => definitions,
+                Err(e) => {
+                    error!("Can't open validator definitions for share refresh: {:?}", e);
+                    continue;
+                }
+            };
+            for def in definitions.as_slice().iter().filter(|def| def.enabled) {
+                // only distributed validators have a committee to refresh with
+                if !default_operator_committee_definition_path(&def.voting_public_key, &validator_dir).exists() {
+                    continue;
+                }
+                let mut validator_pk = [0u8; 48];
+                validator_pk.copy_from_slice(def.voting_public_key.serialize().as_slice());
+                info!("refreshing shares of validator {} at epoch {}", def.voting_public_key, next);
+                let _ = tx
+                    .send(ContractCommand::RefreshShares(validator_pk, next + REFRESH_ACTIVATION_DELAY))
+                    .await;
+            }
+        }
+    });
+}
+
+pub async fn refresh_shares<T: EthSpec>(
+    node: Arc<RwLock<Node<T>>>,
+    validator_pk: [u8; 48],
+    epoch: u64,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
+) -> Result<(), String> {
+    let validator_pk = PublicKey::deserialize(&validator_pk)
+        .map_err(|e| format!("Unable to deserialize validator public key: {:?}", e))?;
+    let (validator_dir, secret_dir, validator_store) = {
+        let node_ = node.read().await;
+        (
+            node_.config.validator_dir.clone(),
+            node_.config.secrets_dir.clone(),
+            node_.validator_store.clone(),
+        )
+    };
+    let validator_store = validator_store.ok_or("no validator store is set".to_string())?;
+    let base_dir = secret_dir
+        .parent()
+        .ok_or("Failed to get parent of secret dir (possibly the root dir)".to_string())?;
+    let self_op_id = *SELF_OPERATOR_ID
+        .get()
+        .ok_or("Self operator has not been set".to_string())?;
+
+    let committee_def_path = default_operator_committee_definition_path(&validator_pk, &validator_dir);
+    let def = OperatorCommitteeDefinition::from_file(&committee_def_path)
+        .map_err(|e| format!("Unable to read committee definition: {:?}", e))?;
+    let keystore_share_path = validator_dir
+        .join(format!("{}", validator_pk))
+        .join(format!("{}", self_op_id))
+        .join(VOTING_KEYSTORE_SHARE_FILE);
+    let staging = staging_dir(base_dir, def.validator_id, epoch);
+    if staging.join(ACTIVATED_FILE).exists() {
+        return Ok(());
+    }
+
+    if !staging.join(STAGED_FILE).exists() {
+        let time_left = time_to_stage(&validator_store, epoch)
+            .ok_or_else(|| format!("[VA {}] refresh for epoch {} is too late", def.validator_id, epoch))?;
+        let keystore_share = KeystoreShare::from_json_file(&keystore_share_path)
+            .map_err(|e| format!("failed to get keystore share from file, error: {:?}", e))?;
+        let password_path = default_keystore_share_password_path(&keystore_share, &secret_dir);
+        let password = read_password(&password_path)
+            .map_err(|e| format!("Unable to read password {:?}: {}", password_path, e))?;
+        let keypair = keystore_share
+            .keystore
+            .decrypt_keypair(password.as_bytes())
+            .map_err(|e| format!("Unable to decrypt keystore share: {:?}", e))?;
+
+        let node_ = node.read().await;
+        let base_port = node_.config.base_address.port();
+        let node_public_keys: Vec<Vec<u8>> = def.node_public_keys.iter().map(|pk| pk.0.to_vec()).collect();
+        let mut operator_ips = get_operator_ips(operator_key_ip_map, &node_public_keys, base_port).await?;
+        for x in operator_ips.iter_mut() {
+            *x = channel_address(*x, DKG_PORT_OFFSET);
+        }
+        let identity = io_identity(&node_.secret, &def.operator_ids, &node_public_keys)?;
+        drop(node_);
+        let session = session_id(&format!("refresh-{}-{}", def.validator_id, epoch), &def.operator_ids);
+        let pks = def
+            .operator_ids
+            .iter()
+            .cloned()
+            .zip(def.operator_public_keys.iter().cloned())
+            .collect();
+        // operators that never show up would keep us waiting forever
+        let (new_keypair, new_pks) = timeout(time_left, async {
+            let io = Arc::new(
+                NetIOCommittee::new(
+                    self_op_id as u64,
+                    base_port + DKG_PORT_OFFSET,
+                    session,
+                    &def.operator_ids,
+                    &operator_ips,
+                    identity,
+                )
+                .await?,
+            );
+            let dkg = DKG::new(self_op_id as u64, io, def.threshold as usize);
+            dkg.refresh(&keypair, &validator_pk, &pks)
+                .await
+                .map_err(|e| format!("run share refresh failed {:?}", e))
+        })
+        .await
+        .map_err(|_| format!("[VA {}] refresh for epoch {} timed out", def.validator_id, epoch))??;
+
+        // the new share is encrypted with the password of the current one
+        let keystore = KeystoreBuilder::new(&new_keypair, password.as_bytes(), "".into())
+            .map_err(|e| format!("Unable to create keystore builder: {:?}", e))?
+            .build()
+            .map_err(|e| format!("Unable to build keystore: {:?}", e))?;
+        let new_keystore_share = KeystoreShare::new(keystore, validator_pk.clone(), def.validator_id, self_op_id as u64);
+        let new_def = OperatorCommitteeDefinition {
+            operator_public_keys: def.operator_ids.iter().map(|id| new_pks[id].clone()).collect(),
+            ..def.clone()
+        };
+        stage(&staging, &new_keystore_share, &new_def)?;
+        info!("[VA {}] refreshed shares are used from epoch {}", def.validator_id, epoch);
+    }
+
+    wait_for_epoch(&validator_store, epoch).await;
+    activate(&staging, &keystore_share_path, &committee_def_path)?;
+
+    // the validator picks up the new share when it is started again
+    let enabled = ValidatorDefinitions::open(&validator_dir)
+        .map_err(|e| format!("Can't open validator definitions {:?}", e))?
+        .as_slice()
+        .iter()
+        .any(|d| d.voting_public_key == validator_pk && d.enabled);
+    if enabled {
+        let keystore_share = KeystoreShare::from_json_file(&keystore_share_path)
+            .map_err(|e| format!("failed to get keystore share from file, error: {:?}", e))?;
+        validator_store.stop_validator_keystore(&validator_pk).await;
+        validator_store
+            .add_validator_keystore_share(
+                keystore_share_path,
+                default_keystore_share_password_path(&keystore_share, &secret_dir),
+                true,
+                None,
+                None,
+                None,
+                None,
+                committee_def_path,
+                keystore_share.master_id,
+                keystore_share.share_id,
+            )
+            .await?;
+    }
+    File::create(staging.join(ACTIVATED_FILE))
+        .map_err(|e| format!("Unable to mark refresh as activated: {}", e))?;
+    info!("[VA {}] switched to refreshed shares at epoch {}", def.validator_id, epoch);
+    Ok(())
+}
+
+// the operators of the current committee that stay in the new one deal their shares to it. Joining
+// operators start and leaving operators stop signing for the validator at the epoch. At least
+// the threshold of the current committee has to stay. The local record follows the new
+// committee once it is in use, unless the change was reorganized out of the chain before.
+pub async fn reshare_validator<T: EthSpec>(
+    node: Arc<RwLock<Node<T>>>,
+    db: Database,
+    validator: Validator,
+    operator_public_keys: OperatorPublicKeys,
+    old_operator_ids: OperatorIds,
+    epoch: u64,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
+) -> Result<(), String> {
+    let validator_id = validator.id;
+    let validator_pk = PublicKey::deserialize(&validator.public_key)
+        .map_err(|e| format!("Unable to deserialize validator public key: {:?}", e))?;
+    let (validator_dir, secret_dir, validator_store) = {
+        let node_ = node.read().await;
+        (
+            node_.config.validator_dir.clone(),
+            node_.config.secrets_dir.clone(),
+            node_.validator_store.clone(),
+        )
+    };
+    let validator_store = validator_store.ok_or("no validator store is set".to_string())?;
+    let base_dir = secret_dir
+        .parent()
+        .ok_or("Failed to get parent of secret dir (possibly the root dir)".to_string())?;
+    let self_op_id = *SELF_OPERATOR_ID
+        .get()
+        .ok_or("Self operator has not been set".to_string())?;
+    let staging = staging_dir(base_dir, validator_id, epoch);
+    if staging.join(ACTIVATED_FILE).exists() {
+        return Ok(());
+    }
+    let va_str = hex::encode(validator.public_key);
+    if reshare_cancelled(&db, &va_str, &staging, epoch).await? {
+        return Ok(());
+    }
+
+    if !validator.releated_operators.contains(&self_op_id) {
+        info!("[VA {}] leaving the committee at epoch {}", validator_id, epoch);
+        wait_for_epoch(&validator_store, epoch).await;
+        if reshare_cancelled(&db, &va_str, &staging, epoch).await? {
+            return Ok(());
+        }
+        validator_store.remove_validator_keystore(&validator_pk).await;
+        cleanup_validator_dir(&validator_dir, &validator_pk, validator_id)?;
+        cleanup_password_dir(&secret_dir, &validator_pk, validator_id)?;
+        apply_committee_change(&db, validator).await;
+        create_dir_all(&staging).map_err(|e| format!("Unable to create {:?}: {}", staging, e))?;
+        File::create(staging.join(ACTIVATED_FILE))
+            .map_err(|e| format!("Unable to mark reshare as activated: {}", e))?;
+        info!("[VA {}] left the committee at epoch {}", validator_id, epoch);
+        return Ok(());
+    }
+
+    let joining = !old_operator_ids.contains(&self_op_id);
+    let committee_def_path = default_operator_committee_definition_path(&validator_pk, &validator_dir);
+    let keystore_share_path = validator_dir
+        .join(format!("{}", validator_pk))
+        .join(format!("{}", self_op_id))
+        .join(VOTING_KEYSTORE_SHARE_FILE);
+
+    if !staging.join(STAGED_FILE).exists() {
+        let time_left = time_to_stage(&validator_store, epoch)
+            .ok_or_else(|| format!("[VA {}] reshare for epoch {} is too late", validator_id, epoch))?;
+        // operators that join have neither a share nor the current committee definition
+        let (current, password) = if joining {
+            (None, hex::encode(rand::thread_rng().gen::<[u8; 32]>()).into_bytes())
+        } else {
+            let def = OperatorCommitteeDefinition::from_file(&committee_def_path)
+                .map_err(|e| format!("Unable to read committee definition: {:?}", e))?;
+            let keystore_share = KeystoreShare::from_json_file(&keystore_share_path)
+                .map_err(|e| format!("failed to get keystore share from file, error: {:?}", e))?;
+            let password_path = default_keystore_share_password_path(&keystore_share, &secret_dir);
+            let password = read_password(&password_path)
+                .map_err(|e| format!("Unable to read password {:?}: {}", password_path, e))?;
+            let keypair = keystore_share
+                .keystore
+                .decrypt_keypair(password.as_bytes())
+                .map_err(|e| format!("Unable to decrypt keystore share: {:?}", e))?;
+            (Some((def, keypair)), password.as_bytes().to_vec())
+        };
+        let old_threshold = current
+            .as_ref()
+            .map_or(committee_threshold(old_operator_ids.len()), |(def, _)| def.threshold) as usize;
+        let old_pks: Option<HashMap<u64, PublicKey>> = current.as_ref().map(|(def, _)| {
+            def.operator_ids
+                .iter()
+                .cloned()
+                .zip(def.operator_public_keys.iter().cloned())
+                .collect()
+        });
+        let keypair: Option<&Keypair> = current.as_ref().map(|(_, keypair)| keypair);
+
+        let operator_ids: Vec<u64> = validator.releated_operators.iter().map(|id| *id as u64).collect();
+        let dealers: Vec<u64> = operator_ids
+            .iter()
+            .filter(|id| old_operator_ids.contains(&(**id as u32)))
+            .cloned()
+            .collect();
+        let node_public_keys = operator_public_keys
+            .iter()
+            .map(|pk| Ok(hscrypto::PublicKey(pk.clone().try_into().map_err(|_| "Invalid operator public key".to_string())?)))
+            .collect::<Result<Vec<hscrypto::PublicKey>, String>>()?;
+
+        let node_ = node.read().await;
+        let base_port = node_.config.base_address.port();
+        let base_socket_addresses = get_operator_ips(operator_key_ip_map, &operator_public_keys, base_port).await?;
+        let mut operator_ips = base_socket_addresses.clone();
+        for x in operator_ips.iter_mut() {
+            *x = channel_address(*x, DKG_PORT_OFFSET);
+        }
+        let identity = io_identity(&node_.secret, &operator_ids, &operator_public_keys)?;
+        drop(node_);
+        let session = session_id(&format!("reshare-{}-{}", validator_id, epoch), &operator_ids);
+        let threshold = committee_threshold(operator_ids.len());
+        let (new_keypair, new_pks) = timeout(time_left, async {
+            let io = Arc::new(
+                NetIOCommittee::new(
+                    self_op_id as u64,
+                    base_port + DKG_PORT_OFFSET,
+                    session,
+                    &operator_ids,
+                    &operator_ips,
+                    identity,
+                )
+                .await?,
+            );
+            let dkg = DKG::new(self_op_id as u64, io, threshold as usize);
+            dkg.reshare(keypair, &dealers, old_threshold, &validator_pk, old_pks.as_ref(), &operator_ids)
+                .await
+                .map_err(|e| format!("run reshare failed {:?}", e))
+        })
+        .await
+        .map_err(|_| format!("[VA {}] reshare for epoch {} timed out", validator_id, epoch))??;
+        let new_keypair = new_keypair.ok_or("no reshared key for a member of the committee".to_string())?;
+
+        let keystore = KeystoreBuilder::new(&new_keypair, &password, "".into())
+            .map_err(|e| format!("Unable to create keystore builder: {:?}", e))?
+            .build()
+            .map_err(|e| format!("Unable to build keystore: {:?}", e))?;
+        let new_keystore_share = KeystoreShare::new(keystore, validator_pk.clone(), validator_id, self_op_id as u64);
+        if joining {
+            // left over from an earlier attempt
+            cleanup_password_dir(&secret_dir, &validator_pk, validator_id)?;
+            write_password_to_file(
+                default_keystore_share_password_path(&new_keystore_share, &secret_dir),
+                &password,
+            )
+            .map_err(|e| format!("Unable to write password: {:?}", e))?;
+        }
+        let new_def = OperatorCommitteeDefinition {
+            total: operator_ids.len() as u64,
+            threshold,
+            validator_id,
+            validator_public_key: validator_pk.clone(),
+            operator_public_keys: operator_ids.iter().map(|id| new_pks[id].clone()).collect(),
+            operator_ids,
+            node_public_keys,
+            base_socket_addresses,
+        };
+        stage(&staging, &new_keystore_share, &new_def)?;
+        info!("[VA {}] reshared to operators {:?}, used from epoch {}", validator_id, validator.releated_operators, epoch);
+    }
+
+    wait_for_epoch(&validator_store, epoch).await;
+    if reshare_cancelled(&db, &va_str, &staging, epoch).await? {
+        return Ok(());
+    }
+    if let Some(dir) = keystore_share_path.parent() {
+        create_dir_all(dir).map_err(|e| format!("Unable to create {:?}: {}", dir, e))?;
+    }
+    activate(&staging, &keystore_share_path, &committee_def_path)?;
+
+    // a joining operator starts the validator, the others restart it if it is running
+    let enabled = ValidatorDefinitions::open(&validator_dir)
+        .map_err(|e| format!("Can't open validator definitions {:?}", e))?
+        .as_slice()
+        .iter()
+        .any(|d| d.voting_public_key == validator_pk && d.enabled);
+    if joining || enabled {
+        let keystore_share = KeystoreShare::from_json_file(&keystore_share_path)
+            .map_err(|e| format!("failed to get keystore share from file, error: {:?}", e))?;
+        if !joining {
+            validator_store.stop_validator_keystore(&validator_pk).await;
+        }
+        validator_store
+            .add_validator_keystore_share(
+                keystore_share_path,
+                default_keystore_share_password_path(&keystore_share, &secret_dir),
+                true,
+                None,
+                None,
+                None,
+                None,
+                committee_def_path,
+                keystore_share.master_id,
+                keystore_share.share_id,
+            )
+            .await?;
+    }
+    apply_committee_change(&db, validator).await;
+    File::create(staging.join(ACTIVATED_FILE))
+        .map_err(|e| format!("Unable to mark reshare as activated: {}", e))?;
+    info!("[VA {}] switched to the reshared committee at epoch {}", validator_id, epoch);
+    Ok(())
+}
+
+// a cancelled reshare leaves the current shares in use and drops the staged ones
+async fn reshare_cancelled(db: &Database, validator_pk: &str, staging: &Path, epoch: u64) -> Result<bool, String> {
+    let cancelled = db
+        .is_reshare_cancelled(validator_pk.to_string(), epoch)
+        .await
+        .map_err(|e| format!("Unable to query cancelled reshares: {}", e))?;
+    if cancelled {
+        if staging.exists() {
+            remove_dir_all(staging).map_err(|e| format!("Unable to clean {:?}: {}", staging, e))?;
+        }
+        info!("reshare of validator {} at epoch {} was cancelled", validator_pk, epoch);
+    }
+    Ok(cancelled)
+}
+
+// how long new shares used from `epoch` may take to be staged, none if it is too late for them
+fn time_to_stage<T: EthSpec>(validator_store: &ValidatorStore<SystemTimeSlotClock, T>, epoch: u64) -> Option<Duration> {
+    validator_store
+        .epoch_start(Epoch::new(epoch))
+        .map(|start| start.saturating_sub(unix_now()).saturating_sub(STAGING_MARGIN))
+        .filter(|time_left| !time_left.is_zero())
+}
+
+async fn wait_for_epoch<T: EthSpec>(validator_store: &ValidatorStore<SystemTimeSlotClock, T>, epoch: u64) {
+    if let Some(start) = validator_store.epoch_start(Epoch::new(epoch)) {
+        sleep(start.saturating_sub(unix_now())).await;
+    }
+}
+
+// keep the new share and committee definition until the epoch, replacing older refreshes of
+// the validator that were never activated
+fn stage(staging: &Path, keystore_share: &KeystoreShare, def: &OperatorCommitteeDefinition) -> Result<(), String> {
+    if let Some(validator_refresh_dir) = staging.parent() {
+        if validator_refresh_dir.exists() {
+            remove_dir_all(validator_refresh_dir)
+                .map_err(|e| format!("Unable to clean {:?}: {}", validator_refresh_dir, e))?;
+        }
+    }
+    create_dir_all(staging).map_err(|e| format!("Unable to create {:?}: {}", staging, e))?;
+    def.to_file(staging.join(OPERATOR_COMMITTEE_DEFINITION_FILENAME))
+        .map_err(|e| format!("Unable to save committee definition: {:?}", e))?;
+    let file = File::create(staging.join(VOTING_KEYSTORE_SHARE_FILE))
+        .map_err(|e| format!("Unable to create keystore share file: {}", e))?;
+    keystore_share
+        .to_json_writer(file)
+        .map_err(|e| format!("Unable to write keystore share: {:?}", e))?;
+    File::create(staging.join(STAGED_FILE))
+        .map(|_| ())
+        .map_err(|e| format!("Unable to mark refresh as staged: {}", e))
+}
+
+// replace the share and committee definition in use. Each file is moved only if it is still
+// staged, so an interrupted switch is finished when the command is retried.
+fn activate(staging: &Path, keystore_share_path: &Path, committee_def_path: &Path) -> Result<(), String> {
+    let staged_def = staging.join(OPERATOR_COMMITTEE_DEFINITION_FILENAME);
+    let staged_keystore_share = staging.join(VOTING_KEYSTORE_SHARE_FILE);
+    if staged_keystore_share.exists() {
+        rename(&staged_keystore_share, keystore_share_path)
+            .map_err(|e| format!("Unable to replace keystore share: {}", e))?;
+    }
+    if staged_def.exists() {
+        rename(&staged_def, committee_def_path)
+            .map_err(|e| format!("Unable to replace committee definition: {}", e))?;
+    }
+    Ok(())
+}
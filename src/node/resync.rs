@@ -0,0 +1,332 @@
+use super::abi::{ContractAbi, EventLog};
+use super::contract::{
+    apply_committee_change, check_paid_blocks, confirmation_depth, get_head, log_key, Contract, ContractRecord,
+    Validator, CONTRACT_DATABASE_FILE, CONTRACT_RECORD_FILE, CONTRACT_STORE_FILE,
+    CONTRACT_VA_OPERATORS_CHANGE_EVENT_NAME, CONTRACT_VA_REG_EVENT_NAME,
+};
+use super::db::{carry_over_local_tables, Database};
+use super::node::{cleanup_db, cleanup_password_dir, cleanup_validator_dir};
+use super::utils::{convert_va_pk_to_u64, ToFile};
+use crate::validation::account_utils::default_operator_committee_definition_path;
+use crate::validation::account_utils::validator_definitions::ValidatorDefinitions;
+use crate::validation::operator_committee_definitions::OperatorCommitteeDefinition;
+use crate::DEFAULT_CHANNEL_CAPACITY;
+use hscrypto::PublicKey as OperatorPublicKey;
+use hsutils::monitored_channel::MonitoredChannel;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs::{create_dir_all, remove_dir_all, rename};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use types::PublicKey;
+use web3::types::Log;
+
+// contract state is rebuilt here before it replaces the files in use
+const RESYNC_DIR: &str = "resync";
+// the replaced files are kept here
+const RESYNC_BACKUP_DIR: &str = "resync_backup";
+
+// a validator found in the validator dir
+struct LocalValidator {
+    public_key: PublicKey,
+    validator_id: u64,
+    enabled: bool,
+}
+
+#[derive(Default)]
+pub struct ResyncPlan {
+    // registered on chain for this operator but missing locally
+    pub start: Vec<Validator>,
+    // running locally but past its paid block
+    pub stop: Vec<Validator>,
+    // stopped locally but paid again
+    pub resume: Vec<Validator>,
+    // running locally but removed from the contract, or never registered
+    pub remove: Vec<(PublicKey, u64)>,
+    // the block the state was rebuilt at, and its timestamp
+    pub head: u64,
+    pub timestamp: u64,
+}
+
+impl ResyncPlan {
+    pub fn is_empty(&self) -> bool {
+        self.start.is_empty() && self.stop.is_empty() && self.resume.is_empty() && self.remove.is_empty()
+    }
+
+    pub fn print(&self) {
+        for va in &self.start {
+            println!("start  validator {} 0x{}", va.id, hex::encode(&va.public_key));
+        }
+        for va in &self.stop {
+            println!("stop   validator {} 0x{} (paid until block {})", va.id, hex::encode(&va.public_key), va.paid_block);
+        }
+        for va in &self.resume {
+            println!("resume validator {} 0x{} (paid until block {})", va.id, hex::encode(&va.public_key), va.paid_block);
+        }
+        for (public_key, validator_id) in &self.remove {
+            println!("remove validator {} {}", validator_id, public_key);
+        }
+    }
+}
+
+pub struct Resync {
+    base_dir: PathBuf,
+    validator_dir: PathBuf,
+    secrets_dir: PathBuf,
+}
+
+impl Resync {
+    pub fn new(base_dir: PathBuf, validator_dir: PathBuf, secrets_dir: PathBuf) -> Self {
+        Self {
+            base_dir,
+            validator_dir,
+            secrets_dir,
+        }
+    }
+
+    // rebuild contract state from `from_block`, compare it with the validator dir, and apply the
+    // differences once the operator confirms them
+    pub async fn run(&self, operator_pk: OperatorPublicKey, from_block: u64) -> Result<(), String> {
+        let plan = self.rebuild(operator_pk, from_block).await?;
+        if plan.is_empty() {
+            println!("local validators match the contract");
+        } else {
+            plan.print();
+        }
+        print!("Replace contract state in {:?} and apply these changes? [y/N] ", self.base_dir);
+        let _ = std::io::stdout().flush();
+        let answer = tokio::task::spawn_blocking(|| {
+            let mut answer = String::new();
+            std::io::stdin().lock().read_line(&mut answer).map(|_| answer)
+        })
+        .await
+        .map_err(|e| format!("Can't read confirmation {}", e))?
+        .map_err(|e| format!("Can't read confirmation {}", e))?;
+        if answer.trim().to_lowercase() != "y" {
+            println!("nothing changed, rebuilt state is left in {:?}", self.base_dir.join(RESYNC_DIR));
+            return Ok(());
+        }
+        self.apply(&plan)
+    }
+
+    pub async fn rebuild(&self, operator_pk: OperatorPublicKey, from_block: u64) -> Result<ResyncPlan, String> {
+        let resync_dir = self.base_dir.join(RESYNC_DIR);
+        if resync_dir.exists() {
+            remove_dir_all(&resync_dir).map_err(|e| format!("Can't clean {:?} {}", resync_dir, e))?;
+        }
+        create_dir_all(&resync_dir).map_err(|e| format!("Can't create {:?} {}", resync_dir, e))?;
+        ContractRecord {
+            block_num: from_block,
+            applied_logs: vec![],
+        }
+        .to_file(resync_dir.join(CONTRACT_RECORD_FILE))?;
+
+        let mut contract = Contract::new(&resync_dir, operator_pk)?;
+        contract.construct_filter().await;
+        // commands are only collected for the plan, nothing is started while rebuilding
+        let (tx, mut rx) = MonitoredChannel::new(DEFAULT_CHANNEL_CAPACITY, "resync-command".to_string(), "debug");
+        tokio::spawn(async move { while rx.recv().await.is_some() {} });
+        let tx_log = contract.process_logs(tx.clone());
+
+        let (head, timestamp) = get_head().await.map_err(|e| e.as_str().to_string())?;
+        let confirmed = head.saturating_sub(confirmation_depth());
+        println!("rebuilding contract state from block {} to {}", from_block, confirmed);
+        let logs = contract
+            .replay(confirmed, &tx_log)
+            .await
+            .map_err(|e| e.as_str().to_string())?;
+        // the state is compared with the committees the validators end up with, reshares that are
+        // still running continue after the swap
+        for validator in committee_changes(&contract, &logs)? {
+            apply_committee_change(&contract.db, validator).await;
+        }
+        check_paid_blocks(&contract.db, head, timestamp, &tx).await;
+
+        let chain = chain_validators(&contract.db).await?;
+        let local = self.local_validators()?;
+        let mut plan = ResyncPlan {
+            head,
+            timestamp,
+            ..Default::default()
+        };
+        for (key, va) in &chain {
+            match local.get(key) {
+                None if va.active => plan.start.push(va.clone()),
+                None => {}
+                Some(local) if local.enabled && !va.active => plan.stop.push(va.clone()),
+                Some(local) if !local.enabled && va.active => plan.resume.push(va.clone()),
+                Some(_) => {}
+            }
+        }
+        for (key, local) in &local {
+            if !chain.contains_key(key) {
+                plan.remove.push((local.public_key.clone(), local.validator_id));
+            }
+        }
+
+        // validators to start are registered again by the contract watcher after the swap,
+        // forget their registration logs and move the cursor back to the oldest of them
+        let registrations = registration_logs(&contract, &logs)?;
+        let mut rewind_to: Option<u64> = None;
+        for va in &plan.start {
+            let public_key = hex::encode(&va.public_key);
+            if let Some(log) = registrations.get(&public_key) {
+                if let Some(key) = log_key(log) {
+                    contract.store.delete(key).await;
+                }
+                contract.db.delete_validator(public_key).await;
+                let block = log.block_number.map_or(from_block, |n| n.as_u64());
+                rewind_to = Some(rewind_to.map_or(block, |b| b.min(block)));
+            }
+        }
+        if let Some(block) = rewind_to {
+            // commands are applied in order, so this returns once the deletes are done
+            let _ = contract.db.query_all_validator_address().await;
+            let mut record = contract.record.write().await;
+            record.block_num = block;
+            record
+                .to_file(resync_dir.join(CONTRACT_RECORD_FILE))
+                .map_err(|e| format!("Can't update resync record {}", e))?;
+        }
+        // the rebuilt files are moved by `apply`
+        contract.store.close().await;
+        contract.db.close().await;
+        Ok(plan)
+    }
+
+    fn local_validators(&self) -> Result<HashMap<String, LocalValidator>, String> {
+        let mut validators = HashMap::new();
+        let definitions = match ValidatorDefinitions::open(&self.validator_dir) {
+            Ok(definitions) => definitions,
+            Err(e) => {
+                warn!("Can't open validator definitions {:?}, assume no local validators", e);
+                return Ok(validators);
+            }
+        };
+        for def in definitions.as_slice() {
+            let committee_def_path =
+                default_operator_committee_definition_path(&def.voting_public_key, &self.validator_dir);
+            // only distributed validators are managed by the contract
+            let committee_def = match OperatorCommitteeDefinition::from_file(&committee_def_path) {
+                Ok(committee_def) => committee_def,
+                Err(_) => continue,
+            };
+            validators.insert(
+                hex::encode(def.voting_public_key.serialize()),
+                LocalValidator {
+                    public_key: def.voting_public_key.clone(),
+                    validator_id: committee_def.validator_id,
+                    enabled: def.enabled,
+                },
+            );
+        }
+        Ok(validators)
+    }
+
+    pub fn apply(&self, plan: &ResyncPlan) -> Result<(), String> {
+        // swap in the rebuilt contract files, keeping the old ones
+        let resync_dir = self.base_dir.join(RESYNC_DIR);
+        let backup_dir = self.base_dir.join(RESYNC_BACKUP_DIR);
+        if backup_dir.exists() {
+            remove_dir_all(&backup_dir).map_err(|e| format!("Can't clean {:?} {}", backup_dir, e))?;
+        }
+        create_dir_all(&backup_dir).map_err(|e| format!("Can't create {:?} {}", backup_dir, e))?;
+        let current_db = self.base_dir.join(CONTRACT_DATABASE_FILE);
+        if current_db.exists() {
+            carry_over_local_tables(&current_db, &resync_dir.join(CONTRACT_DATABASE_FILE), plan.head, plan.timestamp)
+                .map_err(|e| format!("Can't carry over local tables of {:?} {}", current_db, e))?;
+        }
+        for name in [CONTRACT_DATABASE_FILE, CONTRACT_RECORD_FILE, CONTRACT_STORE_FILE] {
+            let current = self.base_dir.join(name);
+            if current.exists() {
+                rename(&current, backup_dir.join(name)).map_err(|e| format!("Can't back up {:?} {}", current, e))?;
+            }
+            rename(resync_dir.join(name), &current).map_err(|e| format!("Can't replace {:?} {}", current, e))?;
+        }
+        let _ = remove_dir_all(&resync_dir);
+        info!("contract state replaced, previous files are in {:?}", backup_dir);
+
+        if plan.stop.is_empty() && plan.resume.is_empty() && plan.remove.is_empty() {
+            return Ok(());
+        }
+        let mut definitions = ValidatorDefinitions::open(&self.validator_dir)
+            .map_err(|e| format!("Can't open validator definitions {:?}", e))?;
+        for def in definitions.as_mut_slice() {
+            let key = def.voting_public_key.serialize();
+            if plan.stop.iter().any(|va| va.public_key == key) {
+                def.enabled = false;
+            } else if plan.resume.iter().any(|va| va.public_key == key) {
+                def.enabled = true;
+            }
+        }
+        definitions.retain(|def| !plan.remove.iter().any(|(public_key, _)| *public_key == def.voting_public_key));
+        definitions
+            .save(&self.validator_dir)
+            .map_err(|e| format!("Can't save validator definitions {:?}", e))?;
+        for (public_key, validator_id) in &plan.remove {
+            cleanup_db(&self.base_dir, *validator_id)?;
+            cleanup_validator_dir(&self.validator_dir, public_key, *validator_id)?;
+            cleanup_password_dir(&self.secrets_dir, public_key, *validator_id)?;
+        }
+        println!("resync applied, validators to start are registered when the node runs");
+        Ok(())
+    }
+}
+
+async fn chain_validators(db: &Database) -> Result<HashMap<String, Validator>, String> {
+    let mut validators = HashMap::new();
+    let owners = db
+        .query_all_validator_address()
+        .await
+        .map_err(|e| format!("Can't query validator owners {}", e))?;
+    for owner in owners {
+        for va in db
+            .query_validator_by_address(owner)
+            .await
+            .map_err(|e| format!("Can't query validators of {} {}", owner, e))?
+        {
+            validators.insert(hex::encode(&va.public_key), va);
+        }
+    }
+    Ok(validators)
+}
+
+// the latest registration log of each validator public key
+fn registration_logs(contract: &Contract, logs: &[Log]) -> Result<HashMap<String, Log>, String> {
+    let abi = ContractAbi::from_config(&contract.config).map_err(|e| e.as_str().to_string())?;
+    let event = abi.event(CONTRACT_VA_REG_EVENT_NAME).map_err(|e| e.as_str().to_string())?;
+    let topic = event.signature();
+    let mut registrations = HashMap::new();
+    for log in logs.iter().filter(|log| log.topics.first() == Some(&topic)) {
+        let public_key = EventLog::decode(event, log.clone())
+            .and_then(|decoded| decoded.bytes("publicKey"))
+            .map_err(|e| e.as_str().to_string())?;
+        registrations.insert(hex::encode(public_key), log.clone());
+    }
+    Ok(registrations)
+}
+
+// the new committees of validator operators change logs, in chain order. Paid blocks of
+// validators that are new to this operator are refreshed from the contract later.
+fn committee_changes(contract: &Contract, logs: &[Log]) -> Result<Vec<Validator>, String> {
+    let abi = ContractAbi::from_config(&contract.config).map_err(|e| e.as_str().to_string())?;
+    let event = match abi.optional_event(CONTRACT_VA_OPERATORS_CHANGE_EVENT_NAME) {
+        Some(event) => event,
+        None => return Ok(vec![]),
+    };
+    let topic = event.signature();
+    let mut changes = vec![];
+    for log in logs.iter().filter(|log| log.topics.first() == Some(&topic)) {
+        let decoded = EventLog::decode(event, log.clone()).map_err(|e| e.as_str().to_string())?;
+        let public_key = decoded.bytes("publicKey").map_err(|e| e.as_str().to_string())?;
+        changes.push(Validator {
+            id: convert_va_pk_to_u64(&public_key),
+            owner_address: decoded.address("ownerAddress").map_err(|e| e.as_str().to_string())?,
+            releated_operators: decoded.u32_array("operatorIds").map_err(|e| e.as_str().to_string())?,
+            public_key: public_key.try_into().map_err(|_| "Invalid validator public key".to_string())?,
+            active: true,
+            paid_block: 0,
+        });
+    }
+    Ok(changes)
+}
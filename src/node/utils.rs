@@ -6,7 +6,7 @@ use std::net::{IpAddr, SocketAddr, Ipv4Addr};
 use std::sync::Arc;
 use crate::node::contract::OperatorPublicKeys;
 use log::{error, info, warn};
-use network::{ReliableSender, DvfMessage, VERSION};
+use network::{Channel, ReliableSender, DvfMessage, VERSION};
 use super::config::BOOT_SOCKETADDR;
 use std::collections::HashMap;
 use serde::de::DeserializeOwned;
@@ -92,13 +92,13 @@ pub async fn request_to_web_server<T: Serialize>(body: T, url_str: &str) -> Resu
 }
 
 pub async fn get_operator_ips(
-    operator_key_ip_map: Arc<RwLock<HashMap<String, IpAddr>>>,
+    operator_key_ip_map: Arc<RwLock<HashMap<String, SocketAddr>>>,
     operator_public_keys: &OperatorPublicKeys,
     base_port: u16
 ) -> Result<Vec<SocketAddr>, String> {
     let key_ip_map = operator_key_ip_map.read().await;
     let mut ip_not_founds: Vec<usize> = vec![];
-    let mut operator_base_address: Vec<Option<IpAddr>> = operator_public_keys
+    let mut operator_base_address: Vec<Option<SocketAddr>> = operator_public_keys
         .iter()
         .enumerate()
         .map(|(i, op_pk)| {
@@ -109,24 +109,31 @@ pub async fn get_operator_ips(
                     ip_not_founds.push(i);
                     None
                 },
-                |ip| {
-                    Some(ip.clone())
+                |address| {
+                    Some(address.clone())
                 },
             )
         })
         .collect();
     for index in ip_not_founds {
-        operator_base_address[index] = query_ip_from_boot(&operator_public_keys[index]).await;
+        operator_base_address[index] = query_ip_from_boot(&operator_public_keys[index], base_port).await;
     }
     if operator_base_address.iter().any(|x| x.is_none()) {
         return Err("Insufficient operators discovered".to_string());
     }
     Ok(operator_base_address
         .into_iter()
-        .map(|x| SocketAddr::new(x.unwrap(), base_port))
+        .map(|x| x.unwrap())
         .collect())
 }
 
+// the number of operators that have to sign for a validator of a committee with `total`
+// operators. It is the hotstuff quorum of the committee, so whatever the committee agrees on can
+// also be signed: 3 of 4, 5 of 7, 7 of 10 and 9 of 13.
+pub fn committee_threshold(total: usize) -> u64 {
+    (2 * total / 3 + 1) as u64
+}
+
 pub fn convert_va_pk_to_u64(pk: &[u8]) -> u64 {
     let mut little_endian: [u8; 8] = [0; 8];
     let mut i = 0;
@@ -148,9 +155,11 @@ pub fn convert_address_to_withdraw_crendentials (address: H160) -> [u8;32] {
     credentials
 }
 
-pub async fn query_ip_from_boot(op_pk: &Vec<u8>) -> Option<IpAddr>{
+// the boot node replies with the ip of the operator, followed by its base port if the operator
+// advertises one. Otherwise the operator is assumed to use the same base port as us.
+pub async fn query_ip_from_boot(op_pk: &Vec<u8>, base_port: u16) -> Option<SocketAddr>{
     let dvf_message = DvfMessage { 
-        version: VERSION, validator_id: 0, message: op_pk.to_vec() 
+        version: VERSION, validator_id: 0, channel: Channel::Discovery, message: op_pk.to_vec() 
     };
     let timeout_mill :u64 = 3000;
     let serialized_msg = bincode::serialize(&dvf_message).unwrap();
@@ -160,16 +169,18 @@ pub async fn query_ip_from_boot(op_pk: &Vec<u8>) -> Option<IpAddr>{
     let result = timeout(Duration::from_millis(timeout_mill), receiver).await;
 
     let base64_pk = base64::encode(op_pk);
-    let ipaddr: Option<IpAddr> = match result {
+    let address: Option<SocketAddr> = match result {
         Ok(output) => {
             match output {
                 Ok(data) => {
-                    if data.len() != 4 {
+                    if data.len() != 4 && data.len() != 6 {
                         error!("can't find ip for op {} from boot node", &base64_pk);
                         None
                     } else {
                         info!("Get ip from server! pk {}, ip {:?}", &base64_pk, data);
-                        Some(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])))
+                        let ip = IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]));
+                        let port = if data.len() == 6 { u16::from_be_bytes([data[4], data[5]]) } else { base_port };
+                        Some(SocketAddr::new(ip, port))
                     }
                 },
                 Err(_) => {
@@ -183,5 +194,40 @@ pub async fn query_ip_from_boot(op_pk: &Vec<u8>) -> Option<IpAddr>{
             None
         }
     };
-    ipaddr
-}
\ No newline at end of file
+    address
+}
+// serde only derives fixed size arrays up to 32 bytes, key arrays go through a vec
+pub mod byte_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        bytes.to_vec().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| D::Error::invalid_length(bytes.len(), &"a fixed size byte array"))
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, const N: usize>(bytes: &Option<[u8; N]>, serializer: S) -> Result<S::Ok, S::Error> {
+            match bytes {
+                Some(bytes) => serializer.serialize_some(&bytes.to_vec()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+            deserializer: D,
+        ) -> Result<Option<[u8; N]>, D::Error> {
+            #[derive(serde_derive::Deserialize)]
+            struct Wrapper<const N: usize>(#[serde(with = "super")] [u8; N]);
+            Ok(Option::<Wrapper<N>>::deserialize(deserializer)?.map(|Wrapper(bytes)| bytes))
+        }
+    }
+}
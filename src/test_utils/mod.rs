@@ -1,3 +1,5 @@
+pub mod mock_contract;
+
 use crate::crypto::{ThresholdSignature};
 // use validator_dir::{ValidatorDir, BuilderError};
 // use std::path::{Path, PathBuf};
@@ -96,11 +96,62 @@ pub fn cli_app<'a, 'b>() -> App<'a, 'b> {
                 )
                 .takes_value(true)
         )
+        .arg(
+            Arg::with_name("port-mode")
+                .long("port-mode")
+                .value_name("PORT_MODE")
+                .help("How the node spreads its channels over TCP ports: per-port (default) listens \
+                       on a port per channel, dual also serves all channels on BASE_PORT and \
+                       addresses operators there, single only listens on BASE_PORT. Move all \
+                       operators to dual before any of them switches to single.")
+                .possible_values(&["per-port", "dual", "single"])
+                .takes_value(true)
+        )
         .arg(
             Arg::with_name("ws-url")
             .long("ws-url")
             .value_name("WS_URL")
-            .help("web socket url of infura to listen contract event")
+            .help("web socket url of infura to listen contract event. Deprecated, use --execution-endpoints.")
+            .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("execution-endpoints")
+            .long("execution-endpoints")
+            .value_name("EXECUTION_ENDPOINTS")
+            .help("Comma-separated websocket (ws/wss) or http(s) urls of execution layer rpc endpoints \
+                   used to read the SafeStake contracts. Unhealthy endpoints are failed over in order.")
+            .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("confirmation-depth")
+            .long("confirmation-depth")
+            .value_name("BLOCKS")
+            .help("Number of blocks a contract event must be buried under before it is processed. \
+                   Default is 12.")
+            .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("resync")
+            .long("resync")
+            .help("Rebuild the contract database from the contract history, print how local validators \
+                   differ from it and exit. Changes are applied only after confirmation.")
+            .takes_value(false)
+        )
+        .arg(
+            Arg::with_name("resync-from-block")
+            .long("resync-from-block")
+            .value_name("BLOCK")
+            .help("Block to replay contract history from in resync mode, usually the contract deploy block. \
+                   Default is 0.")
+            .takes_value(true)
+            .requires("resync")
+        )
+        .arg(
+            Arg::with_name("share-refresh-epochs")
+            .long("share-refresh-epochs")
+            .value_name("EPOCHS")
+            .help("Proactively refresh the key shares of distributed validators every this many epochs. \
+                   All operators of a committee should use the same value. Disabled by default.")
             .takes_value(true)
         )
         .arg(
@@ -16,8 +16,9 @@ use std::fs;
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 use types::{Address, GRAFFITI_BYTES_LEN};
-use crate::node::config::{NodeConfig,API_ADDRESS, BOOT_ENR};
-use crate::node::contract::{DEFAULT_TRANSPORT_URL, SELF_OPERATOR_ID, NETWORK_CONTRACT, REGISTRY_CONTRACT};
+use crate::node::config::{NodeConfig, PortMode, API_ADDRESS, BOOT_ENR, PORT_MODE};
+use crate::node::contract::{SELF_OPERATOR_ID, NETWORK_CONTRACT, REGISTRY_CONTRACT, CONFIRMATION_DEPTH};
+use crate::node::execution::{ExecutionClients, EXECUTION_CLIENTS};
 use dvf_version::{ROOT_VERSION};
 use dvf_directory::{get_default_base_dir};
 
@@ -76,6 +77,10 @@ pub struct Config {
 
     /// Used for 
     pub dvf_node_config: NodeConfig,
+
+    /// If set, rebuild contract state from this block and reconcile local validators instead of
+    /// running the validator client.
+    pub resync_from_block: Option<u64>,
 }
 
 impl Default for Config {
@@ -116,6 +121,7 @@ impl Default for Config {
             disable_run_on_all: false,
             
             dvf_node_config: NodeConfig::default(), 
+            resync_from_block: None,
         }
     }
 }
@@ -199,10 +205,47 @@ impl Config {
             API_ADDRESS.set(api_str).unwrap();
         }
 
+        let mut execution_endpoints: Vec<String> = vec![];
+        if let Some(endpoints) = parse_optional::<String>(cli_args, "execution-endpoints")? {
+            execution_endpoints.extend(endpoints.split(',').map(|url| url.trim().to_string()));
+        }
         if cli_args.value_of("ws-url").is_some() {
             let ws_transport_url_str: String = parse_required(cli_args, "ws-url")?;
             info!(log, "read ws-url"; "ws-url" => &ws_transport_url_str);
-            DEFAULT_TRANSPORT_URL.set(ws_transport_url_str).unwrap();
+            if !execution_endpoints.contains(&ws_transport_url_str) {
+                execution_endpoints.push(ws_transport_url_str);
+            }
+        }
+        if execution_endpoints.is_empty() {
+            error!(log, "can't read execution endpoints, existing;" );
+            return Err("can't read execution endpoints".to_string());
+        }
+        info!(log, "read execution endpoints"; "count" => execution_endpoints.len());
+        let _ = EXECUTION_CLIENTS.set(ExecutionClients::new(execution_endpoints));
+
+        if cli_args.value_of("confirmation-depth").is_some() {
+            let confirmation_depth: u64 = parse_required(cli_args, "confirmation-depth")?;
+            info!(log, "read confirmation depth"; "confirmation-depth" => confirmation_depth);
+            CONFIRMATION_DEPTH.set(confirmation_depth).unwrap();
+        }
+
+        if let Some(port_mode) = parse_optional::<PortMode>(cli_args, "port-mode")? {
+            info!(log, "read port mode"; "port-mode" => format!("{:?}", port_mode));
+            PORT_MODE.set(port_mode).unwrap();
+        }
+
+        if cli_args.is_present("resync") {
+            let from_block = parse_optional::<u64>(cli_args, "resync-from-block")?.unwrap_or(0);
+            info!(log, "resync mode"; "from-block" => from_block);
+            config.resync_from_block = Some(from_block);
+        }
+
+        if let Some(epochs) = parse_optional::<u64>(cli_args, "share-refresh-epochs")? {
+            if epochs == 0 {
+                return Err("share-refresh-epochs should be greater than 0".to_string());
+            }
+            info!(log, "share refresh"; "epochs" => epochs);
+            config.dvf_node_config.share_refresh_epochs = Some(epochs);
         }
 
         if cli_args.value_of("id").is_some() {
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use crate::utils::error::DvfError;
 use crate::validation::operator::{TOperator};
+use crate::validation::partial_signatures::{PartialSignature, PartialSignatures};
 use types::{Hash256, Signature, PublicKey};
 use tokio::sync::{RwLock};
 use tokio::sync::mpsc::{Receiver};
@@ -16,6 +17,10 @@ pub trait TOperatorCommittee : Send {
     async fn add_operator(&mut self, operator_id: u64, operator: Arc<RwLock<dyn TOperator>>); 
     async fn consensus(&self, msg: Hash256) -> Result<(), DvfError>;
     async fn sign(&self, msg: Hash256) -> Result<(Signature, Vec<u64>), DvfError>;
+    /// Push our `share` to the operators in `aggregators`.
+    async fn push(&self, share: &PartialSignature, aggregators: &[u64]);
+    /// The shares the operators pushed to us.
+    fn partial_signatures(&self) -> Arc<PartialSignatures>;
     async fn get_leader(&self, nonce: u64) -> u64;
     fn get_validator_pk(&self) -> String;
     fn threshold(&self) -> usize;
@@ -52,6 +57,14 @@ where
         self.cmt.sign(msg).await
     }
 
+    pub async fn push(&self, share: &PartialSignature, aggregators: &[u64]) {
+        self.cmt.push(share, aggregators).await
+    }
+
+    pub fn partial_signatures(&self) -> Arc<PartialSignatures> {
+        self.cmt.partial_signatures()
+    }
+
     pub async fn get_leader(&self, nonce: u64) -> u64 {
         self.cmt.get_leader(nonce).await
     }
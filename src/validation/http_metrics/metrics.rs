@@ -145,6 +145,18 @@ lazy_static::lazy_static! {
         "sync_eth2_fallback_connected",
         "Set to 1 if connected to atleast one synced eth2 fallback node, otherwise set to 0",
     );
+    /*
+     * Operator network metrics
+     */
+    pub static ref PEER_VERSION: Result<IntGaugeVec> = try_create_int_gauge_vec(
+        "dvf_peer_version",
+        "The dvf version each operator ran when it last connected to this node",
+        &["peer"]
+    );
+    pub static ref PRE_HANDSHAKE_PEERS: Result<IntGauge> = try_create_int_gauge(
+        "dvf_pre_handshake_peers_total",
+        "Number of connections refused because the peer runs a version from before the handshake"
+    );
     /*
      * Signing Metrics
      */
@@ -195,6 +207,11 @@ pub async fn gather_prometheus_metrics<T: EthSpec>(
         }
     }
 
+    for (peer, version) in network::peer_versions() {
+        set_int_gauge(&PEER_VERSION, &[&base64::encode(peer)], version as i64);
+    }
+    set_gauge(&PRE_HANDSHAKE_PEERS, network::pre_handshake_peers() as i64);
+
     warp_utils::metrics::scrape_health_metrics();
 
     encoder
@@ -3,14 +3,15 @@ use std::sync::{Arc};
 use crate::validation::{
     generic_operator_committee::{TOperatorCommittee},
     operator::{TOperator},
+    partial_signatures::{PartialSignature, PartialSignatures},
 };
 use crate::crypto::ThresholdSignature;
 use crate::utils::error::DvfError;
 use bls::{Hash256, Signature, PublicKey};
 use tokio::sync::{RwLock};
 use tokio::sync::mpsc::{Receiver};
-use futures::future::join_all;
-use log::{info};
+use futures::stream::{FuturesUnordered, StreamExt};
+use log::{info, warn};
 use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 use async_trait::async_trait;
@@ -28,6 +29,7 @@ pub struct HotstuffOperatorCommittee {
     operators: RwLock<HashMap<u64, Arc<RwLock<dyn TOperator>>>>,
     threshold_: usize,
     consensus_notifications: Arc<RwLock<HashMap<Hash256, Arc<Notify>>>>,
+    partial_signatures: Arc<PartialSignatures>,
     thread_handle: JoinHandle<()>,
 }
 
@@ -73,6 +75,7 @@ impl TOperatorCommittee for HotstuffOperatorCommittee {
             operators: <_>::default(),
             threshold_: t,
             consensus_notifications,
+            partial_signatures: Arc::new(PartialSignatures::new()),
             thread_handle,
         }
     }
@@ -82,7 +85,7 @@ impl TOperatorCommittee for HotstuffOperatorCommittee {
     }
 
     async fn add_operator(&mut self, operator_id: u64, operator: Arc<RwLock<dyn TOperator>>) {
-
+        self.partial_signatures.add_operator(operator_id, operator.read().await.public_key());
         self.operators.write()
             .await
             .insert(operator_id, operator);
@@ -142,32 +145,85 @@ impl TOperatorCommittee for HotstuffOperatorCommittee {
         // Run consensus protocol 
         self.consensus(msg).await?;
 
+        // Shares are taken as they come: operators that push theirs resolve at once, the others
+        // are asked for it. The slowest operators are dropped once `threshold` shares are in.
         let operators = &self.operators.read().await;
-        let signing_futs = operators.keys().map(|operator_id| async move {
-            let operator = operators.get(operator_id).unwrap().read().await; 
-            operator.sign(msg)
-                .await
-                .map(|x| (operator_id.clone(), operator.public_key(), x))
-            
-        });
-        let results = join_all(signing_futs)
-            .await
-            .into_iter()
-            .flatten()
-            .collect::<Vec<(u64, PublicKey, Signature)>>();
+        let mut signing_futs = operators
+            .iter()
+            .map(|(operator_id, operator)| async move {
+                let operator = operator.read().await;
+                (*operator_id, operator.public_key(), operator.sign(msg).await)
+            })
+            .collect::<FuturesUnordered<_>>();
 
-        let ids = results.iter().map(|x| x.0).collect::<Vec<u64>>();
-        let pks = results.iter().map(|x| &x.1).collect::<Vec<&PublicKey>>();
-        let sigs = results.iter().map(|x| &x.2).collect::<Vec<&Signature>>();
+        let threshold_sig = ThresholdSignature::new(self.threshold());
+        let mut results: Vec<(u64, Signature)> = Vec::new();
+        let mut unverified: Vec<(u64, PublicKey, Signature)> = Vec::new();
+        while let Some((operator_id, pk, result)) = signing_futs.next().await {
+            let sig = match result {
+                Ok(sig) => sig,
+                Err(e) => {
+                    warn!("No signature from operator {}: {:?}", operator_id, e);
+                    continue;
+                }
+            };
+            // pushed shares were verified when they arrived, the others are checked together
+            // once there are enough shares
+            if self.partial_signatures.get(msg, operator_id).as_ref() == Some(&sig) {
+                results.push((operator_id, sig));
+            } else {
+                unverified.push((operator_id, pk, sig));
+            }
+            if results.len() + unverified.len() < self.threshold() {
+                continue;
+            }
+            if !unverified.is_empty() {
+                let sigs = unverified.iter().map(|x| &x.2).collect::<Vec<&Signature>>();
+                let pks = unverified.iter().map(|x| &x.1).collect::<Vec<&PublicKey>>();
+                // only if the batch fails each share is checked on its own to find the faulty operators
+                if !threshold_sig.batch_verify(&sigs[..], &pks[..], msg) {
+                    unverified.retain(|(operator_id, pk, sig)| {
+                        let valid = sig.verify(pk, msg);
+                        if !valid {
+                            warn!("Invalid signature from operator {}", operator_id);
+                        }
+                        valid
+                    });
+                }
+                results.extend(unverified.drain(..).map(|(operator_id, _, sig)| (operator_id, sig)));
+            }
+            if results.len() >= self.threshold() {
+                break;
+            }
+        }
+        // cancel the requests that are still running
+        drop(signing_futs);
 
-        info!("Received {} signatures", sigs.len());
+        info!("Received {} valid signatures", results.len());
+        if results.len() < self.threshold() {
+            return Err(DvfError::InsufficientValidSignatures { got: results.len(), expected: self.threshold() });
+        }
 
-        let threshold_sig = ThresholdSignature::new(self.threshold());
-        let sig = threshold_sig.threshold_aggregate(&sigs[..], &pks[..], &ids[..], msg)?;
+        let ids = results.iter().map(|x| x.0).collect::<Vec<u64>>();
+        let sigs = results.iter().map(|x| &x.1).collect::<Vec<&Signature>>();
+        let sig = threshold_sig.unsafe_aggregate(&sigs[..], &ids[..]);
 
         Ok((sig, ids))
     }
 
+    async fn push(&self, share: &PartialSignature, aggregators: &[u64]) {
+        let operators = self.operators.read().await;
+        for operator_id in aggregators {
+            if let Some(operator) = operators.get(operator_id) {
+                operator.read().await.push(share).await;
+            }
+        }
+    }
+
+    fn partial_signatures(&self) -> Arc<PartialSignatures> {
+        self.partial_signatures.clone()
+    }
+
     fn get_validator_pk(&self) -> String {
         self.validator_public_key.as_hex_string()
     }
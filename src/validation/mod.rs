@@ -64,7 +64,7 @@ use tokio::{
 use types::{EthSpec, Hash256};
 use validator_store::ValidatorStore;
 use crate::node::node::Node;
-use dvf_version::{VERSION};
+use dvf_version::{version_string, MAX_SUPPORTED_VERSION, MIN_SUPPORTED_VERSION, VERSION};
 
 /// The interval between attempts to contact the beacon node during startup.
 const RETRY_DELAY: Duration = Duration::from_secs(2);
@@ -178,7 +178,8 @@ impl<T: EthSpec> ProductionValidatorClient<T> {
         info!(
             log,
             "Starting validator client";
-            "dvf version: " => format!("v{}", VERSION),
+            "dvf version: " => version_string(VERSION),
+            "supported versions" => format!("{} to {}", version_string(MIN_SUPPORTED_VERSION), version_string(MAX_SUPPORTED_VERSION)),
             "beacon_nodes" => format!("{:?}", &config.beacon_nodes),
             "validator_dir" => format!("{:?}", config.validator_dir),
         );
@@ -814,6 +815,7 @@ pub mod generic_operator_committee;
 pub mod impls;
 pub mod operator_committee_definitions;
 pub mod operator_committees;
+pub mod partial_signatures;
 
 macro_rules! define_mod {
     ($name: ident, $mod: path) => {
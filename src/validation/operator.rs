@@ -1,12 +1,14 @@
 use types::{Hash256, Signature, Keypair, PublicKey};
 use std::sync::Arc;
 use crate::utils::error::DvfError;
-use network::{ReliableSender, SimpleSender, DvfMessage, VERSION};
+use crate::validation::partial_signatures::{PartialSignature, PartialSignatures};
+use network::{Channel, ReliableSender, SimpleSender, DvfMessage, VERSION};
 use std::net::SocketAddr;
 use bytes::Bytes;
 use downcast_rs::DowncastSync;
 use std::time::Duration;
 use tokio::time::{timeout, sleep_until, Instant};
+use futures::pin_mut;
 use log::{info, warn};
 use async_trait::async_trait;
 
@@ -18,6 +20,8 @@ pub trait TOperator: DowncastSync + Sync + Send {
     async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError>; 
     fn public_key(&self) -> PublicKey;
     async fn propose(&self, msg: Hash256);
+    /// Hand our share of a signing root to this operator, if it aggregates the duty.
+    async fn push(&self, share: &PartialSignature);
 }
 impl_downcast!(sync TOperator);
 
@@ -42,9 +46,11 @@ impl TOperator for LocalOperator {
 
     async fn propose(&self, msg: Hash256) {
         info!("[Dvf {}/{}] Proposing msg {}", self.operator_id, self.validator_id, msg);
-        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, message: msg.to_fixed_bytes().to_vec()};
+        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, channel: Channel::Transaction, message: msg.to_fixed_bytes().to_vec()};
         self.network.send(self.transaction_address, Bytes::from(bincode::serialize(&dvf_message).unwrap())).await;
     }
+
+    async fn push(&self, _share: &PartialSignature) { }
 }
 
 impl LocalOperator {
@@ -59,20 +65,76 @@ impl LocalOperator {
     }
 }
 
+/// How long an aggregator waits for an operator to push its share before asking for it. Operators
+/// on versions from before shares were pushed only answer requests.
+const PUSH_TIMEOUT: Duration = Duration::from_millis(400);
+/// How long a pushed share may wait for the aggregator's acknowledgement.
+const PUSH_ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub struct RemoteOperator {
     pub validator_id: u64,
     pub operator_id: u64,
     pub operator_public_key: PublicKey,
     pub signature_address: SocketAddr,
+    partial_signatures: Arc<PartialSignatures>,
     network: ReliableSender,
 }
 
 #[async_trait]
 impl TOperator for RemoteOperator {
     async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> { 
+        let pushed = self.partial_signatures.wait_for(msg, self.operator_id);
+        pin_mut!(pushed);
+        if let Ok(signature) = timeout(PUSH_TIMEOUT, &mut pushed).await {
+            info!("Received a signature from operator {}/{} ({:?})", self.operator_id, self.validator_id, self.signature_address);
+            return Ok(signature);
+        }
+        tokio::select! {
+            signature = &mut pushed => {
+                info!("Received a signature from operator {}/{} ({:?})", self.operator_id, self.validator_id, self.signature_address);
+                Ok(signature)
+            }
+            result = self.pull(msg) => result,
+        }
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.operator_public_key.clone()
+    }
+
+    async fn propose(&self, _msg: Hash256) { }
+
+    async fn push(&self, share: &PartialSignature) {
+        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, channel: Channel::Signature, message: bincode::serialize(share).unwrap()};
+        let receiver = self.network.send(self.signature_address, Bytes::from(bincode::serialize(&dvf_message).unwrap())).await;
+        let (operator_id, validator_id, signature_address) = (self.operator_id, self.validator_id, self.signature_address);
+        // the share is sent again until the aggregator acknowledges it, or the wait is over
+        tokio::spawn(async move {
+            match timeout(PUSH_ACK_TIMEOUT, receiver).await {
+                Ok(Ok(_)) => info!("Pushed a signature to operator {}/{} ({:?})", operator_id, validator_id, signature_address),
+                _ => warn!("Failed to push a signature to operator {}/{} ({:?})", operator_id, validator_id, signature_address),
+            }
+        });
+    }
+}
+
+impl RemoteOperator {
+    pub fn new(validator_id: u64, operator_id: u64, operator_public_key: PublicKey, signature_address: SocketAddr, partial_signatures: Arc<PartialSignatures>) -> Self {
+        Self {
+            validator_id,
+            operator_id,
+            operator_public_key,
+            signature_address, 
+            partial_signatures,
+            network: ReliableSender::new(),
+        }
+    }
+
+    /// Ask the operator for its share until it has one.
+    async fn pull(&self, msg: Hash256) -> Result<Signature, DvfError> {
         let n_try: u64 = 3;
         let timeout_mill :u64 = 400;
-        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, message: msg.to_fixed_bytes().to_vec()};
+        let dvf_message = DvfMessage { version: VERSION, validator_id: self.validator_id, channel: Channel::Signature, message: msg.to_fixed_bytes().to_vec()};
         let serialize_msg = bincode::serialize(&dvf_message).unwrap();
         for i in 0..n_try {
             let next_try_instant = Instant::now() + Duration::from_millis(timeout_mill);
@@ -85,6 +147,8 @@ impl TOperator for RemoteOperator {
                             match bincode::deserialize::<Signature>(&data) {
                                 Ok(bls_signature) =>{
                                     info!("Received a signature from operator {}/{} ({:?})", self.operator_id, self.validator_id, self.signature_address);
+                                    let share = PartialSignature { operator_id: self.operator_id, root: msg, signature: bls_signature.clone() };
+                                    self.partial_signatures.insert(share)?;
                                     return Ok(bls_signature);
                                 }
                                 Err(_) => {
@@ -108,25 +172,4 @@ impl TOperator for RemoteOperator {
         warn!("Failed to receive a signature from operator {}/{} ({:?})", self.operator_id, self.validator_id, self.signature_address);
         Err(DvfError::Unknown)
     }
-
-    fn public_key(&self) -> PublicKey {
-        self.operator_public_key.clone()
-    }
-
-    async fn propose(&self, _msg: Hash256) { }
-
 }
-
-impl RemoteOperator {
-    pub fn new(validator_id: u64, operator_id: u64, operator_public_key: PublicKey, signature_address: SocketAddr) -> Self {
-        Self {
-            validator_id,
-            operator_id,
-            operator_public_key,
-            signature_address, 
-            network: ReliableSender::new(),
-        }
-    }
-
-}
-
@@ -164,6 +164,9 @@ impl OperatorCommitteeDefinitions {
             if defs.0[i].base_socket_addresses.len() != (defs.0[i].total as usize) {
                 return Err(Error::InvalidFile);
             }
+            if defs.0[i].threshold == 0 || defs.0[i].threshold > defs.0[i].total {
+                return Err(Error::InvalidFile);
+            }
         }
         Ok(defs)
     }
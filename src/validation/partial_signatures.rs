@@ -0,0 +1,102 @@
+use crate::utils::error::DvfError;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use types::{Hash256, PublicKey, Signature};
+
+/// Signing roots whose shares are kept, the oldest ones are dropped first.
+const MAX_ROOTS: usize = 128;
+
+/// The share of a signing root an operator pushes to the aggregators of the duty.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PartialSignature {
+    pub operator_id: u64,
+    pub root: Hash256,
+    pub signature: Signature,
+}
+
+#[derive(Default)]
+struct Shares {
+    by_root: HashMap<Hash256, HashMap<u64, Signature>>,
+    roots: VecDeque<Hash256>,
+}
+
+/// Valid shares of the operators of a committee, by signing root.
+#[derive(Default)]
+pub struct PartialSignatures {
+    public_keys: Mutex<HashMap<u64, PublicKey>>,
+    shares: Mutex<Shares>,
+    inserted: Notify,
+}
+
+impl PartialSignatures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept the shares of `operator_id`, which verify with `public_key`.
+    pub fn add_operator(&self, operator_id: u64, public_key: PublicKey) {
+        self.public_keys.lock().unwrap().insert(operator_id, public_key);
+    }
+
+    /// Buffer `share` if it is a valid signature of its root by its operator.
+    pub fn insert(&self, share: PartialSignature) -> Result<(), DvfError> {
+        let public_key = self
+            .public_keys
+            .lock()
+            .unwrap()
+            .get(&share.operator_id)
+            .cloned()
+            .ok_or(DvfError::InvalidOperatorId { id: share.operator_id })?;
+        if !share.signature.verify(&public_key, share.root) {
+            return Err(DvfError::InvalidSignatureShare { id: share.operator_id });
+        }
+
+        {
+            let mut shares = self.shares.lock().unwrap();
+            if !shares.by_root.contains_key(&share.root) {
+                if shares.roots.len() >= MAX_ROOTS {
+                    if let Some(oldest) = shares.roots.pop_front() {
+                        shares.by_root.remove(&oldest);
+                    }
+                }
+                shares.roots.push_back(share.root);
+            }
+            shares
+                .by_root
+                .entry(share.root)
+                .or_default()
+                .insert(share.operator_id, share.signature);
+        }
+        self.inserted.notify_waiters();
+        Ok(())
+    }
+
+    pub fn get(&self, root: Hash256, operator_id: u64) -> Option<Signature> {
+        self.shares
+            .lock()
+            .unwrap()
+            .by_root
+            .get(&root)
+            .and_then(|shares| shares.get(&operator_id))
+            .cloned()
+    }
+
+    /// The shares of `root` received so far, by operator.
+    pub fn shares(&self, root: Hash256) -> HashMap<u64, Signature> {
+        self.shares.lock().unwrap().by_root.get(&root).cloned().unwrap_or_default()
+    }
+
+    /// Wait for the share of `root` by `operator_id`.
+    pub async fn wait_for(&self, root: Hash256, operator_id: u64) -> Signature {
+        loop {
+            // register before looking, so that an insert in between isn't missed
+            let inserted = self.inserted.notified();
+            if let Some(signature) = self.get(root, operator_id) {
+                return signature;
+            }
+            inserted.await;
+        }
+    }
+}
@@ -315,9 +315,11 @@ impl SigningMethod {
 
                 // Following LocalKeystore, if the code logic reaches here, then it has already passed all checks of this duty, and
                 // it is safe (from this operator's point of view) to sign it locally.
-                dvf_signer.local_sign_and_store(signing_root).await;
+                // The share is pushed to the aggregators of the duty, so they don't have to ask for it.
+                let aggregators = dvf_signer.aggregators(signing_epoch.as_u64(), only_aggregator).await;
+                dvf_signer.local_sign_and_store(signing_root, &aggregators).await;
 
-                if !only_aggregator || (only_aggregator && dvf_signer.is_aggregator(signing_epoch.as_u64()).await) {
+                if aggregators.contains(&dvf_signer.operator_id()) {
                     log::info!("[Dvf {}/{}] Leader trying to achieve duty consensus and aggregate duty signatures",
                         dvf_signer.operator_id, 
                         dvf_signer.operator_committee.validator_id()
@@ -357,6 +357,16 @@ impl<T: SlotClock + 'static, E: EthSpec> ValidatorStore<T, E> {
             })
     }
 
+    /// The current epoch according to the slot clock.
+    pub fn current_epoch(&self) -> Option<Epoch> {
+        self.slot_clock.now().map(|slot| slot.epoch(E::slots_per_epoch()))
+    }
+
+    /// The time since the unix epoch at which `epoch` starts.
+    pub fn epoch_start(&self, epoch: Epoch) -> Option<std::time::Duration> {
+        self.slot_clock.start_of(epoch.start_slot(E::slots_per_epoch()))
+    }
+
     pub async fn num_voting_validators(&self) -> usize {
         self.validators.read().await.num_enabled()
     }
@@ -0,0 +1,69 @@
+use dvf::crypto::{ThresholdSignature};
+use bls::{Signature, PublicKey};
+use types::{Hash256};
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 20;
+
+// Compares checking each partial signature with its own pairing against one randomized
+// multi-pairing, for committees of 4, 7 and 10 operators. It only times things, so it is left
+// out of the default run; run it with
+// `cargo test --release --test test_batch_verification -- --ignored --nocapture` to see the numbers.
+#[test]
+#[ignore]
+fn bench_batch_verification() {
+    let message = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+    for &(n, t) in [(4usize, 3usize), (7, 5), (10, 7)].iter() {
+        let mut m_threshold = ThresholdSignature::new(t);
+        let ids = (1..n+1).map(|k| k as u64).collect::<Vec<u64>>();
+        let (kp, kps) = m_threshold.key_gen(&ids).unwrap();
+        let sigs: Vec<Signature> = ids.iter().map(|id| kps[id].sk.sign(message)).collect();
+        let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = ids.iter().map(|id| &kps[id].pk).collect();
+
+        let mut single = Duration::default();
+        let mut batch = Duration::default();
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            assert!(sigs_ref[..t].iter().zip(pks[..t].iter()).all(|(sig, pk)| sig.verify(pk, message)));
+            single += start.elapsed();
+
+            let start = Instant::now();
+            assert!(m_threshold.batch_verify(&sigs_ref[..t], &pks[..t], message));
+            batch += start.elapsed();
+        }
+        println!(
+            "{} of {} operators: per-signature {:?}, batch {:?}, speedup {:.2}x",
+            t,
+            n,
+            single / ROUNDS,
+            batch / ROUNDS,
+            single.as_secs_f64() / batch.as_secs_f64()
+        );
+
+        let agg_sig = m_threshold.threshold_aggregate(&sigs_ref[..], &pks[..], &ids[..], message).unwrap();
+        assert!(agg_sig.verify(&kp.pk, message), "Signature verification failed");
+    }
+}
+
+#[test]
+fn test_batch_verification_names_faulty_operator() {
+    let t = 5;
+    let n = 7;
+    let message = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+    let mut m_threshold = ThresholdSignature::new(t);
+    let ids = (1..n+1).map(|k| k as u64).collect::<Vec<u64>>();
+    let (kp, kps) = m_threshold.key_gen(&ids).unwrap();
+    let mut sigs: Vec<Signature> = ids.iter().map(|id| kps[id].sk.sign(message)).collect();
+    // operator 2 signs something else
+    sigs[1] = kps[&2].sk.sign(Hash256::from_slice(&eth2_hashing::hash("bye world".as_bytes())));
+    let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+    let pks: Vec<&PublicKey> = ids.iter().map(|id| &kps[id].pk).collect();
+
+    assert!(!m_threshold.batch_verify(&sigs_ref[..t], &pks[..t], message));
+    // the per-signature fallback skips operator 2 and still reaches the threshold
+    let agg_sig = m_threshold.threshold_aggregate(&sigs_ref[..], &pks[..], &ids[..], message).unwrap();
+    assert!(agg_sig.verify(&kp.pk, message), "Signature verification failed");
+    // without enough valid signatures left the aggregation fails
+    assert!(m_threshold.threshold_aggregate(&sigs_ref[..t], &pks[..t], &ids[..t], message).is_err());
+}
@@ -0,0 +1,323 @@
+use dvf::node::contract::{
+    apply_committee_change, Contract, ContractCommand, ContractConfig, ContractLog, CONFIRMATION_DEPTH,
+    SELF_OPERATOR_ID,
+};
+use dvf::node::execution::{ExecutionClients, EXECUTION_CLIENTS};
+use dvf::node::utils::FromFile;
+use dvf::test_utils::mock_contract::MockContract;
+use hsconfig::Secret;
+use hsutils::monitored_channel::{MonitoredChannel, MonitoredSender};
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+use web3::types::{Address, U256};
+
+// blocks mined on top of an event to confirm it
+const CONFIRMATIONS: u64 = 2;
+// paid blocks far ahead of the mock chain, so the validators are never stopped
+const PAID_BLOCK: u64 = 1_000_000;
+
+async fn next_command(rx: &mut Receiver<ContractCommand>) -> ContractCommand {
+    tokio::time::timeout(Duration::from_secs(30), rx.recv())
+        .await
+        .expect("timeout waiting for contract command")
+        .expect("contract command channel closed")
+}
+
+// logs waiting for their confirmation are checked once per poll interval, so anything left in
+// the pipeline has produced its command by then
+async fn assert_no_command(rx: &mut Receiver<ContractCommand>) {
+    if let Ok(Some(command)) = tokio::time::timeout(Duration::from_secs(15), rx.recv()).await {
+        panic!("unexpected {}", command.name());
+    }
+}
+
+fn register_validator(mock: &MockContract, validator_pk: Vec<u8>) {
+    mock.validator_registration(
+        Address::random(),
+        validator_pk,
+        vec![1, 2, 3, 4],
+        vec![vec![2u8; 48]; 4],
+        vec![vec![3u8; 96]; 4],
+        U256::from(PAID_BLOCK),
+    )
+    .unwrap();
+}
+
+// The execution endpoints and the confirmation depth are process wide, so the scenarios share
+// one mock chain and run in order.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_contract_events_from_mock_chain() {
+    // the deployed network contract has no operators change event, the mock one has. The first
+    // registration is logged before the legacy shares cutoff, the later ones after it.
+    let config = ContractConfig {
+        safestake_network_abi_path: "tests/data/SafeStakeNetworkMock.json".to_string(),
+        legacy_shares_before_block: 500,
+        ..ContractConfig::from_file("contract_config/configs.yml").unwrap()
+    };
+    let mock = MockContract::new(&config).unwrap();
+    let addr = mock.serve();
+    let _ = EXECUTION_CLIENTS.set(ExecutionClients::new(vec![format!("ws://{}", addr)]));
+    let _ = CONFIRMATION_DEPTH.set(CONFIRMATIONS);
+
+    let secrets: Vec<Secret> = (0..4).map(|_| Secret::new()).collect();
+    for (i, secret) in secrets.iter().enumerate() {
+        mock.add_operator(
+            i as u32 + 1,
+            format!("operator{}", i + 1),
+            Address::random(),
+            secret.name.0.to_vec(),
+        );
+    }
+    let _ = SELF_OPERATOR_ID.set(1);
+    mock.mine(1);
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut contract = Contract::new(dir.path(), secrets[0].name.clone()).unwrap();
+    contract.config = config;
+    contract.construct_filter().await;
+    contract.check_operator_id().await;
+    let (tx, mut rx) = MonitoredChannel::new(100, "contract-command".to_string(), "info");
+    let tx_log = contract.process_logs(tx.clone());
+    contract.listen_logs(tx_log.clone()).await;
+    contract.monitor_validator_paidblock(tx);
+    mock.wait_for_subscriber().await;
+
+    let owner = Address::random();
+    mock.set_paid_block(owner, U256::from(1000));
+    let validator_pk = vec![1u8; 48];
+    mock.validator_registration(
+        owner,
+        validator_pk.clone(),
+        vec![1, 2, 3, 4],
+        vec![vec![2u8; 48]; 4],
+        vec![vec![3u8; 96]; 4],
+        U256::from(1000),
+    )
+    .unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(&mut rx).await {
+        ContractCommand::StartValidator(validator, operator_pks, shared_pks, encrypted_keys, accept_legacy_shares) => {
+            assert_eq!(validator.owner_address, owner);
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+            assert_eq!(validator.releated_operators, vec![1, 2, 3, 4]);
+            let expected: Vec<Vec<u8>> = secrets.iter().map(|s| s.name.0.to_vec()).collect();
+            assert_eq!(operator_pks, expected);
+            assert_eq!(shared_pks.len(), 4);
+            assert_eq!(encrypted_keys.len(), 4);
+            assert!(accept_legacy_shares);
+        }
+        _ => panic!("expect StartValidator"),
+    }
+
+    mock.operator_removal(4, Address::random()).unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(&mut rx).await {
+        ContractCommand::OperatorRemoved(operator, committees) => {
+            assert_eq!(operator.id, 4);
+            assert_eq!(committees.len(), 1);
+            assert_eq!(committees[0].0.public_key.to_vec(), validator_pk);
+            assert_eq!(committees[0].1, vec![1, 2, 3]);
+        }
+        _ => panic!("expect OperatorRemoved"),
+    }
+    // re-registration brings the operator back for the following events
+    mock.operator_registration(4, "operator4".to_string(), Address::random(), secrets[3].name.0.to_vec())
+        .unwrap();
+    mock.mine(CONFIRMATIONS);
+
+    // the validator stops once the chain passes its paid block and resumes after a top up
+    mock.mine(1000);
+    match next_command(&mut rx).await {
+        ContractCommand::StopValidator(validator) => {
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+            assert_eq!(validator.paid_block, 1000);
+        }
+        _ => panic!("expect StopValidator"),
+    }
+    mock.funds_deposit(owner, U256::from(1), U256::from(5000)).unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(&mut rx).await {
+        ContractCommand::ActivateValidator(validator) => {
+            assert_eq!(validator.paid_block, 5000);
+        }
+        _ => panic!("expect ActivateValidator"),
+    }
+
+    mock.validator_removal(owner, validator_pk.clone()).unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(&mut rx).await {
+        ContractCommand::RemoveValidator(validator) => {
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+        }
+        _ => panic!("expect RemoveValidator"),
+    }
+
+    mock.initializer_registration(7, owner, vec![1, 2, 3, 4]).unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(&mut rx).await {
+        ContractCommand::StartInitializer(initializer, operator_pks) => {
+            assert_eq!(initializer.id, 7);
+            assert_eq!(initializer.releated_operators, vec![1, 2, 3, 4]);
+            assert_eq!(operator_pks.len(), 4);
+        }
+        _ => panic!("expect StartInitializer"),
+    }
+
+    let minipool_pk = vec![4u8; 48];
+    let minipool_address = Address::random();
+    mock.minipool_created(7, minipool_pk.clone(), minipool_address).unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(&mut rx).await {
+        ContractCommand::MiniPoolCreated(id, va_pk, _, operator_ids, address) => {
+            assert_eq!(id, 7);
+            assert_eq!(va_pk.to_vec(), minipool_pk);
+            assert_eq!(operator_ids, vec![1, 2, 3, 4]);
+            assert_eq!(address, minipool_address);
+        }
+        _ => panic!("expect MiniPoolCreated"),
+    }
+
+    orphaned_registration(&mock, &mut rx).await;
+    reorged_registration(&mock, &mut rx).await;
+    registrations_in_one_transaction(&mock, &contract, &tx_log, &mut rx).await;
+    operators_change(&mock, &contract, &mut rx).await;
+}
+
+// a registration whose block is replaced before it is confirmed is never applied
+async fn orphaned_registration(mock: &MockContract, rx: &mut Receiver<ContractCommand>) {
+    register_validator(mock, vec![6u8; 48]);
+    mock.reorg(1);
+    mock.mine(CONFIRMATIONS);
+    assert_no_command(rx).await;
+}
+
+// an applied registration that is reorganized out removes the validator again
+async fn reorged_registration(mock: &MockContract, rx: &mut Receiver<ContractCommand>) {
+    let validator_pk = vec![7u8; 48];
+    register_validator(mock, validator_pk.clone());
+    mock.mine(CONFIRMATIONS);
+    match next_command(rx).await {
+        ContractCommand::StartValidator(validator, _, _, _, accept_legacy_shares) => {
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+            assert!(!accept_legacy_shares);
+        }
+        _ => panic!("expect StartValidator"),
+    }
+    mock.reorg(CONFIRMATIONS + 1);
+    match next_command(rx).await {
+        ContractCommand::RemoveValidator(validator) => {
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+        }
+        _ => panic!("expect RemoveValidator"),
+    }
+    // the replacement blocks don't bring it back
+    mock.mine(CONFIRMATIONS);
+    assert_no_command(rx).await;
+}
+
+// the events of one transaction are told apart by their log index, and each is applied once
+// whether it arrives from the subscription or from a log query
+async fn registrations_in_one_transaction(
+    mock: &MockContract,
+    contract: &Contract,
+    tx_log: &MonitoredSender<ContractLog>,
+    rx: &mut Receiver<ContractCommand>,
+) {
+    let validator_pks = vec![vec![8u8; 48], vec![9u8; 48]];
+    let events = validator_pks
+        .iter()
+        .map(|validator_pk| {
+            MockContract::validator_registration_event(
+                Address::random(),
+                validator_pk.clone(),
+                vec![1, 2, 3, 4],
+                vec![vec![2u8; 48]; 4],
+                vec![vec![3u8; 96]; 4],
+                U256::from(PAID_BLOCK),
+            )
+        })
+        .collect();
+    let logs = mock.emit(events).unwrap();
+    assert_eq!(logs[0].transaction_hash, logs[1].transaction_hash);
+    mock.mine(CONFIRMATIONS);
+    for validator_pk in &validator_pks {
+        match next_command(rx).await {
+            ContractCommand::StartValidator(validator, _, _, _, _) => {
+                assert_eq!(&validator.public_key.to_vec(), validator_pk);
+            }
+            _ => panic!("expect StartValidator"),
+        }
+    }
+
+    // query the whole chain again, the replay returns once every log has been handled
+    contract.replay(mock.block_number(), tx_log).await.unwrap();
+    assert!(rx.try_recv().is_err());
+}
+
+// the record keeps the current committee until the reshare is in use, and a change that is
+// reorganized out cancels the pending reshare
+async fn operators_change(mock: &MockContract, contract: &Contract, rx: &mut Receiver<ContractCommand>) {
+    let owner = Address::random();
+    let validator_pk = vec![10u8; 48];
+    let va_str = hex::encode(&validator_pk);
+    mock.validator_registration(
+        owner,
+        validator_pk.clone(),
+        vec![1, 2, 3, 4],
+        vec![vec![2u8; 48]; 4],
+        vec![vec![3u8; 96]; 4],
+        U256::from(PAID_BLOCK),
+    )
+    .unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(rx).await {
+        ContractCommand::StartValidator(validator, _, _, _, _) => {
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+        }
+        _ => panic!("expect StartValidator"),
+    }
+
+    mock.validator_operators_change(owner, validator_pk.clone(), vec![1, 2, 3, 4], vec![1, 2, 3], 100)
+        .unwrap();
+    mock.mine(CONFIRMATIONS);
+    let validator = match next_command(rx).await {
+        ContractCommand::ReshareValidator(validator, operator_pks, old_operator_ids, epoch) => {
+            assert_eq!(validator.public_key.to_vec(), validator_pk);
+            assert_eq!(validator.releated_operators, vec![1, 2, 3]);
+            assert_eq!(validator.paid_block, PAID_BLOCK);
+            assert_eq!(operator_pks.len(), 3);
+            assert_eq!(old_operator_ids, vec![1, 2, 3, 4]);
+            assert_eq!(epoch, 100);
+            validator
+        }
+        _ => panic!("expect ReshareValidator"),
+    };
+    let record = contract.db.query_validator_by_public_key(va_str.clone()).await.unwrap().unwrap();
+    assert_eq!(record.releated_operators, vec![1, 2, 3, 4]);
+    // what the reshare does once the new committee is in use
+    apply_committee_change(&contract.db, validator).await;
+    let record = contract.db.query_validator_by_public_key(va_str.clone()).await.unwrap().unwrap();
+    assert_eq!(record.releated_operators, vec![1, 2, 3]);
+    assert_eq!(record.paid_block, PAID_BLOCK);
+
+    mock.validator_operators_change(owner, validator_pk.clone(), vec![1, 2, 3], vec![1, 2, 3, 4], 200)
+        .unwrap();
+    mock.mine(CONFIRMATIONS);
+    match next_command(rx).await {
+        ContractCommand::ReshareValidator(_, _, _, epoch) => assert_eq!(epoch, 200),
+        _ => panic!("expect ReshareValidator"),
+    }
+    assert!(!contract.db.is_reshare_cancelled(va_str.clone(), 200).await.unwrap());
+    mock.reorg(CONFIRMATIONS + 1);
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while !contract.db.is_reshare_cancelled(va_str.clone(), 200).await.unwrap() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("timeout waiting for the reshare to be cancelled");
+    let record = contract.db.query_validator_by_public_key(va_str).await.unwrap().unwrap();
+    assert_eq!(record.releated_operators, vec![1, 2, 3]);
+    mock.mine(CONFIRMATIONS);
+    assert_no_command(rx).await;
+}
@@ -1,7 +1,7 @@
 use aes_gcm::{Aes128Gcm, Key, Nonce};
 use aes_gcm::aead::{Aead, NewAead};
 use secp256k1::{Secp256k1, SecretKey, PublicKey, ecdh};
-use dvf::crypto::elgamal::{Elgamal, Ciphertext};
+use dvf::crypto::elgamal::{Elgamal, Ciphertext, ShareEnvelope, SHARE_ENVELOPE_VERSION};
 use sha256::{digest_bytes};
 
 #[test]
@@ -40,7 +40,7 @@ fn test_elgamal() {
 
     let ct_bytes = ct.to_bytes();
     println!("ct {}", ct_bytes.len());
-    let ct2 = Ciphertext::from_bytes(ct_bytes.as_slice());
+    let ct2 = Ciphertext::from_bytes(ct_bytes.as_slice()).unwrap();
 
     let plain = elgamal.decrypt(&ct2, &sk).unwrap();
     println!("Message: {:?}", String::from_utf8(plain));
@@ -59,13 +59,81 @@ fn test_elgamal_from_js_serialized() {
 
     let mut elgamal = Elgamal::new(rng);
     let sk = SecretKey::from_slice(sk_bytes.as_slice()).unwrap();
-    let ct = Ciphertext::from_bytes(ct_bytes.as_slice()); 
+    let ct = Ciphertext::from_bytes(ct_bytes.as_slice()).unwrap();
 
     let plain = elgamal.decrypt(&ct, &sk).unwrap();
     println!("Message: {:?}", String::from_utf8(plain));
 
 }
 
+#[test]
+fn test_share_envelope() {
+    let mut elgamal = Elgamal::new(rand::thread_rng());
+    let (sk, pk) = elgamal.generate_key();
+    let share = bls::SecretKey::random().serialize();
+    let validator_pk = bls::Keypair::random().pk.serialize();
+    let envelope = elgamal.encrypt_share(share.as_bytes(), &pk, &validator_pk, 3).unwrap();
+
+    let bytes = envelope.to_bytes();
+    assert_eq!(bytes[0], SHARE_ENVELOPE_VERSION);
+    let envelope = ShareEnvelope::from_bytes(&bytes).unwrap();
+    let plain = elgamal.decrypt_share(&envelope, &sk, &validator_pk, 3).unwrap();
+    assert_eq!(plain, share.as_bytes());
+
+    // bound to the validator and the operator id
+    let other_validator_pk = bls::Keypair::random().pk.serialize();
+    assert!(elgamal.decrypt_share(&envelope, &sk, &other_validator_pk, 3).is_err());
+    assert!(elgamal.decrypt_share(&envelope, &sk, &validator_pk, 4).is_err());
+    let (other_sk, _) = elgamal.generate_key();
+    assert!(elgamal.decrypt_share(&envelope, &other_sk, &validator_pk, 3).is_err());
+}
+
+#[test]
+fn test_share_envelope_malformed() {
+    let mut elgamal = Elgamal::new(rand::thread_rng());
+    let (_, pk) = elgamal.generate_key();
+    let validator_pk = bls::Keypair::random().pk.serialize();
+    let bytes = elgamal.encrypt_share(&[1u8; 32], &pk, &validator_pk, 1).unwrap().to_bytes();
+
+    assert!(ShareEnvelope::from_bytes(&[]).is_err());
+    // legacy ciphertexts start with the ephemeral public key
+    assert!(ShareEnvelope::from_bytes(&bytes[1..]).is_err());
+    for len in 0..bytes.len() - 32 {
+        assert!(ShareEnvelope::from_bytes(&bytes[..len]).is_err());
+    }
+    let mut bad_pk = bytes.clone();
+    bad_pk[1] = 0x05;
+    assert!(ShareEnvelope::from_bytes(&bad_pk).is_err());
+    assert!(Ciphertext::from_bytes(&bytes[1..20]).is_err());
+}
+
+#[test]
+fn test_legacy_encrypted_key() {
+    let mut elgamal = Elgamal::new(rand::thread_rng());
+    let (sk, pk) = elgamal.generate_key();
+    let share = bls::SecretKey::random().serialize();
+    let validator_pk = bls::Keypair::random().pk.serialize();
+
+    // registrations made before share envelopes carry a plain ciphertext, later ones can't
+    let legacy = elgamal.encrypt(share.as_bytes(), &pk).unwrap().to_bytes();
+    assert!(legacy[0] == 0x02 || legacy[0] == 0x03);
+    assert_eq!(elgamal.decrypt_encrypted_key(&legacy, &sk, &validator_pk, 3, true).unwrap(), share.as_bytes());
+    assert!(elgamal.decrypt_encrypted_key(&legacy, &sk, &validator_pk, 3, false).is_err());
+
+    // the ciphertext of 'hello' serialized by the js implementation
+    let js_sk = SecretKey::from_slice(&hex::decode("d7bbda914fb72b1d9604be0e86a8d9149fe1418ec95e457aac280434779c162a").unwrap()).unwrap();
+    let js_ct = hex::decode("02fc986be28243bbcde76b80ef86cd0ca77b2a7083761930362882144c90c8c5ec500b4e44cdc0f7cb48f3258b5de50a30f61da8c40de6dfe06989cc3a39b72d79c0").unwrap();
+    assert_eq!(elgamal.decrypt_encrypted_key(&js_ct, &js_sk, &validator_pk, 1, true).unwrap(), b"hello");
+
+    let envelope = elgamal.encrypt_share(share.as_bytes(), &pk, &validator_pk, 3).unwrap().to_bytes();
+    assert_eq!(elgamal.decrypt_encrypted_key(&envelope, &sk, &validator_pk, 3, false).unwrap(), share.as_bytes());
+    assert!(elgamal.decrypt_encrypted_key(&envelope, &sk, &validator_pk, 4, false).is_err());
+    let mut unknown_version = envelope.clone();
+    unknown_version[0] = 0x04;
+    assert!(elgamal.decrypt_encrypted_key(&unknown_version, &sk, &validator_pk, 3, false).is_err());
+    assert!(elgamal.decrypt_encrypted_key(&[], &sk, &validator_pk, 3, false).is_err());
+}
+
 #[test]
 fn test_hash() {
     let hash = digest_bytes(&hex::decode("e767757033da4644738412e4b580a700d59d40d94cf5c0f08dd76e81f70eee94").unwrap());
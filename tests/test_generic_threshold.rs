@@ -1,4 +1,5 @@
 use dvf::crypto::{ThresholdSignature};
+use dvf::node::utils::committee_threshold;
 use bls::{Signature, PublicKey};
 use types::{Hash256}; 
 use eth2_hashing::{Context, Sha256Context};
@@ -34,3 +35,26 @@ fn test_generic_threshold() {
     assert!(status2, "Aggregate signature verification failed");
     assert_eq!(agg_sig, sig, "Signature not match");
 }
+
+#[test]
+fn test_committee_threshold() {
+    let message = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+    for &(n, expected) in [(4usize, 3usize), (7, 5), (10, 7), (13, 9)].iter() {
+        let t = committee_threshold(n) as usize;
+        assert_eq!(t, expected);
+
+        let mut m_threshold = ThresholdSignature::new(t);
+        let ids = (1..n+1).map(|k| k as u64).collect::<Vec<u64>>();
+        let (kp, kps) = m_threshold.key_gen(&ids).unwrap();
+        // the last operators sign, the others are offline
+        let signers = &ids[n-t..];
+        let sigs: Vec<Signature> = signers.iter().map(|id| kps[id].sk.sign(message)).collect();
+        let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+        let pks: Vec<&PublicKey> = signers.iter().map(|id| &kps[id].pk).collect();
+        let agg_sig = m_threshold.threshold_aggregate(&sigs_ref[..], &pks[..], signers, message).unwrap();
+        assert!(agg_sig.verify(&kp.pk, message), "Signature verification failed with {} of {}", t, n);
+
+        // one signature short of the threshold
+        assert!(m_threshold.threshold_aggregate(&sigs_ref[1..], &pks[1..], &signers[1..], message).is_err());
+    }
+}
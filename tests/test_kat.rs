@@ -0,0 +1,180 @@
+use dvf::crypto::dkg::DKG;
+use dvf::crypto::elgamal::{Ciphertext, Elgamal, ShareEnvelope};
+use dvf::crypto::ThresholdSignature;
+use dvf::network::io_committee::MemIOCommittee;
+use bls::{PublicKey, SecretKey, Signature};
+use futures::future::join_all;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::Value;
+use std::sync::Arc;
+use types::Hash256;
+
+// Known-answer vectors shared with other SafeStake implementations, e.g. the web key-splitter.
+// Any change to these outputs breaks compatibility with keys split or encrypted elsewhere.
+const VECTORS: &str = include_str!("data/kat_vectors.json");
+
+fn vectors() -> Value {
+    serde_json::from_str(VECTORS).unwrap()
+}
+
+fn bytes(v: &Value) -> Vec<u8> {
+    hex::decode(v.as_str().unwrap()).unwrap()
+}
+
+fn seed(v: &Value) -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(&bytes(v));
+    seed
+}
+
+fn ids(v: &Value) -> Vec<u64> {
+    v.as_array().unwrap().iter().map(|id| id.as_u64().unwrap()).collect()
+}
+
+fn message() -> Hash256 {
+    Hash256::from_slice(&bytes(&vectors()["message"]))
+}
+
+fn combinations(ids: &[u64], k: usize) -> Vec<Vec<u64>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    if ids.len() < k {
+        return vec![];
+    }
+    let mut subsets: Vec<Vec<u64>> = combinations(&ids[1..], k - 1)
+        .into_iter()
+        .map(|mut c| {
+            c.insert(0, ids[0]);
+            c
+        })
+        .collect();
+    subsets.extend(combinations(&ids[1..], k));
+    subsets
+}
+
+#[test]
+fn test_kat_key_split() {
+    for case in vectors()["key_split"].as_array().unwrap() {
+        let t = case["threshold"].as_u64().unwrap() as usize;
+        let ids = ids(&case["ids"]);
+        assert_eq!(seed(&case["seed"]), [0u8; 32], "deterministic_key_split uses a zero seed");
+        let sk = SecretKey::deserialize(&bytes(&case["secret_key"])).unwrap();
+        assert_eq!(sk.public_key().serialize().to_vec(), bytes(&case["public_key"]));
+
+        let mut m_threshold = ThresholdSignature::new(t);
+        let kps = m_threshold.deterministic_key_split(&sk, &ids).unwrap();
+        for share in case["shares"].as_array().unwrap() {
+            let id = share["id"].as_u64().unwrap();
+            assert_eq!(kps[&id].sk.serialize().as_bytes().to_vec(), bytes(&share["secret_key"]), "share {}", id);
+            assert_eq!(kps[&id].pk.serialize().to_vec(), bytes(&share["public_key"]), "share {}", id);
+            assert_eq!(m_threshold.deterministic_key_share(&sk, id).pk, kps[&id].pk);
+        }
+    }
+}
+
+#[test]
+fn test_kat_threshold_aggregate() {
+    let message = message();
+    for case in vectors()["key_split"].as_array().unwrap() {
+        let t = case["threshold"].as_u64().unwrap() as usize;
+        let expected = bytes(&case["signature"]);
+        let mut sigs = std::collections::HashMap::new();
+        let mut pks = std::collections::HashMap::new();
+        for share in case["shares"].as_array().unwrap() {
+            let id = share["id"].as_u64().unwrap();
+            let sk = SecretKey::deserialize(&bytes(&share["secret_key"])).unwrap();
+            let sig = sk.sign(message);
+            assert_eq!(sig.serialize().to_vec(), bytes(&share["signature"]), "signature of share {}", id);
+            sigs.insert(id, Signature::deserialize(&bytes(&share["signature"])).unwrap());
+            pks.insert(id, PublicKey::deserialize(&bytes(&share["public_key"])).unwrap());
+        }
+
+        let m_threshold = ThresholdSignature::new(t);
+        for subset in combinations(&ids(&case["ids"]), t) {
+            let sigs_ref: Vec<&Signature> = subset.iter().map(|id| &sigs[id]).collect();
+            let pks_ref: Vec<&PublicKey> = subset.iter().map(|id| &pks[id]).collect();
+            let agg_sig = m_threshold.threshold_aggregate(&sigs_ref, &pks_ref, &subset, message).unwrap();
+            assert_eq!(agg_sig.serialize().to_vec(), expected, "aggregate of {:?}", subset);
+        }
+    }
+}
+
+#[test]
+fn test_kat_elgamal() {
+    for case in vectors()["elgamal"].as_array().unwrap() {
+        let sk = secp256k1::SecretKey::from_slice(&bytes(&case["secret_key"])).unwrap();
+        let pk = secp256k1::PublicKey::from_slice(&bytes(&case["public_key"])).unwrap();
+        let plaintext = bytes(&case["plaintext"]);
+
+        let mut elgamal = Elgamal::new(StdRng::from_seed(seed(&case["seed"])));
+        let ct = elgamal.encrypt(&plaintext, &pk).unwrap();
+        assert_eq!(ct.to_bytes(), bytes(&case["ciphertext"]));
+
+        let ct = Ciphertext::from_bytes(&bytes(&case["ciphertext"])).unwrap();
+        assert_eq!(elgamal.decrypt(&ct, &sk).unwrap(), plaintext);
+    }
+}
+
+#[test]
+fn test_kat_share_envelope() {
+    for case in vectors()["share_envelope"].as_array().unwrap() {
+        let sk = secp256k1::SecretKey::from_slice(&bytes(&case["secret_key"])).unwrap();
+        let pk = secp256k1::PublicKey::from_slice(&bytes(&case["public_key"])).unwrap();
+        let validator_pk = bytes(&case["validator_public_key"]);
+        let operator_id = case["operator_id"].as_u64().unwrap();
+        let share = bytes(&case["share"]);
+
+        let mut elgamal = Elgamal::new(StdRng::from_seed(seed(&case["seed"])));
+        let envelope = elgamal.encrypt_share(&share, &pk, &validator_pk, operator_id).unwrap();
+        assert_eq!(envelope.to_bytes(), bytes(&case["envelope"]));
+
+        let envelope = ShareEnvelope::from_bytes(&bytes(&case["envelope"])).unwrap();
+        assert_eq!(elgamal.decrypt_share(&envelope, &sk, &validator_pk, operator_id).unwrap(), share);
+    }
+}
+
+#[tokio::test]
+async fn test_kat_dkg() {
+    let message = message();
+    for case in vectors()["dkg"].as_array().unwrap() {
+        let t = case["threshold"].as_u64().unwrap() as usize;
+        let ids = ids(&case["ids"]);
+        let seeds: Vec<(u64, [u8; 32])> = case["seeds"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| (s["id"].as_u64().unwrap(), seed(&s["seed"])))
+            .collect();
+
+        let io = &Arc::new(MemIOCommittee::new(ids.as_slice()));
+        let futs = seeds.iter().map(|(id, seed)| async move {
+            let dkg = DKG::new(*id, io.clone(), t).with_seed(*seed);
+            dkg.run().await
+        });
+        let results: Vec<_> = join_all(futs).await.into_iter().map(|r| r.unwrap()).collect();
+
+        let mpk = bytes(&case["master_public_key"]);
+        for ((id, _), (kp, result_mpk, pks)) in seeds.iter().zip(results.iter()) {
+            let party = case["parties"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|p| p["id"].as_u64().unwrap() == *id)
+                .unwrap();
+            assert_eq!(result_mpk.serialize().to_vec(), mpk);
+            assert_eq!(kp.sk.serialize().as_bytes().to_vec(), bytes(&party["secret_key"]), "party {}", id);
+            assert_eq!(kp.pk.serialize().to_vec(), bytes(&party["public_key"]), "party {}", id);
+            assert_eq!(pks[id], kp.pk);
+        }
+
+        let sigs: Vec<Signature> = results.iter().map(|(kp, _, _)| kp.sk.sign(message)).collect();
+        let sigs_ref: Vec<&Signature> = sigs.iter().collect();
+        let pks_ref: Vec<&PublicKey> = results.iter().map(|(kp, _, _)| &kp.pk).collect();
+        let agg_sig = ThresholdSignature::new(t)
+            .threshold_aggregate(&sigs_ref, &pks_ref, &ids, message)
+            .unwrap();
+        assert_eq!(agg_sig.serialize().to_vec(), bytes(&case["signature"]));
+    }
+}
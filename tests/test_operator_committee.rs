@@ -1,10 +1,22 @@
 use dvf::validation::{OperatorCommittee};
+#[cfg(feature = "fake_committee")]
 use dvf::validation::operator::{LocalOperator};
+use dvf::validation::operator::{TOperator};
+use dvf::validation::partial_signatures::PartialSignature;
 use dvf::crypto::{ThresholdSignature};
+use dvf::utils::error::DvfError;
 use std::sync::Arc;
-use types::Hash256;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use types::{Hash256, Keypair, PublicKey, Signature};
+#[cfg(feature = "fake_committee")]
 use eth2_hashing::{Context, Sha256Context};
+#[cfg(feature = "fake_committee")]
 use futures::executor::block_on; 
+use tokio::sync::RwLock;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::time::Instant;
+use async_trait::async_trait;
 
 #[cfg(feature = "fake_committee")]
 #[test]
@@ -40,3 +52,101 @@ fn test_fake_operator_committee() {
     //let a = Arc<RwLock<dyn TOperator>>> = Arc::new(RwLock::new(
             //LocalOperator::from_keypair(Arc::new(kps[0].clone()))));
 }
+
+/// Signs with its key share after `delay`, reaching consensus as soon as it's proposed to.
+struct TestOperator {
+    keypair: Keypair,
+    delay: Duration,
+    consensus: Sender<Hash256>,
+    dropped: Arc<AtomicBool>,
+}
+
+/// Marks a signing request as dropped unless it completed.
+struct DropGuard(Arc<AtomicBool>);
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl TOperator for TestOperator {
+    async fn sign(&self, msg: Hash256) -> Result<Signature, DvfError> {
+        let guard = DropGuard(self.dropped.clone());
+        tokio::time::sleep(self.delay).await;
+        std::mem::forget(guard);
+        Ok(self.keypair.sk.sign(msg))
+    }
+
+    fn public_key(&self) -> PublicKey {
+        self.keypair.pk.clone()
+    }
+
+    async fn propose(&self, msg: Hash256) {
+        let _ = self.consensus.send(msg).await;
+    }
+
+    async fn push(&self, _share: &PartialSignature) { }
+}
+
+/// A committee of `n` operators with threshold `t`, where the operators in `slow` sign after
+/// `delay` and the ones in `faulty` sign with a key that isn't their share.
+async fn test_committee(
+    t: usize,
+    n: u64,
+    slow: &[u64],
+    delay: Duration,
+    faulty: &[u64],
+) -> (OperatorCommittee, PublicKey, Vec<Arc<AtomicBool>>) {
+    let ids: Vec<u64> = (1..=n).collect();
+    let (kp, kps) = ThresholdSignature::new(t).key_gen(&ids).unwrap();
+    let (tx, rx) = channel(100);
+    let mut committee = OperatorCommittee::new(0, kp.pk.clone(), t, rx);
+    let mut dropped = vec![];
+    for id in ids.iter() {
+        let keypair = match faulty.contains(id) {
+            true => Keypair::from_components(kps[id].pk.clone(), Keypair::random().sk),
+            false => kps[id].clone(),
+        };
+        let flag = Arc::new(AtomicBool::new(false));
+        let operator = TestOperator {
+            keypair,
+            delay: if slow.contains(id) { delay } else { Duration::ZERO },
+            consensus: tx.clone(),
+            dropped: flag.clone(),
+        };
+        committee.add_operator(*id, Arc::new(RwLock::new(operator))).await;
+        dropped.push(flag);
+    }
+    (committee, kp.pk, dropped)
+}
+
+// An operator that doesn't answer must not hold up the signature once `threshold` shares are in
+#[tokio::test]
+async fn test_hotstuff_committee_sign_without_slow_operator() {
+    let delay = Duration::from_secs(60);
+    let (committee, pk, dropped) = test_committee(3, 4, &[4], delay, &[]).await;
+    let message = Hash256::from_slice(&[1u8; 32]);
+
+    let start = Instant::now();
+    let (sig, ids) = committee.sign(message).await.unwrap();
+    assert!(start.elapsed() < delay / 60, "signing waited for the slow operator");
+    assert!(sig.verify(&pk, message), "Signature verification failed");
+    assert!(!ids.contains(&4));
+    // the request to the slow operator is dropped, not left running
+    assert!(dropped[3].load(Ordering::SeqCst), "the slow request is still pending");
+    assert!(dropped[..3].iter().all(|flag| !flag.load(Ordering::SeqCst)));
+}
+
+// Shares that fail the batch check are checked one by one, and the faulty ones are left out
+#[tokio::test]
+async fn test_hotstuff_committee_sign_with_faulty_operator() {
+    let (committee, pk, _) = test_committee(3, 5, &[], Duration::ZERO, &[1]).await;
+    let message = Hash256::from_slice(&[2u8; 32]);
+
+    let (sig, ids) = committee.sign(message).await.unwrap();
+    assert!(sig.verify(&pk, message), "Signature verification failed");
+    assert!(!ids.contains(&1));
+    assert_eq!(ids.len(), 3);
+}
@@ -0,0 +1,53 @@
+use dvf::crypto::ThresholdSignature;
+use dvf::validation::partial_signatures::{PartialSignature, PartialSignatures};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+use types::Hash256;
+
+#[tokio::test]
+async fn test_partial_signatures() {
+    let (n, t) = (4usize, 3usize);
+    let ids = (1..n + 1).map(|k| k as u64).collect::<Vec<u64>>();
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps) = m_threshold.key_gen(&ids).unwrap();
+    let root = Hash256::from_slice(&eth2_hashing::hash("hello world".as_bytes()));
+    let other_root = Hash256::from_slice(&eth2_hashing::hash("hello again".as_bytes()));
+
+    let partial_signatures = Arc::new(PartialSignatures::new());
+    for id in ids.iter() {
+        partial_signatures.add_operator(*id, kps[id].pk.clone());
+    }
+    let share = |id: u64, root: Hash256| PartialSignature { operator_id: id, root, signature: kps[&id].sk.sign(root) };
+
+    // Only valid shares of the operators are kept.
+    assert!(partial_signatures.insert(PartialSignature { operator_id: 5, ..share(1, root) }).is_err());
+    assert!(partial_signatures.insert(PartialSignature { root: other_root, ..share(1, root) }).is_err());
+    assert!(partial_signatures.insert(PartialSignature { operator_id: 2, ..share(1, root) }).is_err());
+    assert!(partial_signatures.shares(root).is_empty());
+
+    // The collector is woken up once the share it waits for arrives.
+    let collector = {
+        let partial_signatures = partial_signatures.clone();
+        tokio::spawn(async move { partial_signatures.wait_for(root, 4).await })
+    };
+    partial_signatures.insert(share(1, other_root)).unwrap();
+    partial_signatures.insert(share(1, root)).unwrap();
+    partial_signatures.insert(share(3, root)).unwrap();
+    assert_eq!(partial_signatures.get(root, 3), Some(kps[&3].sk.sign(root)));
+    assert!(partial_signatures.get(root, 4).is_none());
+    assert!(timeout(Duration::from_millis(100), partial_signatures.wait_for(root, 4)).await.is_err());
+    partial_signatures.insert(share(4, root)).unwrap();
+
+    let signature = timeout(Duration::from_secs(1), collector).await.unwrap().unwrap();
+    assert_eq!(signature, kps[&4].sk.sign(root));
+    let shares = partial_signatures.shares(root);
+    assert_eq!(shares.len(), t);
+    let mut shares: Vec<_> = shares.into_iter().collect();
+    shares.sort_by_key(|(id, _)| *id);
+    let sigs = shares.iter().map(|(_, sig)| sig).collect::<Vec<_>>();
+    let pks = shares.iter().map(|(id, _)| &kps[id].pk).collect::<Vec<_>>();
+    let share_ids = shares.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    let sig = m_threshold.threshold_aggregate(&sigs, &pks, &share_ids, root).unwrap();
+    assert!(sig.verify(&kp.pk, root));
+}
@@ -0,0 +1,106 @@
+use dvf::node::contract::{
+    Contract, ContractCommand, ContractConfig, ContractRecord, CONFIRMATION_DEPTH, CONTRACT_DATABASE_FILE,
+    CONTRACT_RECORD_FILE, SECONDS_PER_EPOCH, SELF_OPERATOR_ID,
+};
+use dvf::node::db::Database;
+use dvf::node::execution::{ExecutionClients, EXECUTION_CLIENTS};
+use dvf::node::resync::Resync;
+use dvf::node::utils::FromFile;
+use dvf::test_utils::mock_contract::MockContract;
+use hsconfig::Secret;
+use hsutils::monitored_channel::MonitoredChannel;
+use std::time::Duration;
+use web3::types::{Address, U256};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resync_rebuilds_and_restarts_missing_validators() {
+    let config = ContractConfig::from_file("contract_config/configs.yml").unwrap();
+    let mock = MockContract::new(&config).unwrap();
+    let addr = mock.serve();
+    let _ = EXECUTION_CLIENTS.set(ExecutionClients::new(vec![format!("ws://{}", addr)]));
+    let _ = CONFIRMATION_DEPTH.set(0);
+
+    let secrets: Vec<Secret> = (0..4).map(|_| Secret::new()).collect();
+    for (i, secret) in secrets.iter().enumerate() {
+        mock.add_operator(
+            i as u32 + 1,
+            format!("operator{}", i + 1),
+            Address::random(),
+            secret.name.0.to_vec(),
+        );
+    }
+    let _ = SELF_OPERATOR_ID.set(1);
+    mock.mine(10);
+
+    // a paid validator and one whose paid block has passed
+    let paid_owner = Address::random();
+    let paid_pk = vec![1u8; 48];
+    let logs = mock
+        .validator_registration(
+            paid_owner,
+            paid_pk.clone(),
+            vec![1, 2, 3, 4],
+            vec![vec![2u8; 48]; 4],
+            vec![vec![3u8; 96]; 4],
+            U256::from(1000),
+        )
+        .unwrap();
+    let registration_block = logs[0].block_number.unwrap().as_u64();
+    let expired_owner = Address::random();
+    mock.validator_registration(
+        expired_owner,
+        vec![5u8; 48],
+        vec![1, 2, 3, 4],
+        vec![vec![2u8; 48]; 4],
+        vec![vec![3u8; 96]; 4],
+        U256::from(20),
+    )
+    .unwrap();
+    mock.mine(20);
+
+    let base_dir = tempfile::tempdir().unwrap();
+    // accounting and queued commands of the node can't be rebuilt from the chain
+    let db = Database::new(base_dir.path().join(CONTRACT_DATABASE_FILE)).unwrap();
+    db.start_validator_service(hex::encode(&paid_pk), paid_owner, 5, 60).await;
+    let queued = ContractCommand::RefreshShares(paid_pk.clone().try_into().unwrap(), 100);
+    db.enqueue_command(&queued, 0).await.unwrap();
+    db.close().await;
+
+    let validator_dir = base_dir.path().join("validators");
+    let secrets_dir = base_dir.path().join("secrets");
+    std::fs::create_dir_all(&validator_dir).unwrap();
+    std::fs::create_dir_all(&secrets_dir).unwrap();
+    let resync = Resync::new(base_dir.path().to_path_buf(), validator_dir, secrets_dir);
+    let plan = resync.rebuild(secrets[0].name.clone(), 0).await.unwrap();
+    assert_eq!(plan.start.len(), 1);
+    assert_eq!(plan.start[0].public_key.to_vec(), paid_pk);
+    assert!(plan.stop.is_empty());
+    assert!(plan.resume.is_empty());
+    assert!(plan.remove.is_empty());
+
+    resync.apply(&plan).unwrap();
+    let record = ContractRecord::from_file(base_dir.path().join(CONTRACT_RECORD_FILE)).unwrap();
+    assert_eq!(record.block_num, registration_block);
+    let db = Database::new(base_dir.path().join(CONTRACT_DATABASE_FILE)).unwrap();
+    let due = db.query_due_commands(0).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].command.name(), "RefreshShares");
+    // the validator isn't running locally, so its service ended at the rebuilt head
+    let served = db.query_served_epochs(u64::MAX, SECONDS_PER_EPOCH).await.unwrap();
+    assert_eq!(served, vec![(paid_owner, (plan.timestamp - 60) / SECONDS_PER_EPOCH)]);
+    db.close().await;
+
+    // the contract watcher starts the missing validator from the rewound record
+    let mut contract = Contract::new(base_dir.path(), secrets[0].name.clone()).unwrap();
+    contract.construct_filter().await;
+    let (tx, mut rx) = MonitoredChannel::new(100, "contract-command".to_string(), "info");
+    let tx_log = contract.process_logs(tx);
+    contract.replay(mock.block_number(), &tx_log).await.unwrap();
+    match tokio::time::timeout(Duration::from_secs(30), rx.recv()).await.unwrap().unwrap() {
+        ContractCommand::StartValidator(validator, _, _, _, _) => {
+            assert_eq!(validator.public_key.to_vec(), paid_pk);
+            assert_eq!(validator.paid_block, 1000);
+        }
+        _ => panic!("expect StartValidator"),
+    }
+}
@@ -0,0 +1,199 @@
+use bls::{Keypair, SecretKey};
+use dvf::crypto::scalar::{lagrange_coeffs_at_zero, Scalar};
+use dvf::crypto::ThresholdSignature;
+use dvf::math::polynomial::Polynomial;
+use legacy::Ring;
+use num_bigint::{BigInt, Sign};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::collections::HashMap;
+
+// Checks the blst scalar arithmetic against the num-bigint arithmetic it replaced.
+
+const ROUNDS: usize = 100;
+
+// The BigInt key split as it was before the blst scalars, kept as the reference.
+mod legacy {
+    use super::*;
+    use num_bigint::ToBigInt;
+    use num_traits::Zero;
+    use rand::distributions::Uniform;
+    use rand::Rng;
+
+    pub trait Ring {
+        fn reduce(&self, m: &BigInt) -> Self;
+    }
+
+    impl Ring for BigInt {
+        fn reduce(&self, m: &BigInt) -> BigInt {
+            let mut result = self.clone();
+            result %= m;
+            if result.sign() == Sign::Minus {
+                result += m;
+            }
+            result
+        }
+    }
+
+    pub fn sample_vec(rng: &mut StdRng, len: usize, modulus: &BigInt) -> Vec<BigInt> {
+        let range = Uniform::from(BigInt::zero()..modulus.clone());
+        rng.sample_iter(&range).take(len).collect()
+    }
+
+    pub fn key_split(coeffs: Vec<BigInt>, ids: &[u64]) -> HashMap<u64, Keypair> {
+        let poly = Polynomial::new(coeffs);
+        let mut kps = HashMap::new();
+        for id in ids {
+            let (_, mut sk_share) = poly.eval(&id.to_bigint().unwrap()).reduce(&modulus()).to_bytes_be();
+            while sk_share.len() < 32 {
+                sk_share.insert(0, 0);
+            }
+            let sk_share = SecretKey::deserialize(&sk_share).unwrap();
+            kps.insert(*id, Keypair::from_components(sk_share.public_key(), sk_share));
+        }
+        kps
+    }
+
+    // deterministic_key_split before the blst scalars
+    pub fn deterministic_key_split(sk: &SecretKey, t: usize, ids: &[u64]) -> HashMap<u64, Keypair> {
+        let mut rng = StdRng::from_seed([0; 32]);
+        let mut coeffs = sample_vec(&mut rng, t, &modulus());
+        coeffs[0] = BigInt::from_bytes_be(Sign::Plus, sk.serialize().as_bytes());
+        key_split(coeffs, ids)
+    }
+}
+
+fn modulus() -> BigInt {
+    BigInt::parse_bytes(b"73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001", 16).unwrap()
+}
+
+fn reduce(x: BigInt) -> BigInt {
+    x.reduce(&modulus())
+}
+
+fn to_bigint(x: &Scalar) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, &x.to_bytes_be())
+}
+
+fn from_bigint(x: &BigInt) -> Scalar {
+    let (_, mut bytes) = reduce(x.clone()).to_bytes_be();
+    while bytes.len() < 32 {
+        bytes.insert(0, 0);
+    }
+    Scalar::from_bytes_be(&bytes).unwrap()
+}
+
+fn random_bigint(rng: &mut StdRng) -> BigInt {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes);
+    reduce(BigInt::from_bytes_be(Sign::Plus, &bytes))
+}
+
+#[test]
+fn test_scalar_arithmetic() {
+    let mut rng = StdRng::from_seed([7; 32]);
+    let r = modulus();
+    for _ in 0..ROUNDS {
+        let a = random_bigint(&mut rng);
+        let b = random_bigint(&mut rng);
+        let (sa, sb) = (from_bigint(&a), from_bigint(&b));
+        assert_eq!(to_bigint(&sa), a);
+        assert_eq!(to_bigint(&(sa.clone() + sb.clone())), reduce(&a + &b));
+        assert_eq!(to_bigint(&(sa.clone() - sb.clone())), reduce(&a - &b));
+        assert_eq!(to_bigint(&(sa.clone() * sb.clone())), reduce(&a * &b));
+        assert_eq!(to_bigint(&(-sa.clone())), reduce(-&a));
+        let inverse = a.modpow(&(&r - 2), &r);
+        assert_eq!(to_bigint(&sa.inverse().unwrap()), inverse);
+    }
+    assert!(Scalar::zero().inverse().is_none());
+    assert_eq!(to_bigint(&Scalar::from_u64(u64::MAX)), BigInt::from(u64::MAX));
+}
+
+#[test]
+fn test_scalar_bytes() {
+    let (_, r_bytes) = modulus().to_bytes_be();
+    assert!(Scalar::from_bytes_be(&r_bytes).is_none());
+    assert!(Scalar::from_bytes_be(&[0u8; 31]).is_none());
+    let (_, max) = (modulus() - 1).to_bytes_be();
+    assert_eq!(Scalar::from_bytes_be(&max).unwrap().to_bytes_be().to_vec(), max);
+
+    let mut rng = StdRng::from_seed([8; 32]);
+    for _ in 0..ROUNDS {
+        let x = Scalar::random(&mut rng);
+        assert_eq!(Scalar::from_bytes_be(&x.to_bytes_be()).unwrap(), x);
+    }
+}
+
+#[test]
+fn test_polynomial_eval() {
+    let mut rng = StdRng::from_seed([9; 32]);
+    let coeffs: Vec<BigInt> = (0..7).map(|_| random_bigint(&mut rng)).collect();
+    let poly = Polynomial::new(coeffs.iter().map(from_bigint).collect::<Vec<Scalar>>());
+    let legacy_poly = Polynomial::new(coeffs);
+    for id in 1..20u64 {
+        let expected = legacy_poly.eval(&BigInt::from(id)).reduce(&modulus());
+        assert_eq!(to_bigint(&poly.eval(&Scalar::from_u64(id))), expected);
+    }
+}
+
+// The scalar split gives the shares of the BigInt split for the same seed, so keys split by
+// deterministic_key_split of older versions stay the same.
+#[test]
+fn test_key_split_against_legacy() {
+    let ids = [1u64, 2, 3, 4, 5, 6, 7];
+    let sk = SecretKey::deserialize(&from_bigint(&BigInt::from(123_456_789u64)).to_bytes_be()).unwrap();
+    for t in [1, 3, 5, 7] {
+        let mut m_threshold = ThresholdSignature::new(t);
+        let kps = m_threshold.deterministic_key_split(&sk, &ids).unwrap();
+        let legacy_kps = legacy::deterministic_key_split(&sk, t, &ids);
+        for id in &ids {
+            assert_eq!(kps[id].sk.serialize().as_bytes(), legacy_kps[id].sk.serialize().as_bytes(), "t {} share {}", t, id);
+            assert_eq!(kps[id].pk, legacy_kps[id].pk);
+            assert_eq!(m_threshold.deterministic_key_share(&sk, *id).pk, legacy_kps[id].pk);
+        }
+    }
+}
+
+// Scalars are drawn from the RNG stream like the uniform BigInt sampling in [0, r) did.
+#[test]
+fn test_random_against_legacy() {
+    for seed in 0..20u8 {
+        let scalars = Scalar::random_vec(&mut StdRng::from_seed([seed; 32]), 10);
+        let legacy = legacy::sample_vec(&mut StdRng::from_seed([seed; 32]), 10, &modulus());
+        assert_eq!(scalars.iter().map(to_bigint).collect::<Vec<BigInt>>(), legacy);
+    }
+}
+
+#[test]
+fn test_lagrange_coeffs() {
+    let r = modulus();
+    let ids = [1u64, 3, 4, 7, 10];
+    let coeffs = lagrange_coeffs_at_zero(&ids);
+    for (i, xi) in ids.iter().enumerate() {
+        let mut numerator = BigInt::from(1);
+        let mut denominator = BigInt::from(1);
+        for xj in ids.iter().filter(|xj| *xj != xi) {
+            numerator *= BigInt::from(*xj);
+            denominator *= BigInt::from(*xj) - BigInt::from(*xi);
+        }
+        let inverse = reduce(denominator).modpow(&(&r - 2), &r);
+        assert_eq!(to_bigint(&coeffs[i]), reduce(numerator * inverse));
+    }
+}
+
+#[test]
+fn test_key_split_reconstruct() {
+    let t = 3;
+    let ids = [1u64, 2, 3, 4];
+    let mut m_threshold = ThresholdSignature::new(t);
+    let (kp, kps) = m_threshold.key_gen(&ids).unwrap();
+    let sk = BigInt::from_bytes_be(Sign::Plus, kp.sk.serialize().as_bytes());
+    for subset in [[1u64, 2, 3], [2, 3, 4], [1, 3, 4]].iter() {
+        let lambdas = lagrange_coeffs_at_zero(subset);
+        let reconstructed = subset.iter().zip(lambdas.iter()).fold(BigInt::from(0), |acc, (id, lambda)| {
+            let share = BigInt::from_bytes_be(Sign::Plus, kps[id].sk.serialize().as_bytes());
+            acc + share * to_bigint(lambda)
+        });
+        assert_eq!(reduce(reconstructed), sk);
+    }
+}